@@ -61,16 +61,22 @@ pub trait TypeMetadata {
 /// Opaque type that holds a bind name.
 pub struct BindName<Db: Backend> {
     inner: Db::BindName,
+    /// Position of the parameter in the bind collector.
+    index: usize,
 }
 
 impl<Db: Backend> BindName<Db> {
-    pub(crate) fn new(name: Db::BindName) -> Self {
-        Self { inner: name }
+    pub(crate) fn new(name: Db::BindName, index: usize) -> Self {
+        Self { inner: name, index }
     }
 
     pub(crate) fn inner(&self) -> &Db::BindName {
         &self.inner
     }
+
+    pub(crate) fn index(&self) -> usize {
+        self.index
+    }
 }
 
 impl<Db: Backend> Copy for BindName<Db> where Db::BindName: Copy + Clone {}
@@ -82,10 +88,12 @@ where
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            index: self.index,
         }
     }
 
     fn clone_from(&mut self, other: &Self) {
         self.inner.clone_from(&other.inner);
+        self.index = other.index;
     }
 }
@@ -1,20 +1,52 @@
-use crate::backend::{Backend, TypeMetadata};
+use crate::backend::{Backend, HasSqlType, TypeMetadata};
 use crate::error::{Error, QueryResult};
-use crate::query::{PreparableQuery, PreparedQuery, Query, QueryBuilder};
+use crate::query::{PreparableQuery, Query, QueryBuilder, QueryCacheKey};
+use crate::types::FromSql;
 use futures_util::future::{Future, LocalBoxFuture};
+use futures_util::stream::{BoxStream, StreamExt, TryStreamExt};
+use std::sync::Arc;
+use std::time::Instant;
 
+mod cache;
+mod instrumentation;
+mod prepared;
+mod retry;
 mod row;
+#[cfg(test)]
+pub(crate) mod test_db;
 mod transaction;
 
+use self::cache::StatementCache;
 #[doc(inline)]
-pub use self::row::{Row, RowStream};
+pub use self::cache::StatementCacheMetrics;
+use self::instrumentation::QuerySpan;
+#[cfg(feature = "tracing")]
+#[doc(inline)]
+pub use self::instrumentation::TracingInstrumentation;
+#[doc(inline)]
+pub use self::instrumentation::{Instrumentation, QueryInfo, TransactionEvent};
+#[doc(inline)]
+pub use self::prepared::{Binds, PreparedStatement};
+#[doc(inline)]
+pub use self::retry::{Backoff, RetryPolicy, RetryableError, RetryingTransaction};
+#[doc(inline)]
+pub use self::row::{
+    get_field, get_named_field, ColumnDescriptor, ColumnTypeMismatch, FromRow, FromRowError, Row,
+    RowStream,
+};
 #[doc(inline)]
 pub use self::transaction::{
     IsolationLevel, NoopTransactionManager, Transaction, TransactionConfig, TransactionManager,
 };
+#[cfg(feature = "derive")]
+#[doc(inline)]
+pub use asphalt_derive::FromRow;
 
 pub type EstablishResult<Conn> = Result<Conn, <Conn as RawConnection>::EstablishError>;
 
+/// Type alias for the rows returned by the connections of a backend.
+pub type RowOf<Db> = <<Db as Backend>::RawConnection as RawConnection>::Row;
+
 /// A low level connection to a backend.
 pub trait RawConnection: Sized + Send + Sync {
     /// The backend of this connection.
@@ -22,7 +54,7 @@ pub trait RawConnection: Sized + Send + Sync {
     /// The transaction manager of this connection.
     type TransactionManager: TransactionManager<Self>;
     /// The type of row returned by the connection.
-    type Row: Row;
+    type Row: Row<Backend = Self::Backend>;
     /// The configuration necessary to establish a connection.
     ///
     /// In many cases, this can be `str`.
@@ -60,11 +92,16 @@ pub trait RawConnection: Sized + Send + Sync {
 /// Most users will prefer to use something more high-level than this, using something like the DSL
 /// provided by `asphalt_dsl`. Some users, though, will find this interface very useful in cases of
 /// high dynamic SQL query where many backends need to be supported.
+///
+/// Queries that are safe to cache (see [`QueryBuilder::is_safe_to_cache`]) are prepared only
+/// once, and the prepared statement is reused in the next executions of the same query.
 pub struct Connection<Db>
 where
     Db: Backend,
 {
     conn: Db::RawConnection,
+    statement_cache: StatementCache<Db>,
+    instrumentation: Option<Arc<dyn Instrumentation>>,
 }
 
 impl<Db> Connection<Db>
@@ -77,7 +114,46 @@ where
     ) -> Result<Self, <Db::RawConnection as RawConnection>::EstablishError> {
         let conn = <Db::RawConnection as RawConnection>::establish(config).await?;
 
-        Ok(Self { conn })
+        Ok(Self {
+            conn,
+            statement_cache: StatementCache::new(self::cache::DEFAULT_CAPACITY),
+            instrumentation: None,
+        })
+    }
+
+    /// Establish a new connection to the backend, reporting its operations to `instrumentation`.
+    ///
+    /// The establishment of the connection is also reported.
+    pub async fn establish_instrumented(
+        config: <Db::RawConnection as RawConnection>::Config,
+        instrumentation: Arc<dyn Instrumentation>,
+    ) -> Result<Self, <Db::RawConnection as RawConnection>::EstablishError> {
+        let started = Instant::now();
+        let res = Self::establish(config).await;
+
+        let result = match &res {
+            Ok(_) => Ok(()),
+            Err(err) => Err(err as &dyn std::error::Error),
+        };
+        instrumentation.on_establish(started.elapsed(), result);
+
+        let mut conn = res?;
+        conn.instrumentation = Some(instrumentation);
+        Ok(conn)
+    }
+
+    /// Report the operations of this connection to `instrumentation`.
+    pub fn set_instrumentation(&mut self, instrumentation: Arc<dyn Instrumentation>) {
+        self.instrumentation = Some(instrumentation);
+    }
+
+    /// Returns the underlying raw connection.
+    ///
+    /// This is useful to implement backend specific functionality. Queries executed
+    /// directly in the raw connection don't use the statement cache, and aren't reported
+    /// to the connection instrumentation.
+    pub fn raw_connection(&self) -> &Db::RawConnection {
+        &self.conn
     }
 
     /// Is this connection in a broken state?
@@ -87,6 +163,24 @@ where
         self.conn.transaction_manager().is_broken()
     }
 
+    /// Sets the maximum number of prepared statements cached by this connection.
+    ///
+    /// The least recently used statements are evicted when the cache is full. A capacity
+    /// of zero disables the cache.
+    pub fn set_statement_cache_capacity(&self, capacity: usize) {
+        self.statement_cache.set_capacity(capacity);
+    }
+
+    /// Removes all the prepared statements cached by this connection.
+    pub fn clear_statement_cache(&self) {
+        self.statement_cache.clear();
+    }
+
+    /// Returns the hit and miss counters of the prepared statement cache.
+    pub fn statement_cache_metrics(&self) -> StatementCacheMetrics {
+        self.statement_cache.metrics()
+    }
+
     /// Create a new [`QueryBuilder`] bound to this connection.
     pub fn query_builder(&self) -> QueryBuilder<'_, 'static, Db> {
         QueryBuilder::new(self.conn.metadata_lookup())
@@ -94,14 +188,21 @@ where
 
     /// Prepares the query stored inside a [`QueryBuilder`], returning the prepared statement and
     /// the bound parameters.
+    ///
+    /// The statement can be executed with the returned parameters, or with new ones created
+    /// by [`PreparedStatement::binds`].
     pub async fn prepare<'c>(
         &'c self,
         query: QueryBuilder<'c, 'static, Db>,
-    ) -> QueryResult<(PreparedQuery<Db>, Db::BindCollector)> {
+    ) -> QueryResult<(PreparedStatement<'c, Db>, Binds<'c, Db>)> {
+        let sql = query.sql().to_string();
         let Query { inner, binds } = query.finish();
         let prepared = inner.prepare(&self.conn).await?;
 
-        Ok((prepared, binds))
+        Ok((
+            PreparedStatement::new(self, prepared, sql),
+            Binds::new(self.conn.metadata_lookup(), binds),
+        ))
     }
 
     /// Executes the query stored inside a [`QueryBuilder`], returning the result set as a stream.
@@ -109,19 +210,149 @@ where
         &'c self,
         query: QueryBuilder<'c, 'static, Db>,
     ) -> QueryResult<RowStream<'c, Db::RawConnection>> {
-        self.conn.query(query.finish()).await
+        let span = self.start_query(&query);
+        let res = QuerySpan::scoped(span.as_ref(), async {
+            let (query, key) = self.cached_query(query).await?;
+            let res = self.conn.query(query).await;
+
+            self.invalidate_if_stale(res, key)
+        })
+        .await;
+
+        match span {
+            Some(span) => span.finish_stream(res),
+            None => res,
+        }
     }
 
     /// Executes the query stored inside a [`QueryBuilder`], returning the number of affected rows.
     pub async fn executes<'c>(&'c self, query: QueryBuilder<'c, 'static, Db>) -> QueryResult<u64> {
-        self.conn.execute(query.finish()).await
+        let span = self.start_query(&query);
+        let res = QuerySpan::scoped(span.as_ref(), async {
+            let (query, key) = self.cached_query(query).await?;
+            let res = self.conn.execute(query).await;
+
+            self.invalidate_if_stale(res, key)
+        })
+        .await;
+
+        match span {
+            Some(span) => span.finish(res),
+            None => res,
+        }
+    }
+
+    /// Executes one or more SQL statements, separated by semicolons, without bind parameters.
+    ///
+    /// This is meant for SQL scripts, like schema migrations, so the statements are never
+    /// prepared nor cached.
+    pub async fn batch_execute(&self, sql: &str) -> QueryResult<()> {
+        let span = self
+            .instrumentation
+            .as_deref()
+            .map(|instrumentation| QuerySpan::start(instrumentation, sql, 0));
+        let res = QuerySpan::scoped(span.as_ref(), self.conn.simple_execute(sql)).await;
+
+        match span {
+            // Affected rows aren't reported for batches.
+            Some(span) => span.finish(res.map(|()| 0)).map(drop),
+            None => res,
+        }
+    }
+
+    /// Executes the query stored inside a [`QueryBuilder`], deserializing the single returned row.
+    ///
+    /// Fails with [`ErrorKind::NotFound`](crate::error::ErrorKind::NotFound) if the query returns
+    /// no rows, and with [`ErrorKind::TooManyRows`](crate::error::ErrorKind::TooManyRows) if it
+    /// returns more than one.
+    pub async fn get_result<'c, T>(&'c self, query: QueryBuilder<'c, 'static, Db>) -> QueryResult<T>
+    where
+        T: for<'r> FromRow<'r, RowOf<Db>>,
+    {
+        let mut rows = self.query(query).await?;
+        let row = rows.try_next().await?.ok_or_else(Error::not_found)?;
+
+        if rows.try_next().await?.is_some() {
+            return Err(Error::too_many_rows());
+        }
+
+        T::from_row(&row)
+    }
+
+    /// Executes the query stored inside a [`QueryBuilder`], deserializing the first returned row,
+    /// if any.
+    pub async fn get_optional<'c, T>(
+        &'c self,
+        query: QueryBuilder<'c, 'static, Db>,
+    ) -> QueryResult<Option<T>>
+    where
+        T: for<'r> FromRow<'r, RowOf<Db>>,
+    {
+        match self.query(query).await?.try_next().await? {
+            Some(row) => T::from_row(&row).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Executes the query stored inside a [`QueryBuilder`], deserializing all the returned rows.
+    pub async fn load<'c, T>(&'c self, query: QueryBuilder<'c, 'static, Db>) -> QueryResult<Vec<T>>
+    where
+        T: for<'r> FromRow<'r, RowOf<Db>>,
+    {
+        let mut rows = self.query(query).await?;
+        let mut values = Vec::new();
+
+        while let Some(row) = rows.try_next().await? {
+            values.push(T::from_row(&row)?);
+        }
+
+        Ok(values)
+    }
+
+    /// Executes the query stored inside a [`QueryBuilder`], returning a stream of the
+    /// deserialized rows.
+    pub async fn load_stream<'c, T>(
+        &'c self,
+        query: QueryBuilder<'c, 'static, Db>,
+    ) -> QueryResult<BoxStream<'c, QueryResult<T>>>
+    where
+        T: for<'r> FromRow<'r, RowOf<Db>> + Send + 'c,
+    {
+        let rows = self.query(query).await?;
+
+        Ok(Box::pin(
+            rows.map(|row| row.and_then(|row| T::from_row(&row))),
+        ))
+    }
+
+    /// Executes the query stored inside a [`QueryBuilder`], deserializing the first column
+    /// of the first returned row as a value of the SQL type `ST`.
+    ///
+    /// Fails with [`ErrorKind::NotFound`](crate::error::ErrorKind::NotFound) if the query returns no rows.
+    pub async fn get_scalar<'c, ST, T>(
+        &'c self,
+        query: QueryBuilder<'c, 'static, Db>,
+    ) -> QueryResult<T>
+    where
+        Db: HasSqlType<ST>,
+        T: for<'r> FromSql<'r, ST, Db>,
+    {
+        let row = self
+            .query(query)
+            .await?
+            .try_next()
+            .await?
+            .ok_or_else(Error::not_found)?;
+
+        row.get_column::<ST, T>(0)
+            .map_err(Error::deserialization_failure)
     }
 
     /// Executes the given future inside of a database transaction.
     ///
     /// If there is already an open transaction, a savepoint will be created instead.
     ///
-    /// If the transaction fails to commit due to a serialization failure, a rollback
+    /// If the transaction fails to commit, e.g. due to a serialization failure, a rollback
     /// will be attempted as is expected. When the rollback succeeds, the original error
     /// will be returned, otherwise, the rollback error will be returned instead. In
     /// the second case, the connection should be considered broken as it contains an
@@ -131,12 +362,85 @@ where
     ///
     /// If the received future panics, the future returned by this function will try
     /// to rollback the transaction before resuming the panic.
+    ///
+    /// # Cancellation
+    ///
+    /// If the returned future is dropped before completion, the connection is marked as
+    /// broken, as the transaction can't be rolled back.
     pub fn transaction<F, T, E>(&self, fut: F) -> Transaction<'_, Db::RawConnection, F>
     where
-        F: Future<Output = Result<T, E>> + Send,
-        T: Send,
-        E: Send + From<Error>,
+        F: Future<Output = Result<T, E>>,
+        E: From<Error>,
+    {
+        Transaction::new(&self.conn, fut).instrumented(self.instrumentation.as_deref())
+    }
+
+    /// Executes the future returned by `make_future` inside of a database transaction,
+    /// retrying the whole transaction when it fails due to a transient error.
+    ///
+    /// Transient errors are serialization failures and deadlocks (see [`RetryableError`]).
+    /// Before each attempt a fresh future is created by calling `make_future` again, so
+    /// it shouldn't carry over state from previous attempts. The number of attempts and
+    /// the delay between them is controlled by the [`RetryPolicy`] of the transaction.
+    ///
+    /// Retrying only makes sense for top-level transactions: when used inside an open
+    /// transaction, a savepoint is created instead, but a serialization failure aborts
+    /// the outer transaction, so all the retries will fail.
+    pub fn retrying_transaction<G, F, T, E>(
+        &self,
+        make_future: G,
+    ) -> RetryingTransaction<'_, Db::RawConnection, G, T, E>
+    where
+        G: FnMut() -> F,
+        F: Future<Output = Result<T, E>>,
+        E: RetryableError,
     {
-        Transaction::new(&self.conn, fut)
+        RetryingTransaction::new(&self.conn, make_future)
+            .instrumented(self.instrumentation.as_deref())
+    }
+
+    /// Report the start of the query, if the connection is instrumented.
+    fn start_query<'c>(&'c self, query: &QueryBuilder<'c, 'static, Db>) -> Option<QuerySpan<'c>> {
+        let instrumentation = self.instrumentation.as_deref()?;
+
+        Some(QuerySpan::start(
+            instrumentation,
+            query.sql(),
+            query.bind_count(),
+        ))
+    }
+
+    /// Finish the query, replacing it with a cached prepared statement when possible.
+    async fn cached_query<'c>(
+        &'c self,
+        query: QueryBuilder<'c, 'static, Db>,
+    ) -> QueryResult<(Query<Db>, Option<QueryCacheKey<Db>>)> {
+        let safe_to_cache = query.is_safe_to_cache();
+        let Query { inner, binds } = query.finish();
+
+        let (inner, key) = if safe_to_cache {
+            self.statement_cache.prepare(inner, &self.conn).await?
+        } else {
+            (inner, None)
+        };
+
+        Ok((Query { inner, binds }, key))
+    }
+
+    /// Drop the cached prepared statement if the backend reported that it is stale.
+    ///
+    /// The next execution of the query will prepare it again.
+    fn invalidate_if_stale<T>(
+        &self,
+        res: QueryResult<T>,
+        key: Option<QueryCacheKey<Db>>,
+    ) -> QueryResult<T> {
+        if let (Err(err), Some(key)) = (&res, key) {
+            if err.kind().is_stale_prepared_statement() {
+                self.statement_cache.invalidate(&key);
+            }
+        }
+
+        res
     }
 }
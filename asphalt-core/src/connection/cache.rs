@@ -0,0 +1,286 @@
+use crate::backend::Backend;
+use crate::error::QueryResult;
+use crate::query::{PreparableQuery, PreparedQuery, QueryCacheKey};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Default number of prepared statements kept by a connection.
+pub(crate) const DEFAULT_CAPACITY: usize = 100;
+
+/// Hit and miss counters of a prepared statement cache.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct StatementCacheMetrics {
+    /// Number of queries that reused a cached prepared statement.
+    pub hits: u64,
+    /// Number of queries that had to be prepared.
+    pub misses: u64,
+    /// Number of prepared statements evicted to make room for new ones.
+    pub evictions: u64,
+    /// Number of prepared statements invalidated by the backend.
+    pub invalidations: u64,
+}
+
+/// A least recently used cache of prepared statements.
+///
+/// Only queries marked as safe to cache by the [`QueryBuilder`](crate::query::QueryBuilder)
+/// should be stored here, otherwise the cache may thrash.
+pub(crate) struct StatementCache<Db: Backend> {
+    inner: Mutex<CacheInner<Db>>,
+}
+
+struct CacheInner<Db: Backend> {
+    capacity: usize,
+    statements: HashMap<QueryCacheKey<Db>, CachedStatement<Db>>,
+    /// Monotonic counter used to track the recency of each statement.
+    clock: u64,
+    metrics: StatementCacheMetrics,
+}
+
+struct CachedStatement<Db: Backend> {
+    statement: PreparedQuery<Db>,
+    last_used: u64,
+}
+
+impl<Db: Backend> StatementCache<Db> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(CacheInner {
+                capacity,
+                statements: HashMap::with_capacity(capacity),
+                clock: 0,
+                metrics: StatementCacheMetrics::default(),
+            }),
+        }
+    }
+
+    /// Returns a prepared version of `query`, reusing a cached statement if possible.
+    ///
+    /// The returned key should be used to invalidate the statement if the backend
+    /// reports that it is stale.
+    pub(crate) async fn prepare(
+        &self,
+        query: Db::Query,
+        conn: &Db::RawConnection,
+    ) -> QueryResult<(Db::Query, Option<QueryCacheKey<Db>>)> {
+        let key = match query.cache_key() {
+            Some(key) => key,
+            None => return Ok((query, None)),
+        };
+
+        if let Some(statement) = self.lock().get(&key) {
+            return Ok((Db::Query::from_prepared(statement), Some(key)));
+        }
+
+        let statement = query.prepare(conn).await?;
+        self.lock().insert(key.clone(), statement.clone());
+
+        Ok((Db::Query::from_prepared(statement), Some(key)))
+    }
+
+    /// Removes the statement with the given key from the cache.
+    pub(crate) fn invalidate(&self, key: &QueryCacheKey<Db>) {
+        let mut inner = self.lock();
+        if inner.statements.remove(key).is_some() {
+            inner.metrics.invalidations += 1;
+        }
+    }
+
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        let mut inner = self.lock();
+        inner.capacity = capacity;
+
+        while inner.statements.len() > capacity {
+            inner.evict();
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        self.lock().statements.clear();
+    }
+
+    pub(crate) fn metrics(&self) -> StatementCacheMetrics {
+        self.lock().metrics
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheInner<Db>> {
+        // The cache is always left in a consistent state, so poisoning can be ignored.
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<Db: Backend> CacheInner<Db> {
+    fn get(&mut self, key: &QueryCacheKey<Db>) -> Option<PreparedQuery<Db>> {
+        self.clock += 1;
+        let clock = self.clock;
+
+        match self.statements.get_mut(key) {
+            Some(cached) => {
+                cached.last_used = clock;
+                self.metrics.hits += 1;
+                Some(cached.statement.clone())
+            }
+            None => {
+                self.metrics.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, key: QueryCacheKey<Db>, statement: PreparedQuery<Db>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if !self.statements.contains_key(&key) && self.statements.len() >= self.capacity {
+            self.evict();
+        }
+
+        self.clock += 1;
+        self.statements.insert(
+            key,
+            CachedStatement {
+                statement,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    /// Evicts the least recently used statement.
+    ///
+    /// This does a linear scan over the cache, which is fine for the small capacities
+    /// used in practice and is much cheaper than preparing a statement.
+    fn evict(&mut self) {
+        let oldest = self
+            .statements
+            .iter()
+            .min_by_key(|(_, cached)| cached.last_used)
+            .map(|(key, _)| key.clone());
+
+        if let Some(key) = oldest {
+            self.statements.remove(&key);
+            self.metrics.evictions += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connection::test_db::{connection, TestDb};
+    use crate::connection::{Connection, StatementCacheMetrics};
+    use crate::error::QueryResult;
+    use futures_util::FutureExt;
+
+    fn execute(conn: &Connection<TestDb>, sql: &str) -> QueryResult<u64> {
+        let mut query = conn.query_builder();
+        query.push_sql(sql);
+
+        conn.executes(query).now_or_never().unwrap()
+    }
+
+    fn prepared(conn: &Connection<TestDb>) -> Vec<String> {
+        conn.conn.prepared.lock().unwrap().clone()
+    }
+
+    fn metrics(
+        hits: u64,
+        misses: u64,
+        evictions: u64,
+        invalidations: u64,
+    ) -> StatementCacheMetrics {
+        StatementCacheMetrics {
+            hits,
+            misses,
+            evictions,
+            invalidations,
+        }
+    }
+
+    #[test]
+    fn reuses_cached_statements() {
+        let conn = connection(&[]);
+
+        execute(&conn, "SELECT 1").unwrap();
+        execute(&conn, "SELECT 1").unwrap();
+
+        assert_eq!(prepared(&conn), ["SELECT 1"]);
+        assert_eq!(conn.statement_cache_metrics(), metrics(1, 1, 0, 0));
+    }
+
+    #[test]
+    fn doesnt_cache_unsafe_queries() {
+        let conn = connection(&[]);
+
+        let mut query = conn.query_builder();
+        query.push_sql("SELECT 1");
+        query.unsafe_to_cache();
+        conn.executes(query).now_or_never().unwrap().unwrap();
+
+        assert!(prepared(&conn).is_empty());
+        assert_eq!(conn.statement_cache_metrics(), metrics(0, 0, 0, 0));
+    }
+
+    #[test]
+    fn evicts_least_recently_used_statements() {
+        let conn = connection(&[]);
+        conn.set_statement_cache_capacity(2);
+
+        for sql in &[
+            "SELECT 1", "SELECT 2", "SELECT 1", "SELECT 3", "SELECT 2", "SELECT 3",
+        ] {
+            execute(&conn, sql).unwrap();
+        }
+
+        // `SELECT 2` is evicted by `SELECT 3`, then `SELECT 1` by `SELECT 2`.
+        assert_eq!(
+            prepared(&conn),
+            ["SELECT 1", "SELECT 2", "SELECT 3", "SELECT 2"]
+        );
+        assert_eq!(conn.statement_cache_metrics(), metrics(2, 4, 2, 0));
+    }
+
+    #[test]
+    fn shrinking_the_capacity_evicts_statements() {
+        let conn = connection(&[]);
+
+        execute(&conn, "SELECT 1").unwrap();
+        execute(&conn, "SELECT 2").unwrap();
+        conn.set_statement_cache_capacity(1);
+
+        execute(&conn, "SELECT 2").unwrap();
+        execute(&conn, "SELECT 1").unwrap();
+
+        assert_eq!(prepared(&conn), ["SELECT 1", "SELECT 2", "SELECT 1"]);
+        assert_eq!(conn.statement_cache_metrics(), metrics(1, 3, 2, 0));
+    }
+
+    #[test]
+    fn zero_capacity_disables_the_cache() {
+        let conn = connection(&[]);
+        conn.set_statement_cache_capacity(0);
+
+        execute(&conn, "SELECT 1").unwrap();
+        execute(&conn, "SELECT 1").unwrap();
+
+        assert_eq!(prepared(&conn), ["SELECT 1", "SELECT 1"]);
+        assert_eq!(conn.statement_cache_metrics(), metrics(0, 2, 0, 0));
+    }
+
+    #[test]
+    fn invalidates_stale_statements() {
+        let conn = connection(&[]);
+
+        execute(&conn, "SELECT 1").unwrap();
+        conn.conn.stale.lock().unwrap().push("SELECT 1");
+
+        let err = execute(&conn, "SELECT 1").unwrap_err();
+        assert!(err.kind().is_stale_prepared_statement());
+        assert_eq!(conn.statement_cache_metrics(), metrics(1, 1, 0, 1));
+
+        // The next execution prepares the statement again.
+        conn.conn.stale.lock().unwrap().clear();
+        execute(&conn, "SELECT 1").unwrap();
+
+        assert_eq!(prepared(&conn), ["SELECT 1", "SELECT 1"]);
+        assert_eq!(conn.statement_cache_metrics(), metrics(1, 2, 0, 1));
+    }
+}
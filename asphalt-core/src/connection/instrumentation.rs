@@ -0,0 +1,231 @@
+use crate::error::{Error, QueryResult};
+use futures_core::stream::{BoxStream, Stream};
+use std::error::Error as StdError;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tracing")]
+mod tracing;
+
+#[cfg(feature = "tracing")]
+#[doc(inline)]
+pub use self::tracing::TracingInstrumentation;
+
+/// Observes the operations executed by a [`Connection`](super::Connection).
+///
+/// All the callbacks are called synchronously while the operation is executed, so
+/// implementations should return quickly. Every callback does nothing by default.
+pub trait Instrumentation: Send + Sync {
+    /// Called after an attempt to establish the connection.
+    fn on_establish(&self, _duration: Duration, _result: Result<(), &dyn StdError>) {}
+
+    /// Called before a query is prepared and executed.
+    fn on_query_start(&self, _query: &QueryInfo<'_>) {}
+
+    /// Called every time the query is polled, with `poll` doing the polling, while it is
+    /// executed or its result set is consumed.
+    ///
+    /// This allows implementations to set up a context for the execution of the query,
+    /// e.g. entering a [`tracing`] span. Implementations must call `poll` exactly once,
+    /// which is what the default implementation does.
+    ///
+    /// [`tracing`]: https://docs.rs/tracing
+    fn in_query_scope(&self, _query: &QueryInfo<'_>, poll: &mut dyn FnMut()) {
+        poll()
+    }
+
+    /// Called when a query finishes.
+    ///
+    /// The result contains the number of affected rows for executed queries, or the
+    /// number of returned rows for queries returning a result set. In the latter case,
+    /// the query finishes only after the result set is consumed or dropped.
+    fn on_query_finish(
+        &self,
+        _query: &QueryInfo<'_>,
+        _duration: Duration,
+        _result: Result<u64, &Error>,
+    ) {
+    }
+
+    /// Called after a transaction is begun, committed or rolled back.
+    ///
+    /// Nested transactions, i.e. savepoints, are reported as any other transaction.
+    fn on_transaction(&self, _event: TransactionEvent, _result: Result<(), &Error>) {}
+}
+
+/// Information about a query being executed.
+#[derive(Debug, Copy, Clone)]
+pub struct QueryInfo<'a> {
+    /// Unique identifier of the query execution.
+    ///
+    /// Useful to correlate the start and finish callbacks of the same query.
+    pub id: u64,
+    /// The SQL of the query.
+    pub sql: &'a str,
+    /// Number of parameters bound to the query.
+    pub bind_count: usize,
+}
+
+/// A transaction operation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TransactionEvent {
+    Begin,
+    Commit,
+    Rollback,
+}
+
+static NEXT_QUERY_ID: AtomicU64 = AtomicU64::new(0);
+
+/// An instrumented query execution.
+///
+/// Reports the start of the query when created, and its finish when one of the
+/// `finish` methods is called.
+pub(crate) struct QuerySpan<'c> {
+    instrumentation: &'c dyn Instrumentation,
+    id: u64,
+    sql: String,
+    bind_count: usize,
+    started: Instant,
+}
+
+impl<'c> QuerySpan<'c> {
+    pub(crate) fn start(
+        instrumentation: &'c dyn Instrumentation,
+        sql: &str,
+        bind_count: usize,
+    ) -> Self {
+        let span = Self {
+            instrumentation,
+            id: NEXT_QUERY_ID.fetch_add(1, Ordering::Relaxed),
+            sql: sql.to_string(),
+            bind_count,
+            started: Instant::now(),
+        };
+
+        span.instrumentation.on_query_start(&span.info());
+        span
+    }
+
+    fn info(&self) -> QueryInfo<'_> {
+        QueryInfo {
+            id: self.id,
+            sql: &self.sql,
+            bind_count: self.bind_count,
+        }
+    }
+
+    /// Polls `fut` inside the scope of the query, if there is one.
+    pub(crate) fn scoped<'s, F: Future>(span: Option<&'s Self>, fut: F) -> Scoped<'s, 'c, F> {
+        Scoped { span, inner: fut }
+    }
+
+    /// Runs `f` inside the scope of the query.
+    fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        let mut f = Some(f);
+        let mut output = None;
+        self.instrumentation
+            .in_query_scope(&self.info(), &mut || output = f.take().map(|f| f()));
+
+        // Don't lose the poll if the instrumentation didn't call it.
+        output.unwrap_or_else(|| (f.take().unwrap())())
+    }
+
+    fn report(&self, result: Result<u64, &Error>) {
+        self.instrumentation
+            .on_query_finish(&self.info(), self.started.elapsed(), result);
+    }
+
+    /// Finish an executed query.
+    pub(crate) fn finish(self, res: QueryResult<u64>) -> QueryResult<u64> {
+        self.report(res.as_ref().map(|affected| *affected));
+        res
+    }
+
+    /// Finish a query returning a result set, which is reported only after the
+    /// returned stream is exhausted or dropped.
+    pub(crate) fn finish_stream<R: 'c>(
+        self,
+        res: QueryResult<BoxStream<'c, QueryResult<R>>>,
+    ) -> QueryResult<BoxStream<'c, QueryResult<R>>> {
+        match res {
+            Ok(stream) => Ok(Box::pin(InstrumentedStream {
+                inner: stream,
+                span: Some(self),
+                rows: 0,
+            })),
+            Err(err) => {
+                self.report(Err(&err));
+                Err(err)
+            }
+        }
+    }
+}
+
+/// A future polled inside the scope of its query.
+#[pin_project]
+pub(crate) struct Scoped<'s, 'c, F> {
+    span: Option<&'s QuerySpan<'c>>,
+    #[pin]
+    inner: F,
+}
+
+impl<F: Future> Future for Scoped<'_, '_, F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.project();
+        let inner = me.inner;
+
+        match me.span {
+            Some(span) => span.in_scope(|| inner.poll(cx)),
+            None => inner.poll(cx),
+        }
+    }
+}
+
+/// A result set which reports its query as finished when exhausted or dropped.
+struct InstrumentedStream<'c, R> {
+    inner: BoxStream<'c, QueryResult<R>>,
+    span: Option<QuerySpan<'c>>,
+    rows: u64,
+}
+
+impl<R> Stream for InstrumentedStream<'_, R> {
+    type Item = QueryResult<R>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let me = &mut *self;
+        let inner = &mut me.inner;
+        let item = ready!(match &me.span {
+            Some(span) => span.in_scope(|| inner.as_mut().poll_next(cx)),
+            None => inner.as_mut().poll_next(cx),
+        });
+
+        match &item {
+            Some(Ok(_)) => self.rows += 1,
+            Some(Err(err)) => {
+                if let Some(span) = self.span.take() {
+                    span.report(Err(err));
+                }
+            }
+            None => {
+                if let Some(span) = self.span.take() {
+                    span.report(Ok(self.rows));
+                }
+            }
+        }
+
+        Poll::Ready(item)
+    }
+}
+
+impl<R> Drop for InstrumentedStream<'_, R> {
+    fn drop(&mut self) {
+        if let Some(span) = self.span.take() {
+            span.report(Ok(self.rows));
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use super::{Instrumentation, QueryInfo, TransactionEvent};
+use crate::error::Error;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::field::{display, Empty};
+use tracing::Span;
+
+/// An [`Instrumentation`] that reports operations using [`tracing`].
+///
+/// Each query is recorded as a `db.query` span, with attributes following the
+/// OpenTelemetry semantic conventions for database clients. Connection establishment
+/// and transaction operations are reported as events.
+///
+/// The spans are children of the span active when the query was started, and are
+/// entered while the query is executed and its result set consumed.
+pub struct TracingInstrumentation {
+    system: &'static str,
+    spans: Mutex<HashMap<u64, Span>>,
+}
+
+impl TracingInstrumentation {
+    /// Creates a new instrumentation for a database of the given system.
+    ///
+    /// `system` is reported as the `db.system` attribute, and should be one of the
+    /// values defined by OpenTelemetry, e.g. `postgresql`, `mysql` or `sqlite`.
+    pub fn new(system: &'static str) -> Self {
+        Self {
+            system,
+            spans: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn spans(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Span>> {
+        // The map is always left in a consistent state, so poisoning can be ignored.
+        self.spans.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Instrumentation for TracingInstrumentation {
+    fn on_establish(&self, duration: Duration, result: Result<(), &dyn StdError>) {
+        let duration_ms = duration.as_millis() as u64;
+
+        match result {
+            Ok(()) => tracing::debug!(
+                db.system = self.system,
+                duration_ms,
+                "database connection established"
+            ),
+            Err(err) => tracing::error!(
+                db.system = self.system,
+                duration_ms,
+                error.message = %err,
+                "failed to establish database connection"
+            ),
+        }
+    }
+
+    fn on_query_start(&self, query: &QueryInfo<'_>) {
+        let operation = operation(query.sql);
+        let span = tracing::info_span!(
+            "db.query",
+            otel.name = %operation,
+            otel.kind = "client",
+            otel.status_code = Empty,
+            db.system = self.system,
+            db.statement = query.sql,
+            db.operation = %operation,
+            db.bind_count = query.bind_count as u64,
+            db.rows = Empty,
+            error.message = Empty,
+        );
+
+        self.spans().insert(query.id, span);
+    }
+
+    fn in_query_scope(&self, query: &QueryInfo<'_>, poll: &mut dyn FnMut()) {
+        // Don't hold the lock while polling, the query may start other queries.
+        let span = self.spans().get(&query.id).cloned();
+
+        match span {
+            Some(span) => span.in_scope(poll),
+            None => poll(),
+        }
+    }
+
+    fn on_query_finish(
+        &self,
+        query: &QueryInfo<'_>,
+        _duration: Duration,
+        result: Result<u64, &Error>,
+    ) {
+        // The span is closed when dropped here.
+        let span = match self.spans().remove(&query.id) {
+            Some(span) => span,
+            None => return,
+        };
+
+        match result {
+            Ok(rows) => {
+                span.record("db.rows", &rows);
+                span.record("otel.status_code", &"OK");
+            }
+            Err(err) => {
+                span.record("otel.status_code", &"ERROR");
+                span.record("error.message", &display(err));
+            }
+        }
+    }
+
+    fn on_transaction(&self, event: TransactionEvent, result: Result<(), &Error>) {
+        let operation = match event {
+            TransactionEvent::Begin => "BEGIN",
+            TransactionEvent::Commit => "COMMIT",
+            TransactionEvent::Rollback => "ROLLBACK",
+        };
+
+        match result {
+            Ok(()) => tracing::debug!(
+                db.system = self.system,
+                db.operation = operation,
+                "transaction"
+            ),
+            Err(err) => tracing::error!(
+                db.system = self.system,
+                db.operation = operation,
+                error.message = %err,
+                "transaction failed"
+            ),
+        }
+    }
+}
+
+/// Returns the SQL keyword identifying the operation of the query, e.g. `SELECT`.
+fn operation(sql: &str) -> String {
+    sql.split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::test_db::{connection, TestDb};
+    use crate::connection::Connection;
+    use futures_util::{FutureExt, TryStreamExt};
+    use std::sync::Arc;
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// A subscriber recording the names of the entered spans.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        names: Arc<Mutex<Vec<&'static str>>>,
+        entered: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut names = self.names.lock().unwrap();
+            names.push(span.metadata().name());
+            Id::from_u64(names.len() as u64)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, span: &Id) {
+            let name = self.names.lock().unwrap()[span.into_u64() as usize - 1];
+            self.entered.lock().unwrap().push(name);
+        }
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    fn instrumented() -> Connection<TestDb> {
+        let mut conn = connection(&[]);
+        conn.set_instrumentation(Arc::new(TracingInstrumentation::new("test")));
+        conn
+    }
+
+    fn entered(recorder: &Recorder) -> Vec<&'static str> {
+        recorder.entered.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn enters_the_span_while_executing_queries() {
+        let recorder = Recorder::default();
+        let conn = instrumented();
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut query = conn.query_builder();
+            query.push_sql("UPDATE users SET name = NULL");
+            conn.executes(query).now_or_never().unwrap().unwrap();
+        });
+
+        assert_eq!(entered(&recorder), ["db.query"]);
+    }
+
+    #[test]
+    fn enters_the_span_while_consuming_result_sets() {
+        let recorder = Recorder::default();
+        let conn = instrumented();
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut query = conn.query_builder();
+            query.push_sql("SELECT * FROM users");
+            let rows = conn.query(query).now_or_never().unwrap().unwrap();
+            rows.try_collect::<Vec<_>>()
+                .now_or_never()
+                .unwrap()
+                .unwrap();
+        });
+
+        // Entered while executing the query, then while consuming its result set.
+        assert_eq!(entered(&recorder), ["db.query", "db.query"]);
+    }
+}
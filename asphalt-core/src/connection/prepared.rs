@@ -0,0 +1,152 @@
+use super::{ColumnDescriptor, Connection, QuerySpan, RawConnection, RowStream};
+use crate::backend::{Backend, HasSqlType};
+use crate::error::QueryResult;
+use crate::query::{BindCollector, DescribePrepared, PreparableQuery, PreparedQuery, Query};
+use crate::types::ToSql;
+
+/// A statement prepared by [`Connection::prepare`].
+///
+/// The statement can be executed many times, each time with a new set of bind
+/// parameters created by [`PreparedStatement::binds`]. Prepared statements aren't
+/// stored in the connection's statement cache, and some backends only free them when
+/// the connection is closed, so statements that aren't needed anymore should be
+/// freed with [`PreparedStatement::deallocate`].
+pub struct PreparedStatement<'c, Db: Backend> {
+    conn: &'c Connection<Db>,
+    statement: PreparedQuery<Db>,
+    sql: String,
+}
+
+impl<'c, Db: Backend> PreparedStatement<'c, Db> {
+    pub(super) fn new(conn: &'c Connection<Db>, statement: PreparedQuery<Db>, sql: String) -> Self {
+        Self {
+            conn,
+            statement,
+            sql,
+        }
+    }
+
+    /// Returns the SQL of the statement.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Returns the statement prepared by the backend.
+    pub fn statement(&self) -> &PreparedQuery<Db> {
+        &self.statement
+    }
+
+    /// Returns the types of the parameters of the statement, in bind order.
+    pub fn param_types(&self) -> Vec<Db::TypeMetadata>
+    where
+        PreparedQuery<Db>: DescribePrepared<Db>,
+    {
+        self.statement.param_types()
+    }
+
+    /// Returns the columns of the result set of the statement.
+    pub fn columns(&self) -> Vec<ColumnDescriptor<'_, Db>>
+    where
+        PreparedQuery<Db>: DescribePrepared<Db>,
+    {
+        self.statement.columns()
+    }
+
+    /// Creates an empty set of bind parameters for an execution of the statement.
+    pub fn binds(&self) -> Binds<'c, Db> {
+        Binds::new(self.conn.conn.metadata_lookup(), Default::default())
+    }
+
+    /// Executes the statement with the given parameters, returning the number of affected rows.
+    pub async fn execute(&self, binds: Binds<'c, Db>) -> QueryResult<u64> {
+        let span = self.start_query(&binds);
+        let res = QuerySpan::scoped(
+            span.as_ref(),
+            self.conn.conn.execute(self.query_with(binds)),
+        )
+        .await;
+
+        match span {
+            Some(span) => span.finish(res),
+            None => res,
+        }
+    }
+
+    /// Executes the statement with the given parameters, returning the result set as a stream.
+    pub async fn query(
+        &self,
+        binds: Binds<'c, Db>,
+    ) -> QueryResult<RowStream<'c, Db::RawConnection>> {
+        let conn: &'c Connection<Db> = self.conn;
+        let span = self.start_query(&binds);
+        let res = QuerySpan::scoped(span.as_ref(), conn.conn.query(self.query_with(binds))).await;
+
+        match span {
+            Some(span) => span.finish_stream(res),
+            None => res,
+        }
+    }
+
+    /// Frees the statement in the backend.
+    pub async fn deallocate(self) -> QueryResult<()> {
+        Db::Query::deallocate(self.statement, &self.conn.conn).await
+    }
+
+    fn query_with(&self, binds: Binds<'c, Db>) -> Query<Db> {
+        Query {
+            inner: Db::Query::from_prepared(self.statement.clone()),
+            binds: binds.collector,
+        }
+    }
+
+    /// Report the start of an execution, if the connection is instrumented.
+    fn start_query(&self, binds: &Binds<'c, Db>) -> Option<QuerySpan<'c>> {
+        let conn: &'c Connection<Db> = self.conn;
+        let instrumentation = conn.instrumentation.as_deref()?;
+
+        Some(QuerySpan::start(
+            instrumentation,
+            &self.sql,
+            binds.bind_count(),
+        ))
+    }
+}
+
+/// The bind parameters of an execution of a [`PreparedStatement`].
+///
+/// The parameters must be bound in the same order, and with the same SQL types, as
+/// the ones bound when the statement was prepared.
+pub struct Binds<'c, Db: Backend> {
+    metadata_lookup: &'c Db::MetadataLookup,
+    collector: Db::BindCollector,
+}
+
+impl<'c, Db: Backend> Binds<'c, Db> {
+    pub(super) fn new(
+        metadata_lookup: &'c Db::MetadataLookup,
+        collector: Db::BindCollector,
+    ) -> Self {
+        Self {
+            metadata_lookup,
+            collector,
+        }
+    }
+
+    /// Bind the next parameter of the statement.
+    pub async fn bind<ST, RT>(&mut self, bind: &RT) -> QueryResult<()>
+    where
+        Db: HasSqlType<ST>,
+        RT: ToSql<ST, Db> + ?Sized,
+    {
+        self.collector
+            .push_bound_value::<ST, _>(bind, self.metadata_lookup)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the number of parameters bound until now.
+    pub fn bind_count(&self) -> usize {
+        self.collector.bind_count()
+    }
+}
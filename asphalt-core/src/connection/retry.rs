@@ -0,0 +1,331 @@
+use super::{Instrumentation, RawConnection, Transaction, TransactionConfig};
+use crate::connection::IsolationLevel;
+use crate::error::Error;
+use crate::extensions::{IsolationLevel as IsoLvl, ReadOnly, Supports};
+use futures_util::future::LocalBoxFuture;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Errors returned by transactions executed with
+/// [`Connection::retrying_transaction`](super::Connection::retrying_transaction).
+///
+/// Implementations are responsible to tell whether the error is a transient failure of
+/// the transaction, in which case the transaction is retried. User defined error types
+/// wrapping an [`Error`] will usually delegate to its implementation.
+pub trait RetryableError: From<Error> {
+    /// Should the failed transaction be retried?
+    fn should_retry(&self) -> bool;
+}
+
+/// Serialization failures and deadlocks are retried.
+impl RetryableError for Error {
+    fn should_retry(&self) -> bool {
+        self.kind().is_serialization_failure() || self.kind().is_deadlock()
+    }
+}
+
+/// How long to wait before retrying a failed transaction.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Backoff {
+    /// Retry immediately.
+    Immediate,
+    /// Wait a fixed amount of time before each retry.
+    Constant(Duration),
+    /// Wait `initial` before the first retry, doubling the delay for each following
+    /// retry, up to `max`.
+    Exponential {
+        /// Delay before the first retry.
+        initial: Duration,
+        /// Maximum delay between retries.
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// Returns how long to wait before the `retry`-th retry, starting from 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        match *self {
+            Self::Immediate => Duration::from_secs(0),
+            Self::Constant(delay) => delay,
+            Self::Exponential { initial, max } => {
+                let factor = 1u32
+                    .checked_shl(retry.saturating_sub(1))
+                    .unwrap_or(u32::MAX);
+                initial
+                    .checked_mul(factor)
+                    .map_or(max, |delay| delay.min(max))
+            }
+        }
+    }
+}
+
+/// The retry policy of a [`RetryingTransaction`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of times the transaction is executed, including the first attempt.
+    pub max_attempts: u32,
+    /// How long to wait between attempts.
+    pub backoff: Backoff,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Backoff::Exponential {
+                initial: Duration::from_millis(10),
+                max: Duration::from_secs(1),
+            },
+        }
+    }
+}
+
+/// A future which executes a transaction, retrying it on transient failures.
+///
+/// See [`Connection::retrying_transaction`](super::Connection::retrying_transaction).
+#[pin_project]
+pub struct RetryingTransaction<'c, Conn, G, T, E> {
+    conn: &'c Conn,
+    instrumentation: Option<&'c dyn Instrumentation>,
+    config: TransactionConfig,
+    policy: RetryPolicy,
+    make_future: Option<G>,
+    running: Option<LocalBoxFuture<'c, Result<T, E>>>,
+}
+
+impl<'c, Conn, G, T, E> RetryingTransaction<'c, Conn, G, T, E>
+where
+    Conn: RawConnection,
+{
+    pub(super) fn new(conn: &'c Conn, make_future: G) -> Self {
+        Self {
+            conn,
+            instrumentation: None,
+            config: TransactionConfig::default(),
+            policy: RetryPolicy::default(),
+            make_future: Some(make_future),
+            running: None,
+        }
+    }
+
+    pub(super) fn instrumented(mut self, instrumentation: Option<&'c dyn Instrumentation>) -> Self {
+        self.instrumentation = instrumentation;
+        self
+    }
+
+    /// Sets the isolation level of the transaction.
+    pub fn isolation_level(mut self, level: IsolationLevel) -> Self
+    where
+        Conn::Backend: Supports<IsoLvl>,
+    {
+        self.config.isolation = Some(level);
+        self
+    }
+
+    /// Sets the access mode of the transaction.
+    pub fn read_only(mut self) -> Self
+    where
+        Conn::Backend: Supports<ReadOnly>,
+    {
+        self.config.read_only = Some(true);
+        self
+    }
+
+    /// Sets the retry policy of the transaction.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the maximum number of times the transaction is executed.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.policy.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets how long to wait between attempts.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.policy.backoff = backoff;
+        self
+    }
+}
+
+impl<'c, Conn, G, F, T, E> Future for RetryingTransaction<'c, Conn, G, T, E>
+where
+    Conn: RawConnection,
+    G: FnMut() -> F + 'c,
+    F: Future<Output = Result<T, E>> + 'c,
+    T: 'c,
+    E: RetryableError + 'c,
+{
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.project();
+
+        if me.running.is_none() {
+            let make_future = me
+                .make_future
+                .take()
+                .expect("Polled a finished RetryingTransaction!");
+
+            *me.running = Some(Box::pin(retry(
+                *me.conn,
+                *me.instrumentation,
+                *me.config,
+                *me.policy,
+                make_future,
+            )));
+        }
+
+        me.running.as_mut().unwrap().as_mut().poll(cx)
+    }
+}
+
+async fn retry<Conn, G, F, T, E>(
+    conn: &Conn,
+    instrumentation: Option<&dyn Instrumentation>,
+    config: TransactionConfig,
+    policy: RetryPolicy,
+    mut make_future: G,
+) -> Result<T, E>
+where
+    Conn: RawConnection,
+    G: FnMut() -> F,
+    F: Future<Output = Result<T, E>>,
+    E: RetryableError,
+{
+    let mut attempt = 1;
+
+    loop {
+        let transaction =
+            Transaction::with_config(conn, make_future(), config).instrumented(instrumentation);
+
+        match transaction.await {
+            Err(err) if attempt < policy.max_attempts && err.should_retry() => {
+                let delay = policy.backoff.delay(attempt);
+                if delay > Duration::from_secs(0) {
+                    futures_timer::Delay::new(delay).await;
+                }
+
+                attempt += 1;
+            }
+            res => return res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::test_db::{connection, statements};
+    use crate::error::{DatabaseErrorKind, QueryResult};
+    use futures_util::FutureExt;
+    use std::cell::Cell;
+
+    fn error(kind: DatabaseErrorKind) -> Error {
+        Error::database_error(kind, format!("{:?}", kind))
+    }
+
+    /// Runs a retrying transaction failing with `kind` in the first `failures` attempts.
+    fn run(failures: u32, kind: DatabaseErrorKind, max_attempts: u32) -> (QueryResult<u32>, u32) {
+        let conn = connection(&[]);
+        let attempts = Cell::new(0);
+
+        let res = conn
+            .retrying_transaction(|| async {
+                attempts.set(attempts.get() + 1);
+                if attempts.get() <= failures {
+                    Err(error(kind))
+                } else {
+                    Ok(attempts.get())
+                }
+            })
+            .max_attempts(max_attempts)
+            .backoff(Backoff::Immediate)
+            .now_or_never()
+            .unwrap();
+
+        (res, attempts.get())
+    }
+
+    #[test]
+    fn retries_serialization_failures() {
+        let (res, attempts) = run(2, DatabaseErrorKind::SerializationFailure, 3);
+
+        assert_eq!(res.unwrap(), 3);
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn retries_deadlocks() {
+        let (res, attempts) = run(1, DatabaseErrorKind::DeadlockDetected, 3);
+
+        assert_eq!(res.unwrap(), 2);
+        assert_eq!(attempts, 2);
+    }
+
+    #[test]
+    fn doesnt_retry_other_errors() {
+        let (res, attempts) = run(1, DatabaseErrorKind::UniqueViolation, 3);
+
+        assert!(matches!(
+            res.unwrap_err().kind(),
+            crate::error::ErrorKind::DatabaseError(DatabaseErrorKind::UniqueViolation, _)
+        ));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let (res, attempts) = run(5, DatabaseErrorKind::SerializationFailure, 2);
+
+        assert!(res.unwrap_err().kind().is_serialization_failure());
+        assert_eq!(attempts, 2);
+    }
+
+    #[test]
+    fn each_attempt_is_a_new_transaction() {
+        let conn = connection(&[]);
+        let attempts = Cell::new(0);
+
+        let res = conn
+            .retrying_transaction(|| async {
+                attempts.set(attempts.get() + 1);
+                if attempts.get() == 1 {
+                    Err(error(DatabaseErrorKind::SerializationFailure))
+                } else {
+                    Ok(())
+                }
+            })
+            .backoff(Backoff::Immediate)
+            .now_or_never()
+            .unwrap();
+
+        assert!(res.is_ok());
+        assert_eq!(statements(&conn), ["BEGIN", "ROLLBACK", "BEGIN", "COMMIT"]);
+    }
+
+    #[test]
+    fn computes_backoff_delays() {
+        let ms = Duration::from_millis;
+
+        assert_eq!(Backoff::Immediate.delay(3), ms(0));
+        assert_eq!(Backoff::Constant(ms(5)).delay(1), ms(5));
+        assert_eq!(Backoff::Constant(ms(5)).delay(7), ms(5));
+
+        let exponential = Backoff::Exponential {
+            initial: ms(10),
+            max: ms(1000),
+        };
+        assert_eq!(exponential.delay(1), ms(10));
+        assert_eq!(exponential.delay(2), ms(20));
+        assert_eq!(exponential.delay(3), ms(40));
+        assert_eq!(exponential.delay(7), ms(640));
+        assert_eq!(exponential.delay(8), ms(1000));
+        // Doesn't overflow with large retry counts.
+        assert_eq!(exponential.delay(100), ms(1000));
+    }
+}
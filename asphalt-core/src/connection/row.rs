@@ -1,8 +1,9 @@
 use super::RawConnection;
-use crate::backend::{Backend, HasSqlType};
-use crate::error::{AnyResult, QueryResult};
+use crate::backend::{Backend, HasSqlType, TypeMetadata};
+use crate::error::{AnyError, AnyResult, Error, QueryResult};
 use crate::types::FromSql;
 use futures_util::stream::BoxStream;
+use std::error::Error as StdError;
 
 /// A stream of rows resulting from the execution of a query by a connection `Conn`.
 pub type RowStream<'c, Conn> = BoxStream<'c, QueryResult<<Conn as RawConnection>::Row>>;
@@ -15,9 +16,280 @@ pub trait Row {
     /// Number of columns in this row.
     fn n_columns(&self) -> usize;
 
+    /// Returns the description of the column at `idx`, if it exists.
+    fn column(&self, idx: usize) -> Option<ColumnDescriptor<'_, Self::Backend>>;
+
+    /// Returns the index of the column with the given name, if any.
+    ///
+    /// If more than one column has the same name, the first one is returned.
+    fn column_index(&self, name: &str) -> Option<usize> {
+        (0..self.n_columns()).find(|&idx| {
+            self.column(idx)
+                .map_or(false, |column| column.name() == name)
+        })
+    }
+
     /// get a column using a specific rust type.
+    ///
+    /// Backends should check that the column type is compatible with `SqlTy`, failing
+    /// with a [`ColumnTypeMismatch`] error otherwise. Which types are compatible is up to
+    /// the backend, e.g. Postgres only accepts the types that share the binary format of
+    /// `SqlTy`, so an `INT2` column can't be read as `Integer` and `get_column_unchecked`
+    /// (or a cast in the query) is needed instead.
     fn get_column<'a, SqlTy, RustTy>(&'a self, idx: usize) -> AnyResult<RustTy>
     where
         Self::Backend: HasSqlType<SqlTy>,
         RustTy: FromSql<'a, SqlTy, Self::Backend>;
+
+    /// get a column using a specific rust type, without checking the column type.
+    ///
+    /// This is an escape hatch for types the backend doesn't know to be compatible,
+    /// the value is decoded as `SqlTy` regardless of the actual type of the column.
+    fn get_column_unchecked<'a, SqlTy, RustTy>(&'a self, idx: usize) -> AnyResult<RustTy>
+    where
+        Self::Backend: HasSqlType<SqlTy>,
+        RustTy: FromSql<'a, SqlTy, Self::Backend>,
+    {
+        self.get_column::<SqlTy, RustTy>(idx)
+    }
+
+    /// get a column by its name using a specific rust type.
+    fn get_column_by_name<'a, SqlTy, RustTy>(&'a self, name: &str) -> AnyResult<RustTy>
+    where
+        Self::Backend: HasSqlType<SqlTy>,
+        RustTy: FromSql<'a, SqlTy, Self::Backend>,
+    {
+        match self.column_index(name) {
+            Some(idx) => self.get_column::<SqlTy, RustTy>(idx),
+            None => Err(format!("No column named `{}` in the row", name).into()),
+        }
+    }
+}
+
+/// Error returned when a column is read as an incompatible SQL type.
+#[derive(Debug)]
+pub struct ColumnTypeMismatch {
+    /// The name of the column.
+    pub column: String,
+    /// The name of the SQL type requested.
+    pub expected: String,
+    /// The name of the column SQL type.
+    pub actual: String,
+}
+
+impl std::fmt::Display for ColumnTypeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Column `{}` has type `{}`, which is not compatible with the expected type `{}`",
+            self.column, self.actual, self.expected
+        )
+    }
+}
+
+impl StdError for ColumnTypeMismatch {}
+
+/// Description of a column in a [`Row`].
+pub struct ColumnDescriptor<'r, Db: TypeMetadata> {
+    name: &'r str,
+    metadata: Db::TypeMetadata,
+    table_oid: Option<u32>,
+    column_number: Option<i16>,
+}
+
+impl<'r, Db: TypeMetadata> ColumnDescriptor<'r, Db> {
+    pub fn new(name: &'r str, metadata: Db::TypeMetadata) -> Self {
+        Self {
+            name,
+            metadata,
+            table_oid: None,
+            column_number: None,
+        }
+    }
+
+    /// Set the table from which the column was taken.
+    pub fn with_table(mut self, table_oid: u32, column_number: i16) -> Self {
+        self.table_oid = Some(table_oid);
+        self.column_number = Some(column_number);
+        self
+    }
+
+    /// The name of the column.
+    pub fn name(&self) -> &'r str {
+        self.name
+    }
+
+    /// The metadata of the column type.
+    pub fn metadata(&self) -> &Db::TypeMetadata {
+        &self.metadata
+    }
+
+    /// The OID of the table from which the column was taken, if the column is a
+    /// reference to a table column and the backend has this information.
+    pub fn table_oid(&self) -> Option<u32> {
+        self.table_oid
+    }
+
+    /// The attribute number of the column in its table, if the column is a
+    /// reference to a table column and the backend has this information.
+    pub fn column_number(&self) -> Option<i16> {
+        self.column_number
+    }
+}
+
+impl<'r, Db: TypeMetadata> Clone for ColumnDescriptor<'r, Db> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            metadata: self.metadata.clone(),
+            table_oid: self.table_oid,
+            column_number: self.column_number,
+        }
+    }
+}
+
+impl<'r, Db> std::fmt::Debug for ColumnDescriptor<'r, Db>
+where
+    Db: TypeMetadata,
+    Db::TypeMetadata: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ColumnDescriptor")
+            .field("name", &self.name)
+            .field("metadata", &self.metadata)
+            .field("table_oid", &self.table_oid)
+            .field("column_number", &self.column_number)
+            .finish()
+    }
+}
+
+/// Deserialize a whole row into a rust type.
+///
+/// This is usually implemented with `#[derive(FromRow)]`, available with the `derive`
+/// feature. The SQL type of each field is given with `#[asphalt(sql_type = ...)]`, and
+/// `Option` fields are read as nullable columns:
+///
+/// ```ignore
+/// #[derive(FromRow)]
+/// struct User {
+///     id: i32,
+///     #[asphalt(column = "user_name")]
+///     name: String,
+///     #[asphalt(sql_type = Uuid)]
+///     tenant_id: uuid::Uuid,
+///     email: Option<String>,
+/// }
+/// ```
+///
+/// The attribute can be omitted for the fields of these types, and `Option`s of them:
+///
+/// | Rust type | SQL type |
+/// |-----------|----------|
+/// | `bool` | `Bool` |
+/// | `i8`, `i16`, `i32`, `i64` | `TinyInt`, `SmallInt`, `Integer`, `BigInt` |
+/// | `f32`, `f64` | `Float`, `Double` |
+/// | `String`, `&str` | `Text` |
+/// | `Vec<u8>`, `&[u8]` | `Binary` |
+///
+/// Fields are mapped to columns by name, using the field name, or the name given in
+/// `#[asphalt(column = "...")]`. Adding `#[asphalt(by_position)]` to the struct maps
+/// the fields to the columns in order instead, which is required for tuple structs.
+pub trait FromRow<'r, R: Row>: Sized {
+    fn from_row(row: &'r R) -> QueryResult<Self>;
+}
+
+/// Deserialize the column at `idx` as the value of `field`.
+///
+/// Errors are reported as [`FromRowError`]s naming the field.
+pub fn get_field<'r, R, SqlTy, RustTy>(
+    row: &'r R,
+    field: &'static str,
+    idx: usize,
+) -> QueryResult<RustTy>
+where
+    R: Row,
+    R::Backend: HasSqlType<SqlTy>,
+    RustTy: FromSql<'r, SqlTy, R::Backend>,
+{
+    if idx >= row.n_columns() {
+        return Err(FromRowError::NotEnoughColumns {
+            expected: idx + 1,
+            found: row.n_columns(),
+        }
+        .into());
+    }
+
+    row.get_column::<SqlTy, RustTy>(idx)
+        .map_err(|source| FromRowError::InvalidField { field, source }.into())
+}
+
+/// Deserialize the column named `column` as the value of `field`.
+///
+/// Errors are reported as [`FromRowError`]s naming the field.
+pub fn get_named_field<'r, R, SqlTy, RustTy>(
+    row: &'r R,
+    field: &'static str,
+    column: &'static str,
+) -> QueryResult<RustTy>
+where
+    R: Row,
+    R::Backend: HasSqlType<SqlTy>,
+    RustTy: FromSql<'r, SqlTy, R::Backend>,
+{
+    let idx = row
+        .column_index(column)
+        .ok_or(FromRowError::MissingColumn { field, column })?;
+
+    row.get_column::<SqlTy, RustTy>(idx)
+        .map_err(|source| FromRowError::InvalidField { field, source }.into())
+}
+
+/// Errors that happen when deserializing a row with [`FromRow`].
+#[derive(Debug)]
+pub enum FromRowError {
+    /// The row doesn't have the column mapped to a field.
+    MissingColumn {
+        field: &'static str,
+        column: &'static str,
+    },
+    /// The row has less columns than fields mapped by position.
+    NotEnoughColumns { expected: usize, found: usize },
+    /// Failed to deserialize the value of a field.
+    InvalidField {
+        field: &'static str,
+        source: AnyError,
+    },
+}
+
+impl std::fmt::Display for FromRowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingColumn { field, column } => {
+                write!(f, "No column `{}` for field `{}`", column, field)
+            }
+            Self::NotEnoughColumns { expected, found } => write!(
+                f,
+                "Expected at least {} columns, but the row has {}",
+                expected, found
+            ),
+            Self::InvalidField { field, source } => {
+                write!(f, "Invalid value for field `{}`: {}", field, source)
+            }
+        }
+    }
+}
+
+impl StdError for FromRowError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::InvalidField { source, .. } => Some(&**source),
+            _ => None,
+        }
+    }
+}
+
+impl From<FromRowError> for Error {
+    fn from(error: FromRowError) -> Self {
+        Error::deserialization_failure(Box::new(error))
+    }
 }
@@ -0,0 +1,225 @@
+//! A minimal backend used by the tests of the connection machinery.
+use crate::backend::{Backend, HasSqlType, TypeMetadata};
+use crate::connection::{
+    ColumnDescriptor, Connection, EstablishResult, RawConnection, Row, RowStream,
+};
+use crate::error::{AnyResult, DatabaseErrorKind, Error, QueryResult};
+use crate::query::{BindCollector, DebugBind, PreparableQuery, Query, QueryWriter};
+use crate::sql::AnsiTransactionManager;
+use crate::types::{FromSql, ToSql};
+use crate::values::RawValue;
+use futures_util::future::{self, FutureExt, LocalBoxFuture};
+use std::sync::Mutex;
+
+pub(crate) struct TestDb;
+
+impl TypeMetadata for TestDb {
+    type TypeMetadata = ();
+    type MetadataLookup = ();
+}
+
+impl Backend for TestDb {
+    type Query = TestQuery;
+    type QueryWriter = TestQueryWriter;
+    type BindName = ();
+    type BindCollector = TestBindCollector;
+    type RawConnection = TestConnection;
+    type RawValue<'b> = ();
+}
+
+impl RawValue<TestDb> for () {
+    fn is_null(&self) -> bool {
+        true
+    }
+
+    fn null_value() -> Self {}
+}
+
+/// A query, identified in the statement cache by its SQL.
+pub(crate) struct TestQuery {
+    sql: String,
+    prepared: bool,
+}
+
+impl PreparableQuery<TestDb> for TestQuery {
+    type Prepared = String;
+    type CacheKey = String;
+
+    fn prepare(self, conn: &TestConnection) -> LocalBoxFuture<QueryResult<Self::Prepared>> {
+        conn.prepared.lock().unwrap().push(self.sql.clone());
+        Box::pin(async { Ok(self.sql) })
+    }
+
+    fn from_prepared(sql: Self::Prepared) -> Self {
+        TestQuery {
+            sql,
+            prepared: true,
+        }
+    }
+
+    fn cache_key(&self) -> Option<Self::CacheKey> {
+        if self.prepared {
+            None
+        } else {
+            Some(self.sql.clone())
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct TestQueryWriter(String);
+
+impl QueryWriter<TestDb> for TestQueryWriter {
+    fn push_sql(&mut self, sql: &str) {
+        self.0.push_str(sql);
+    }
+
+    fn push_identifier(&mut self, _identifier: &str) {}
+
+    fn push_bind_param(&mut self, _name: &()) {}
+
+    fn sql(&self) -> &str {
+        &self.0
+    }
+
+    fn finish(self) -> TestQuery {
+        TestQuery {
+            sql: self.0,
+            prepared: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct TestBindCollector;
+
+impl BindCollector<TestDb> for TestBindCollector {
+    fn push_bound_value<'a, SqlTy, RustTy>(
+        &'a mut self,
+        _bind: &'a RustTy,
+        _metadata_lookup: &'a (),
+    ) -> LocalBoxFuture<'a, QueryResult<()>>
+    where
+        TestDb: HasSqlType<SqlTy>,
+        RustTy: ToSql<SqlTy, TestDb> + ?Sized,
+    {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn bind_count(&self) -> usize {
+        0
+    }
+
+    fn debug_binds(&self) -> Vec<DebugBind> {
+        Vec::new()
+    }
+}
+
+pub(crate) struct TestRow;
+
+impl Row for TestRow {
+    type Backend = TestDb;
+
+    fn n_columns(&self) -> usize {
+        0
+    }
+
+    fn column(&self, _idx: usize) -> Option<ColumnDescriptor<'_, TestDb>> {
+        None
+    }
+
+    fn get_column<'a, SqlTy, RustTy>(&'a self, _idx: usize) -> AnyResult<RustTy>
+    where
+        TestDb: HasSqlType<SqlTy>,
+        RustTy: FromSql<'a, SqlTy, TestDb>,
+    {
+        Err("TestRow has no columns".into())
+    }
+}
+
+/// A connection that records every statement and fails the ones in `failing`.
+///
+/// Queries executed with a prepared statement fail as stale if their SQL is in `stale`.
+#[derive(Default)]
+pub(crate) struct TestConnection {
+    manager: AnsiTransactionManager,
+    pub(crate) statements: Mutex<Vec<String>>,
+    pub(crate) failing: Mutex<Vec<&'static str>>,
+    /// The SQL of the prepared statements, in preparation order.
+    pub(crate) prepared: Mutex<Vec<String>>,
+    pub(crate) stale: Mutex<Vec<&'static str>>,
+}
+
+impl TestConnection {
+    fn run(&self, query: &TestQuery) -> QueryResult<()> {
+        self.statements.lock().unwrap().push(query.sql.clone());
+
+        if query.prepared && self.stale.lock().unwrap().contains(&&*query.sql) {
+            return Err(Error::database_error(
+                DatabaseErrorKind::StalePreparedStatement,
+                format!("{} is stale", query.sql),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl RawConnection for TestConnection {
+    type Backend = TestDb;
+    type TransactionManager = AnsiTransactionManager;
+    type Row = TestRow;
+    type Config = ();
+    type EstablishError = std::fmt::Error;
+
+    fn establish(_config: ()) -> LocalBoxFuture<'static, EstablishResult<Self>> {
+        Box::pin(async { Ok(Self::default()) })
+    }
+
+    fn transaction_manager(&self) -> &Self::TransactionManager {
+        &self.manager
+    }
+
+    fn simple_execute<'s>(&'s self, sql: &'s str) -> LocalBoxFuture<'s, QueryResult<()>> {
+        self.statements.lock().unwrap().push(sql.to_string());
+
+        let res = if self.failing.lock().unwrap().contains(&sql) {
+            Err(Error::database_error(
+                DatabaseErrorKind::Unknown,
+                format!("{} failed", sql),
+            ))
+        } else {
+            Ok(())
+        };
+
+        Box::pin(future::ready(res))
+    }
+
+    fn execute(&self, query: Query<TestDb>) -> LocalBoxFuture<'_, QueryResult<u64>> {
+        let res = self.run(&query.inner).map(|()| 0);
+        Box::pin(future::ready(res))
+    }
+
+    fn query(&self, query: Query<TestDb>) -> LocalBoxFuture<'_, QueryResult<RowStream<'_, Self>>> {
+        let res = self
+            .run(&query.inner)
+            .map(|()| Box::pin(futures_util::stream::empty()) as RowStream<'_, Self>);
+        Box::pin(future::ready(res))
+    }
+
+    fn metadata_lookup(&self) -> &() {
+        &()
+    }
+}
+
+/// Establishes a test connection, failing the statements in `failing`.
+pub(crate) fn connection(failing: &[&'static str]) -> Connection<TestDb> {
+    let conn: Connection<TestDb> = Connection::establish(()).now_or_never().unwrap().unwrap();
+    conn.conn.failing.lock().unwrap().extend_from_slice(failing);
+    conn
+}
+
+/// Returns the statements executed by the connection, in execution order.
+pub(crate) fn statements(conn: &Connection<TestDb>) -> Vec<String> {
+    conn.conn.statements.lock().unwrap().clone()
+}
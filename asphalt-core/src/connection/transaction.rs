@@ -1,4 +1,4 @@
-use super::RawConnection;
+use super::{Instrumentation, RawConnection, TransactionEvent};
 use crate::error::{Error, QueryResult};
 use crate::extensions::{IsolationLevel as IsoLvl, ReadOnly, Supports};
 use futures_util::future::{CatchUnwind, LocalBoxFuture, TryFuture};
@@ -61,7 +61,8 @@ where
     /// Commit the transaction.
     ///
     /// Is expected that the implementation rollback the transaction if the `COMMIT` operation
-    /// failed due to a serialization error.
+    /// failed, e.g. due to a serialization error, as the transaction may still be open. If
+    /// the rollback also fails, the connection must be marked as broken.
     fn commit_transaction<'c>(&'c self, conn: &'c Conn) -> LocalBoxFuture<'c, QueryResult<()>>;
 
     /// Rollbacks the transaction.
@@ -69,6 +70,12 @@ where
 
     /// Returns whether the connection is in a broken state.
     fn is_broken(&self) -> bool;
+
+    /// Marks the connection as broken.
+    ///
+    /// This is called when the state of the transaction can't be known anymore, e.g. when
+    /// a [`Transaction`] future is dropped before completion.
+    fn mark_broken(&self);
 }
 
 /// A transaction manager that does nothing.
@@ -99,16 +106,26 @@ where
     fn is_broken(&self) -> bool {
         false
     }
+
+    fn mark_broken(&self) {}
 }
 
-// TODO: what to do when Transaction is dropped?
 /// A future which executes the inner future inside a database transaction.
-#[pin_project]
+///
+/// # Drop
+///
+/// Dropping this future after it was first polled but before it completes leaves the
+/// transaction in an unknown state, as we can't rollback it synchronously. In this case
+/// the connection is marked as broken (see [`TransactionManager::mark_broken`]) and
+/// shouldn't be used anymore.
+#[pin_project(PinnedDrop)]
 pub struct Transaction<'c, Conn, F>
 where
+    Conn: RawConnection,
     F: TryFuture,
 {
     conn: &'c Conn,
+    instrumentation: Option<&'c dyn Instrumentation>,
     #[pin]
     state: TransactionState<'c, F>,
 }
@@ -119,12 +136,22 @@ where
     F: TryFuture,
 {
     pub(super) fn new(conn: &'c Conn, inner: F) -> Self {
+        Self::with_config(conn, inner, TransactionConfig::default())
+    }
+
+    pub(super) fn with_config(conn: &'c Conn, inner: F, config: TransactionConfig) -> Self {
         Self {
             conn,
-            state: TransactionState::NotStarted(Some(inner), Some(TransactionConfig::default())),
+            instrumentation: None,
+            state: TransactionState::NotStarted(Some(inner), Some(config)),
         }
     }
 
+    pub(super) fn instrumented(mut self, instrumentation: Option<&'c dyn Instrumentation>) -> Self {
+        self.instrumentation = instrumentation;
+        self
+    }
+
     /// Sets the isolation level of the transaction.
     pub fn isolation_level(mut self, level: IsolationLevel) -> Self
     where
@@ -186,6 +213,8 @@ where
         /// The panic payload.
         payload: Option<Box<dyn std::any::Any + Send>>,
     },
+    /// The transaction finished.
+    Done,
 }
 
 impl<Conn, F, T, E> Future for Transaction<'_, Conn, F>
@@ -201,74 +230,417 @@ where
 
         let mut me = self.project();
 
-        let next = match me.state.as_mut().project() {
-            StateProj::NotStarted(inner, config) => {
-                let tm = me.conn.transaction_manager();
-                let begin = tm.begin_transaction(config.take().unwrap(), me.conn);
-                TransactionState::Beginning(begin, inner.take())
-            }
-            StateProj::Beginning(begin, inner) => {
-                if let Err(err) = ready!(begin.poll(cx)) {
-                    return Poll::Ready(Err(err.into()));
+        loop {
+            let next = match me.state.as_mut().project() {
+                StateProj::NotStarted(inner, config) => {
+                    let tm = me.conn.transaction_manager();
+                    let begin = tm.begin_transaction(config.take().unwrap(), me.conn);
+                    TransactionState::Beginning(begin, inner.take())
                 }
+                StateProj::Beginning(begin, inner) => {
+                    let res = ready!(begin.poll(cx));
+                    report(*me.instrumentation, TransactionEvent::Begin, &res);
 
-                TransactionState::InProgress(AssertUnwindSafe(inner.take().unwrap()).catch_unwind())
-            }
-            StateProj::InProgress(inner) => {
-                match ready!(inner.try_poll(cx)) {
-                    // The future didn't panic and resolved correctly, commit the transaction.
-                    Ok(Ok(ok)) => {
-                        let tm = me.conn.transaction_manager();
-                        let inner = tm.commit_transaction(me.conn);
-                        TransactionState::Committing {
-                            inner,
-                            output: Some(ok),
-                        }
+                    if let Err(err) = res {
+                        me.state.set(TransactionState::Done);
+                        return Poll::Ready(Err(err.into()));
                     }
-                    // The future didn't panic but resolved to an error, rollback the transaction.
-                    Ok(Err(err)) => {
-                        let tm = me.conn.transaction_manager();
-                        let inner = tm.rollback_transaction(me.conn);
-                        TransactionState::Aborting {
-                            inner,
+
+                    TransactionState::InProgress(
+                        AssertUnwindSafe(inner.take().unwrap()).catch_unwind(),
+                    )
+                }
+                StateProj::InProgress(inner) => {
+                    let tm = me.conn.transaction_manager();
+                    match ready!(inner.try_poll(cx)) {
+                        // The future didn't panic and resolved correctly, commit the transaction.
+                        Ok(Ok(ok)) => TransactionState::Committing {
+                            inner: tm.commit_transaction(me.conn),
+                            output: Some(ok),
+                        },
+                        // The future didn't panic but resolved to an error, rollback the transaction.
+                        Ok(Err(err)) => TransactionState::Aborting {
+                            inner: tm.rollback_transaction(me.conn),
                             output: Some(err),
-                        }
-                    }
-                    // The future panicked, rollback the transaction and resume unwind.
-                    Err(payload) => {
-                        let tm = me.conn.transaction_manager();
-                        let inner = tm.rollback_transaction(me.conn);
-                        TransactionState::Panicking {
-                            inner,
+                        },
+                        // The future panicked, rollback the transaction and resume unwind.
+                        Err(payload) => TransactionState::Panicking {
+                            inner: tm.rollback_transaction(me.conn),
                             payload: Some(payload),
-                        }
+                        },
                     }
                 }
-            }
-            StateProj::Committing { inner, output } => {
-                return match ready!(inner.poll(cx)) {
-                    Ok(_) => Poll::Ready(Ok(output.take().unwrap())),
-                    Err(err) => Poll::Ready(Err(err.into())),
+                StateProj::Committing { inner, output } => {
+                    let res = ready!(inner.poll(cx));
+                    report(*me.instrumentation, TransactionEvent::Commit, &res);
+                    let output = output.take().unwrap();
+                    me.state.set(TransactionState::Done);
+
+                    return match res {
+                        Ok(_) => Poll::Ready(Ok(output)),
+                        Err(err) => Poll::Ready(Err(err.into())),
+                    };
+                }
+                StateProj::Aborting { inner, output } => {
+                    let res = ready!(inner.poll(cx));
+                    report(*me.instrumentation, TransactionEvent::Rollback, &res);
+                    let output = output.take().unwrap();
+                    me.state.set(TransactionState::Done);
+
+                    return match res {
+                        Ok(_) => Poll::Ready(Err(output)),
+                        // Should we return the abort error here? I'm following the diesel
+                        // behaviour but I'm not sure if this is the best one.
+                        Err(err) => Poll::Ready(Err(err.into())),
+                    };
+                }
+                StateProj::Panicking { inner, payload } => {
+                    // The rollback error is only reported, as we're already panicking and the
+                    // transaction manager marks the connection as broken if it fails.
+                    let res = ready!(inner.poll(cx));
+                    report(*me.instrumentation, TransactionEvent::Rollback, &res);
+                    let payload = payload.take().unwrap();
+                    me.state.set(TransactionState::Done);
+
+                    std::panic::resume_unwind(payload)
                 }
-            }
-            StateProj::Aborting { inner, output } => {
-                return match ready!(inner.poll(cx)) {
-                    Ok(_) => Poll::Ready(Err(output.take().unwrap())),
-                    // Should we return the abort error here? I'm following the diesel
-                    // behaviour but I'm not sure if this is the best one.
-                    Err(err) => Poll::Ready(Err(err.into())),
-                };
-            }
-            StateProj::Panicking { inner, payload } => {
-                // TODO: What to do in case this fails?
-                //   We're panicking, so we can just log and forget?
-                let _ = ready!(inner.poll(cx));
-                std::panic::resume_unwind(payload.take().unwrap())
-            }
-        };
-
-        me.state.set(next);
-
-        todo!()
+                StateProj::Done => panic!("Polled a finished Transaction future!"),
+            };
+
+            me.state.set(next);
+        }
+    }
+}
+
+fn report(
+    instrumentation: Option<&dyn Instrumentation>,
+    event: TransactionEvent,
+    res: &QueryResult<()>,
+) {
+    if let Some(instrumentation) = instrumentation {
+        instrumentation.on_transaction(event, res.as_ref().map(|_| ()));
+    }
+}
+
+#[pinned_drop]
+impl<Conn, F> PinnedDrop for Transaction<'_, Conn, F>
+where
+    Conn: RawConnection,
+    F: TryFuture,
+{
+    fn drop(self: Pin<&mut Self>) {
+        let me = self.project();
+
+        match me.state.project() {
+            StateProj::NotStarted(..) | StateProj::Done => {}
+            // We may be inside an open transaction, and there is no way to roll it back from here.
+            _ => me.conn.transaction_manager().mark_broken(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connection::test_db::{connection, statements, TestDb, TestRow};
+    use crate::connection::{
+        Connection, Instrumentation, QueryInfo, RawConnection, TransactionEvent,
+    };
+    use crate::error::Error;
+    use futures_util::future::{self, FutureExt};
+    use futures_util::task::noop_waker_ref;
+    use futures_util::TryStreamExt;
+    use std::future::Future;
+    use std::panic::AssertUnwindSafe;
+    use std::sync::{Arc, Mutex};
+    use std::task::Context;
+    use std::time::Duration;
+
+    fn transaction_depth(conn: &Connection<TestDb>) -> u8 {
+        conn.raw_connection()
+            .transaction_manager()
+            .transaction_depth()
+    }
+
+    #[test]
+    fn commits_successful_transactions() {
+        let conn = connection(&[]);
+
+        let res = conn
+            .transaction(async { Ok::<_, Error>(42) })
+            .now_or_never()
+            .unwrap();
+
+        assert_eq!(res.unwrap(), 42);
+        assert_eq!(statements(&conn), ["BEGIN", "COMMIT"]);
+        assert!(!conn.is_broken());
+    }
+
+    #[test]
+    fn rollbacks_failed_transactions() {
+        let conn = connection(&[]);
+
+        let res = conn
+            .transaction(async { Err::<(), _>(Error::rollback_transaction()) })
+            .now_or_never()
+            .unwrap();
+
+        assert!(matches!(
+            res.unwrap_err().kind(),
+            crate::error::ErrorKind::RollbackTransaction
+        ));
+        assert_eq!(statements(&conn), ["BEGIN", "ROLLBACK"]);
+        assert!(!conn.is_broken());
+    }
+
+    #[test]
+    fn rollbacks_panicking_transactions() {
+        let conn = connection(&[]);
+
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            conn.transaction(async { panic!("boom") as Result<(), Error> })
+                .now_or_never()
+        }));
+
+        assert_eq!(res.unwrap_err().downcast_ref::<&str>(), Some(&"boom"));
+        assert_eq!(statements(&conn), ["BEGIN", "ROLLBACK"]);
+        assert!(!conn.is_broken());
+    }
+
+    #[test]
+    fn nested_transactions_use_savepoints() {
+        let conn = connection(&[]);
+
+        let res = conn
+            .transaction(async {
+                conn.transaction(async { Ok::<_, Error>(()) }).await?;
+                conn.transaction(async { Err::<(), _>(Error::rollback_transaction()) })
+                    .await
+                    .unwrap_err();
+                Ok::<_, Error>(())
+            })
+            .now_or_never()
+            .unwrap();
+
+        assert!(res.is_ok());
+        assert_eq!(
+            statements(&conn),
+            [
+                "BEGIN",
+                "SAVEPOINT asphalt_savepoint_1",
+                "RELEASE SAVEPOINT asphalt_savepoint_1",
+                "SAVEPOINT asphalt_savepoint_1",
+                "ROLLBACK TO SAVEPOINT asphalt_savepoint_1",
+                "COMMIT",
+            ]
+        );
+    }
+
+    #[test]
+    fn returns_begin_errors() {
+        let conn = connection(&["BEGIN"]);
+
+        let res = conn
+            .transaction(async { Ok::<_, Error>(()) })
+            .now_or_never()
+            .unwrap();
+
+        assert!(res.is_err());
+        assert_eq!(statements(&conn), ["BEGIN"]);
+        assert!(!conn.is_broken());
+    }
+
+    #[test]
+    fn returns_commit_errors() {
+        let conn = connection(&["COMMIT"]);
+
+        let res = conn
+            .transaction(async { Ok::<_, Error>(()) })
+            .now_or_never()
+            .unwrap();
+
+        assert!(res.is_err());
+        assert_eq!(statements(&conn), ["BEGIN", "COMMIT", "ROLLBACK"]);
+        assert_eq!(transaction_depth(&conn), 0);
+        assert!(!conn.is_broken());
+    }
+
+    #[test]
+    fn failed_commits_and_rollbacks_break_the_connection() {
+        let conn = connection(&["COMMIT", "ROLLBACK"]);
+
+        let res = conn
+            .transaction(async { Ok::<_, Error>(()) })
+            .now_or_never()
+            .unwrap();
+
+        assert!(res.is_err());
+        assert_eq!(statements(&conn), ["BEGIN", "COMMIT", "ROLLBACK"]);
+        assert!(conn.is_broken());
+    }
+
+    #[test]
+    fn rollbacks_savepoints_that_fail_to_release() {
+        let conn = connection(&["RELEASE SAVEPOINT asphalt_savepoint_1"]);
+
+        let res = conn
+            .transaction(async {
+                conn.transaction(async { Ok::<_, Error>(()) })
+                    .await
+                    .unwrap_err();
+                assert_eq!(transaction_depth(&conn), 1);
+                Ok::<_, Error>(())
+            })
+            .now_or_never()
+            .unwrap();
+
+        assert!(res.is_ok());
+        assert_eq!(
+            statements(&conn),
+            [
+                "BEGIN",
+                "SAVEPOINT asphalt_savepoint_1",
+                "RELEASE SAVEPOINT asphalt_savepoint_1",
+                "ROLLBACK TO SAVEPOINT asphalt_savepoint_1",
+                "COMMIT",
+            ]
+        );
+        assert_eq!(transaction_depth(&conn), 0);
+        assert!(!conn.is_broken());
+    }
+
+    #[test]
+    fn failed_rollbacks_break_the_connection() {
+        let conn = connection(&["ROLLBACK"]);
+
+        let res = conn
+            .transaction(async { Err::<(), _>(Error::rollback_transaction()) })
+            .now_or_never()
+            .unwrap();
+
+        assert!(res.is_err());
+        assert!(conn.is_broken());
+    }
+
+    #[test]
+    fn failed_rollbacks_while_panicking_break_the_connection() {
+        let conn = connection(&["ROLLBACK"]);
+
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            conn.transaction(async { panic!("boom") as Result<(), Error> })
+                .now_or_never()
+        }));
+
+        assert!(res.is_err());
+        assert!(conn.is_broken());
+    }
+
+    #[test]
+    fn dropping_an_unpolled_transaction_is_harmless() {
+        let conn = connection(&[]);
+
+        drop(conn.transaction(async { Ok::<_, Error>(()) }));
+
+        assert!(statements(&conn).is_empty());
+        assert!(!conn.is_broken());
+    }
+
+    #[test]
+    fn dropping_an_in_progress_transaction_breaks_the_connection() {
+        let conn = connection(&[]);
+
+        let mut tx = Box::pin(conn.transaction(future::pending::<Result<(), Error>>()));
+        assert!(tx
+            .as_mut()
+            .poll(&mut Context::from_waker(noop_waker_ref()))
+            .is_pending());
+        drop(tx);
+
+        assert_eq!(statements(&conn), ["BEGIN"]);
+        assert!(conn.is_broken());
+    }
+
+    #[test]
+    fn dropping_a_finished_transaction_is_harmless() {
+        let conn = connection(&[]);
+
+        let mut tx = Box::pin(conn.transaction(async { Ok::<_, Error>(()) }));
+        assert!(tx
+            .as_mut()
+            .poll(&mut Context::from_waker(noop_waker_ref()))
+            .is_ready());
+        drop(tx);
+
+        assert!(!conn.is_broken());
+    }
+
+    /// Records the instrumentation callbacks as strings.
+    #[derive(Default)]
+    struct RecordingInstrumentation(Mutex<Vec<String>>);
+
+    impl Instrumentation for RecordingInstrumentation {
+        fn on_establish(&self, _duration: Duration, result: Result<(), &dyn std::error::Error>) {
+            let event = format!("establish ok={}", result.is_ok());
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn on_query_start(&self, query: &QueryInfo<'_>) {
+            let event = format!("start {} binds={}", query.sql, query.bind_count);
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn on_query_finish(
+            &self,
+            query: &QueryInfo<'_>,
+            _duration: Duration,
+            result: Result<u64, &Error>,
+        ) {
+            let event = format!("finish {} rows={:?}", query.sql, result.ok());
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn on_transaction(&self, event: TransactionEvent, result: Result<(), &Error>) {
+            let event = format!("{:?} ok={}", event, result.is_ok());
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    #[test]
+    fn reports_instrumented_operations() {
+        let instrumentation = Arc::new(RecordingInstrumentation::default());
+        let conn: Connection<TestDb> =
+            Connection::establish_instrumented((), instrumentation.clone())
+                .now_or_never()
+                .unwrap()
+                .unwrap();
+        conn.conn.failing.lock().unwrap().push("COMMIT");
+
+        let res = conn
+            .transaction(async {
+                let mut query = conn.query_builder();
+                query.push_sql("SELECT 1");
+                let rows: Vec<TestRow> = conn.query(query).await?.try_collect().await?;
+
+                let mut query = conn.query_builder();
+                query.push_sql("DELETE FROM users");
+                conn.executes(query).await?;
+
+                Ok::<_, Error>(rows.len())
+            })
+            .now_or_never()
+            .unwrap();
+
+        assert!(res.is_err());
+        assert_eq!(
+            *instrumentation.0.lock().unwrap(),
+            [
+                "establish ok=true",
+                "Begin ok=true",
+                "start SELECT 1 binds=0",
+                "finish SELECT 1 rows=Some(0)",
+                "start DELETE FROM users binds=0",
+                "finish DELETE FROM users rows=Some(0)",
+                "Commit ok=false",
+            ]
+        );
     }
 }
@@ -14,12 +14,16 @@ pub type QueryResult<T> = Result<T, Error>;
 pub struct Error {
     kind: ErrorKind,
     backtrace: Option<Backtrace>,
+    query: Option<String>,
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.kind {
             ErrorKind::NotFound => f.write_str("No row returned when one was expected"),
+            ErrorKind::TooManyRows => {
+                f.write_str("More than one row returned when only one was expected")
+            }
             ErrorKind::DatabaseError(kind, info) => match kind {
                 DatabaseErrorKind::UniqueViolation => {
                     write!(f, "Unique violation: {}", info.message())
@@ -27,12 +31,34 @@ impl std::fmt::Display for Error {
                 DatabaseErrorKind::ForeignKeyViolation => {
                     write!(f, "Foreign key violation: {}", info.message())
                 }
+                DatabaseErrorKind::NotNullViolation => {
+                    write!(f, "Not null violation: {}", info.message())
+                }
+                DatabaseErrorKind::CheckViolation => {
+                    write!(f, "Check violation: {}", info.message())
+                }
+                DatabaseErrorKind::ExclusionViolation => {
+                    write!(f, "Exclusion violation: {}", info.message())
+                }
                 DatabaseErrorKind::SerializationFailure => {
                     write!(f, "Serialization failure: {}", info.message())
                 }
+                DatabaseErrorKind::DeadlockDetected => {
+                    write!(f, "Deadlock detected: {}", info.message())
+                }
                 DatabaseErrorKind::ReadOnlyTransaction => {
                     write!(f, "Tried to write in a RO-transaction: {}", info.message())
                 }
+                DatabaseErrorKind::StalePreparedStatement => {
+                    write!(f, "Stale prepared statement: {}", info.message())
+                }
+                DatabaseErrorKind::QueryCanceled => {
+                    write!(f, "Query canceled: {}", info.message())
+                }
+                DatabaseErrorKind::InvalidInput => write!(f, "Invalid input: {}", info.message()),
+                DatabaseErrorKind::ConnectionFailure => {
+                    write!(f, "Connection failure: {}", info.message())
+                }
                 DatabaseErrorKind::Unknown => write!(f, "Unknown error: {}", info.message()),
             },
             ErrorKind::DeserializationError(err) => {
@@ -43,12 +69,60 @@ impl std::fmt::Display for Error {
                 write!(f, "Error while serializing value: {}", err)
             }
             ErrorKind::RollbackTransaction => write!(f, "Transaction rollback"),
+        }?;
+
+        if let ErrorKind::DatabaseError(_, info) = &self.kind {
+            match (info.position(), &self.query) {
+                (Some(ErrorPosition::Original(position)), Some(query)) => {
+                    write_position(f, query, position)?
+                }
+                (Some(ErrorPosition::Internal { position, query }), _) => {
+                    write_position(f, query, position)?
+                }
+                _ => {}
+            }
         }
+
+        Ok(())
     }
 }
 
 impl StdError for Error {}
 
+/// Writes the line of `query` containing the character at `position`, starting from 1,
+/// with a caret pointing to it, in the same format used by `psql`.
+fn write_position(f: &mut std::fmt::Formatter<'_>, query: &str, position: u32) -> std::fmt::Result {
+    let mut remaining = (position as usize).saturating_sub(1);
+
+    for (n, line) in query.lines().enumerate() {
+        let len = line.chars().count();
+        if remaining <= len {
+            let prefix = format!("LINE {}: ", n + 1);
+            // Keep tabs so the caret is aligned with the offending character.
+            let padding: String = line
+                .chars()
+                .take(remaining)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            return write!(
+                f,
+                "\n{}{}\n{:width$}{}^",
+                prefix,
+                line,
+                "",
+                padding,
+                width = prefix.len()
+            );
+        }
+
+        // Skip the line and its line break.
+        remaining -= len + 1;
+    }
+
+    Ok(())
+}
+
 impl Error {
     pub fn kind(&self) -> &ErrorKind {
         &self.kind
@@ -60,6 +134,7 @@ impl Error {
         Self {
             kind: ErrorKind::DeserializationError(error),
             backtrace,
+            query: None,
         }
     }
 
@@ -69,6 +144,44 @@ impl Error {
         Self {
             kind: ErrorKind::SerializationError(error),
             backtrace,
+            query: None,
+        }
+    }
+
+    pub fn query_builder_error(error: AnyError) -> Self {
+        let backtrace = error.backtrace().is_none().then(Backtrace::capture);
+
+        Self {
+            kind: ErrorKind::QueryBuilderError(error),
+            backtrace,
+            query: None,
+        }
+    }
+
+    /// Error used to rollback a transaction without any other failure.
+    pub fn rollback_transaction() -> Self {
+        Self {
+            kind: ErrorKind::RollbackTransaction,
+            backtrace: None,
+            query: None,
+        }
+    }
+
+    /// Error used when a query returns no rows, but one was expected.
+    pub fn not_found() -> Self {
+        Self {
+            kind: ErrorKind::NotFound,
+            backtrace: None,
+            query: None,
+        }
+    }
+
+    /// Error used when a query returns more than one row, but only one was expected.
+    pub fn too_many_rows() -> Self {
+        Self {
+            kind: ErrorKind::TooManyRows,
+            backtrace: None,
+            query: None,
         }
     }
 
@@ -79,13 +192,29 @@ impl Error {
         Self {
             kind: ErrorKind::DatabaseError(kind, Box::new(info)),
             backtrace: Some(Backtrace::capture()),
+            query: None,
         }
     }
+
+    /// Attach the SQL of the query that caused this error.
+    ///
+    /// This is used to point to the offending part of the query when the database
+    /// reports the position of the error.
+    pub fn with_query(mut self, query: impl Into<String>) -> Self {
+        self.query = Some(query.into());
+        self
+    }
+
+    /// The SQL of the query that caused this error, if known.
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
 }
 
 #[derive(Debug)]
 pub enum ErrorKind {
     NotFound,
+    TooManyRows,
     DatabaseError(
         DatabaseErrorKind,
         Box<dyn DatabaseErrorInformation + Send + Sync>,
@@ -97,19 +226,37 @@ pub enum ErrorKind {
 }
 
 impl ErrorKind {
-    pub(crate) fn is_serialization_failure(&self) -> bool {
+    /// Is this a serialization failure of a transaction?
+    pub fn is_serialization_failure(&self) -> bool {
         match self {
             Self::DatabaseError(DatabaseErrorKind::SerializationFailure, _) => true,
             _ => false,
         }
     }
 
-    pub(crate) fn is_read_only_transaction(&self) -> bool {
+    /// Was a deadlock detected?
+    pub fn is_deadlock(&self) -> bool {
+        match self {
+            Self::DatabaseError(DatabaseErrorKind::DeadlockDetected, _) => true,
+            _ => false,
+        }
+    }
+
+    /// Was a write attempted in a read-only transaction?
+    pub fn is_read_only_transaction(&self) -> bool {
         match self {
             Self::DatabaseError(DatabaseErrorKind::ReadOnlyTransaction, _) => true,
             _ => false,
         }
     }
+
+    /// Is the prepared statement used stale?
+    pub fn is_stale_prepared_statement(&self) -> bool {
+        match self {
+            Self::DatabaseError(DatabaseErrorKind::StalePreparedStatement, _) => true,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -117,17 +264,57 @@ impl ErrorKind {
 pub enum DatabaseErrorKind {
     UniqueViolation,
     ForeignKeyViolation,
+    NotNullViolation,
+    CheckViolation,
+    ExclusionViolation,
     SerializationFailure,
+    DeadlockDetected,
     ReadOnlyTransaction,
+    /// A cached prepared statement can't be used anymore, e.g. because the schema
+    /// of the tables it uses changed.
+    StalePreparedStatement,
+    /// The query was canceled, e.g. due to a statement timeout or a user request.
+    QueryCanceled,
+    /// The data sent to the database is invalid, e.g. a numeric value out of range
+    /// or a malformed JSON document.
+    InvalidInput,
+    /// The connection to the database failed or was closed.
+    ConnectionFailure,
     Unknown,
 }
 
+/// The position of an error inside a query.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ErrorPosition<'a> {
+    /// A position, in characters starting from 1, in the query sent to the database.
+    Original(u32),
+    /// A position in a query generated internally by the database, e.g. inside the
+    /// body of a SQL function.
+    Internal {
+        /// The position, in characters starting from 1, in `query`.
+        position: u32,
+        /// The internally generated query.
+        query: &'a str,
+    },
+}
+
 pub trait DatabaseErrorInformation {
     fn message(&self) -> &str;
+    /// The SQLSTATE code of the error, if the backend uses them.
+    fn sql_state(&self) -> Option<&str>;
+    /// The severity of the error, e.g. `ERROR` or `FATAL`.
+    fn severity(&self) -> Option<&str>;
     fn details(&self) -> Option<&str>;
     fn hint(&self) -> Option<&str>;
+    fn position(&self) -> Option<ErrorPosition<'_>>;
+    /// The context in which the error happened, e.g. a traceback of the functions
+    /// being executed.
+    fn where_(&self) -> Option<&str>;
+    fn schema(&self) -> Option<&str>;
     fn table(&self) -> Option<&str>;
     fn column(&self) -> Option<&str>;
+    /// The name of the data type related to the error.
+    fn datatype(&self) -> Option<&str>;
     fn constraint(&self) -> Option<&str>;
 }
 
@@ -142,6 +329,14 @@ impl DatabaseErrorInformation for String {
         self
     }
 
+    fn sql_state(&self) -> Option<&str> {
+        None
+    }
+
+    fn severity(&self) -> Option<&str> {
+        None
+    }
+
     fn details(&self) -> Option<&str> {
         None
     }
@@ -150,6 +345,18 @@ impl DatabaseErrorInformation for String {
         None
     }
 
+    fn position(&self) -> Option<ErrorPosition<'_>> {
+        None
+    }
+
+    fn where_(&self) -> Option<&str> {
+        None
+    }
+
+    fn schema(&self) -> Option<&str> {
+        None
+    }
+
     fn table(&self) -> Option<&str> {
         None
     }
@@ -158,7 +365,128 @@ impl DatabaseErrorInformation for String {
         None
     }
 
+    fn datatype(&self) -> Option<&str> {
+        None
+    }
+
     fn constraint(&self) -> Option<&str> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An error reported by the database at a position of a query.
+    struct Positioned {
+        position: u32,
+        internal_query: Option<&'static str>,
+    }
+
+    impl DatabaseErrorInformation for Positioned {
+        fn message(&self) -> &str {
+            "syntax error"
+        }
+
+        fn sql_state(&self) -> Option<&str> {
+            None
+        }
+
+        fn severity(&self) -> Option<&str> {
+            None
+        }
+
+        fn details(&self) -> Option<&str> {
+            None
+        }
+
+        fn hint(&self) -> Option<&str> {
+            None
+        }
+
+        fn position(&self) -> Option<ErrorPosition<'_>> {
+            Some(match self.internal_query {
+                Some(query) => ErrorPosition::Internal {
+                    position: self.position,
+                    query,
+                },
+                None => ErrorPosition::Original(self.position),
+            })
+        }
+
+        fn where_(&self) -> Option<&str> {
+            None
+        }
+
+        fn schema(&self) -> Option<&str> {
+            None
+        }
+
+        fn table(&self) -> Option<&str> {
+            None
+        }
+
+        fn column(&self) -> Option<&str> {
+            None
+        }
+
+        fn datatype(&self) -> Option<&str> {
+            None
+        }
+
+        fn constraint(&self) -> Option<&str> {
+            None
+        }
+    }
+
+    fn error(position: u32, internal_query: Option<&'static str>) -> Error {
+        let info = Positioned {
+            position,
+            internal_query,
+        };
+        Error::database_error(DatabaseErrorKind::Unknown, info)
+    }
+
+    #[test]
+    fn points_to_the_error_position() {
+        let err = error(10, None).with_query("SELECT * FORM users");
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown error: syntax error\nLINE 1: SELECT * FORM users\n                 ^"
+        );
+    }
+
+    #[test]
+    fn points_to_the_line_of_multi_line_queries() {
+        let err = error(22, None).with_query("SELECT *\nFROM users\n\tWHER id = 1");
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown error: syntax error\nLINE 3: \tWHER id = 1\n        \t^"
+        );
+    }
+
+    #[test]
+    fn points_to_internal_queries() {
+        let err = error(5, Some("SELEC 1")).with_query("SELECT f()");
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown error: syntax error\nLINE 1: SELEC 1\n            ^"
+        );
+    }
+
+    #[test]
+    fn ignores_positions_without_the_query() {
+        assert_eq!(error(8, None).to_string(), "Unknown error: syntax error");
+    }
+
+    #[test]
+    fn ignores_positions_outside_of_the_query() {
+        let err = error(100, None).with_query("SELECT 1");
+
+        assert_eq!(err.to_string(), "Unknown error: syntax error");
+    }
+}
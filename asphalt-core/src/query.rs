@@ -1,11 +1,19 @@
 use crate::backend::{Backend, BindName, HasSqlType};
+use crate::connection::ColumnDescriptor;
 use crate::error::QueryResult;
 use crate::types::ToSql;
 use crate::utils::CowMut;
 use futures_util::future::LocalBoxFuture;
 use std::cell::Cell;
+use std::hash::Hash;
 use std::marker::PhantomData;
 
+mod debug;
+
+use self::debug::BindPositions;
+#[doc(inline)]
+pub use self::debug::{DebugBind, DebugQuery};
+
 /// A constructed query.
 pub struct Query<Db: Backend> {
     /// The final constructed query.
@@ -18,16 +26,50 @@ pub struct Query<Db: Backend> {
 pub trait PreparableQuery<Db: Backend>: Sized {
     /// The type of prepared queries.
     type Prepared: Clone;
+    /// The key identifying this query in the prepared statement cache.
+    ///
+    /// Two queries with the same key must result in equivalent prepared statements, e.g.
+    /// in most backends this should be the SQL text and the types of the parameters.
+    type CacheKey: Clone + Eq + Hash;
 
     /// Prepare the query, binding it to the given connection.
     fn prepare(self, conn: &Db::RawConnection) -> LocalBoxFuture<QueryResult<Self::Prepared>>;
 
     fn from_prepared(prepared: Self::Prepared) -> Self;
+
+    /// Free a prepared statement in the backend.
+    ///
+    /// The default implementation only drops the statement, which is enough for drivers
+    /// that free their statements when dropped.
+    fn deallocate(
+        prepared: Self::Prepared,
+        _conn: &Db::RawConnection,
+    ) -> LocalBoxFuture<'_, QueryResult<()>> {
+        drop(prepared);
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Returns the key of this query in the prepared statement cache.
+    ///
+    /// Queries that are already prepared should return `None`.
+    fn cache_key(&self) -> Option<Self::CacheKey>;
+}
+
+/// Prepared statements which can describe their parameters and result columns.
+pub trait DescribePrepared<Db: Backend> {
+    /// Returns the types of the parameters of the statement, in bind order.
+    fn param_types(&self) -> Vec<Db::TypeMetadata>;
+
+    /// Returns the columns of the result set of the statement.
+    fn columns(&self) -> Vec<ColumnDescriptor<'_, Db>>;
 }
 
 /// Type alias for a prepared query.
 pub type PreparedQuery<Db> = <<Db as Backend>::Query as PreparableQuery<Db>>::Prepared;
 
+/// Type alias for the prepared statement cache key of a query.
+pub type QueryCacheKey<Db> = <<Db as Backend>::Query as PreparableQuery<Db>>::CacheKey;
+
 /// Manages serialization of bind parameters during query construction.
 pub trait BindCollector<Db: Backend>: Default {
     /// Add a new bind parameter to the collector.
@@ -38,7 +80,13 @@ pub trait BindCollector<Db: Backend>: Default {
     ) -> LocalBoxFuture<'a, QueryResult<Db::BindName>>
     where
         Db: HasSqlType<SqlTy>,
-        RustTy: ToSql<SqlTy, Db>;
+        RustTy: ToSql<SqlTy, Db> + ?Sized;
+
+    /// Returns the number of parameters bound until now.
+    fn bind_count(&self) -> usize;
+
+    /// Returns the parameters bound until now, rendered for debugging.
+    fn debug_binds(&self) -> Vec<DebugBind>;
 }
 
 /// Constructs a SQL query from its parts.
@@ -49,6 +97,8 @@ pub trait QueryWriter<Db: Backend>: Default {
     fn push_identifier(&mut self, identifier: &str);
     /// Add a placeholder `name` for a bind parameter to the end of the query being constructed.
     fn push_bind_param(&mut self, name: &Db::BindName);
+    /// Returns the SQL constructed until now.
+    fn sql(&self) -> &str;
     /// Returns the constructed query.
     fn finish(self) -> Db::Query;
 }
@@ -59,6 +109,7 @@ pub struct QueryBuilder<'q, 'b, Db: Backend> {
     writer: CowMut<'b, Db::QueryWriter>,
     collector: CowMut<'b, Db::BindCollector>,
     safe_to_cache: CowMut<'b, bool>,
+    positions: CowMut<'b, BindPositions>,
     // Make QueryBuilder invariant over 'q.
     _marker: PhantomData<Cell<&'q ()>>,
 }
@@ -70,6 +121,7 @@ impl<'q, Db: Backend> QueryBuilder<'q, 'static, Db> {
             safe_to_cache: CowMut::Owned(true),
             collector: CowMut::Owned(Default::default()),
             writer: CowMut::Owned(Default::default()),
+            positions: CowMut::Owned(Default::default()),
             _marker: PhantomData,
         }
     }
@@ -103,6 +155,7 @@ impl<'q, 'b, Db: Backend> QueryBuilder<'q, 'b, Db> {
             writer: self.writer.reborrow(),
             collector: self.collector.reborrow(),
             safe_to_cache: self.safe_to_cache.reborrow(),
+            positions: self.positions.reborrow(),
             _marker: self._marker,
         }
     }
@@ -121,6 +174,27 @@ impl<'q, 'b, Db: Backend> QueryBuilder<'q, 'b, Db> {
         *self.safe_to_cache = false;
     }
 
+    /// Returns the SQL of the query constructed until now.
+    pub fn sql(&self) -> &str {
+        self.writer.sql()
+    }
+
+    /// Returns the number of parameters bound to the query until now.
+    pub fn bind_count(&self) -> usize {
+        self.collector.bind_count()
+    }
+
+    /// Renders the query constructed until now for debugging.
+    ///
+    /// See [`DebugQuery`] for more info.
+    pub fn debug_query(&self) -> DebugQuery {
+        DebugQuery::new(
+            self.sql().to_string(),
+            self.collector.debug_binds(),
+            &self.positions,
+        )
+    }
+
     /// Push the given SQL string to the end of the query being constructed.
     pub fn push_sql(&mut self, sql: &str) {
         self.writer.push_sql(sql)
@@ -140,20 +214,52 @@ impl<'q, 'b, Db: Backend> QueryBuilder<'q, 'b, Db> {
     pub async fn push_bind_param<ST, RT>(&mut self, bind: &RT) -> QueryResult<BindName<Db>>
     where
         Db: HasSqlType<ST>,
-        RT: ToSql<ST, Db>,
+        RT: ToSql<ST, Db> + ?Sized,
     {
         let name = self
             .collector
             .push_bound_value::<ST, _>(bind, self.metadata_lookup)
             .await?;
+        let name = BindName::new(name, self.collector.bind_count() - 1);
+
+        self.push_bind_name(&name);
+
+        Ok(name)
+    }
 
-        self.writer.push_bind_param(&name);
+    /// Push a sensitive value onto the given query, e.g. a password.
+    ///
+    /// The value is sent as any other bind parameter, but isn't shown when the query
+    /// is rendered for debugging (see [`QueryBuilder::debug_query`]).
+    pub async fn push_sensitive_bind_param<ST, RT>(
+        &mut self,
+        bind: &RT,
+    ) -> QueryResult<BindName<Db>>
+    where
+        Db: HasSqlType<ST>,
+        RT: ToSql<ST, Db> + ?Sized,
+    {
+        let name = self.push_bind_param::<ST, RT>(bind).await?;
+        self.positions.sensitive.push(name.index());
 
-        Ok(BindName::new(name))
+        Ok(name)
+    }
+
+    /// Mark the parameters bound since the `first` one as sensitive, as if they were
+    /// pushed with [`QueryBuilder::push_sensitive_bind_param`].
+    ///
+    /// This is used to redact the values compared against sensitive columns.
+    pub fn mark_sensitive_since(&mut self, first: usize) {
+        let count = self.bind_count();
+        self.positions.sensitive.extend(first..count);
     }
 
     /// Push an already bound parameter into the query being constructed.
     pub fn push_bind_name(&mut self, name: &BindName<Db>) {
+        let start = self.writer.sql().len();
         self.writer.push_bind_param(name.inner());
+        let end = self.writer.sql().len();
+
+        self.positions.placeholders.push((start..end, name.index()));
     }
 }
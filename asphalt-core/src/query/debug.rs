@@ -0,0 +1,175 @@
+use std::fmt;
+use std::ops::Range;
+
+/// A bind parameter, rendered for debugging.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DebugBind {
+    /// Name of the SQL type of the parameter.
+    pub sql_type: String,
+    /// The value of the parameter as a SQL literal, e.g. `'asphalt'` or `NULL`.
+    pub literal: String,
+}
+
+/// Where the bind parameters were referenced while building a query.
+#[derive(Default)]
+pub(crate) struct BindPositions {
+    /// The byte range of each placeholder in the SQL, and the index of its parameter.
+    pub(crate) placeholders: Vec<(Range<usize>, usize)>,
+    /// Indexes of the parameters that shouldn't be shown.
+    pub(crate) sensitive: Vec<usize>,
+}
+
+/// A query rendered for debugging, see [`QueryBuilder::debug_query`](super::QueryBuilder::debug_query).
+///
+/// By default, the query is displayed as its SQL followed by the list of bind parameters
+/// and their types:
+///
+/// ```text
+/// SELECT * FROM "users" WHERE "name" = $1 -- binds: ['asphalt' (text)]
+/// ```
+///
+/// With [`inline_binds`](DebugQuery::inline_binds), the placeholders are replaced by
+/// the parameters instead, which is useful to copy and paste the query in a database
+/// shell. Parameters marked as sensitive are never displayed.
+#[derive(Debug, Clone)]
+pub struct DebugQuery {
+    sql: String,
+    binds: Vec<DebugBind>,
+    positions: Vec<(Range<usize>, usize)>,
+    sensitive: Vec<bool>,
+    inline: bool,
+}
+
+const REDACTED: &str = "<redacted>";
+
+impl DebugQuery {
+    pub(crate) fn new(sql: String, binds: Vec<DebugBind>, positions: &BindPositions) -> Self {
+        let mut sensitive = vec![false; binds.len()];
+        for &idx in &positions.sensitive {
+            sensitive[idx] = true;
+        }
+
+        Self {
+            sql,
+            binds,
+            positions: positions.placeholders.clone(),
+            sensitive,
+            inline: false,
+        }
+    }
+
+    /// Returns the SQL of the query, with placeholders for the bind parameters.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Returns the bind parameters of the query, including the sensitive ones.
+    pub fn binds(&self) -> &[DebugBind] {
+        &self.binds
+    }
+
+    /// Replace the placeholders in the SQL by the bind parameters when displayed.
+    ///
+    /// Sensitive parameters are replaced by `NULL`.
+    pub fn inline_binds(mut self) -> Self {
+        self.inline = true;
+        self
+    }
+
+    /// Don't display the bind parameter with the given index.
+    ///
+    /// # Panics
+    ///
+    /// If there is no parameter with this index.
+    pub fn redact(mut self, idx: usize) -> Self {
+        self.sensitive[idx] = true;
+        self
+    }
+
+    fn fmt_inlined(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut last = 0;
+
+        for (range, idx) in &self.positions {
+            f.write_str(&self.sql[last..range.start])?;
+            if self.sensitive[*idx] {
+                write!(f, "NULL /* {} */", REDACTED)?;
+            } else {
+                f.write_str(&self.binds[*idx].literal)?;
+            }
+            last = range.end;
+        }
+
+        f.write_str(&self.sql[last..])
+    }
+}
+
+impl fmt::Display for DebugQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.inline {
+            return self.fmt_inlined(f);
+        }
+
+        write!(f, "{} -- binds: [", self.sql)?;
+        for (idx, bind) in self.binds.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(", ")?;
+            }
+
+            let literal = if self.sensitive[idx] {
+                REDACTED
+            } else {
+                &bind.literal
+            };
+            write!(f, "{} ({})", literal, bind.sql_type)?;
+        }
+        f.write_str("]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query() -> DebugQuery {
+        let sql = String::from("UPDATE users SET password = $2 WHERE name = $1 OR alias = $1");
+        let binds = vec![
+            DebugBind {
+                sql_type: String::from("text"),
+                literal: String::from("'o''brien'"),
+            },
+            DebugBind {
+                sql_type: String::from("text"),
+                literal: String::from("'hunter2'"),
+            },
+        ];
+        let positions = BindPositions {
+            placeholders: vec![(28..30, 1), (44..46, 0), (58..60, 0)],
+            sensitive: vec![1],
+        };
+
+        DebugQuery::new(sql, binds, &positions)
+    }
+
+    #[test]
+    fn lists_bind_parameters() {
+        assert_eq!(
+            query().to_string(),
+            "UPDATE users SET password = $2 WHERE name = $1 OR alias = $1 \
+             -- binds: ['o''brien' (text), <redacted> (text)]"
+        );
+    }
+
+    #[test]
+    fn inlines_bind_parameters() {
+        assert_eq!(
+            query().inline_binds().to_string(),
+            "UPDATE users SET password = NULL /* <redacted> */ \
+             WHERE name = 'o''brien' OR alias = 'o''brien'"
+        );
+        assert_eq!(
+            query().redact(0).inline_binds().to_string(),
+            "UPDATE users SET password = NULL /* <redacted> */ \
+             WHERE name = NULL /* <redacted> */ OR alias = NULL /* <redacted> */"
+        );
+    }
+}
@@ -1,38 +1,151 @@
 use crate::connection::{IsolationLevel, RawConnection, TransactionConfig, TransactionManager};
 use crate::error::QueryResult;
 use futures_core::future::LocalBoxFuture;
-use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
-
-/// An implementation of [`TransactionManager`] for the backends which use the ANSI syntax
-/// for transactions and savepoint, such as PostgreSQL and SQLite.
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+
+/// The state of the transactions of a connection, shared by the transaction managers
+/// which use savepoints for nested transactions.
+///
+/// Only how the outermost transaction is started differs between these managers, the
+/// savepoints, commits and rollbacks, and the tracking of the transaction depth and of
+/// broken connections are all handled here.
 #[derive(Debug, Default)]
-pub struct AnsiTransactionManager {
+pub struct TransactionState {
     depth: AtomicU8,
+    generation: AtomicU64,
     broken: AtomicBool,
 }
 
-impl AnsiTransactionManager {
-    fn current_depth(&self) -> u8 {
+impl TransactionState {
+    /// Returns the number of open transactions, including savepoints.
+    ///
+    /// Zero means that the connection isn't inside a transaction.
+    pub fn transaction_depth(&self) -> u8 {
         self.depth.load(Ordering::Acquire)
     }
 
-    fn increment_depth(&self, query: QueryResult<()>) -> QueryResult<()> {
-        if query.is_ok() {
+    /// Returns a counter incremented every time a transaction or savepoint is rolled back,
+    /// and when the outermost transaction is committed.
+    ///
+    /// These are the only operations which drop the state created inside a transaction,
+    /// e.g. cursors, so while the counter doesn't change, such state is still alive.
+    pub fn transaction_generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Begins a transaction, starting the outermost one with `first_transaction`.
+    pub async fn begin<Conn, F>(
+        &self,
+        conn: &Conn,
+        first_transaction: impl FnOnce() -> F,
+    ) -> QueryResult<()>
+    where
+        Conn: RawConnection,
+        F: Future<Output = QueryResult<()>>,
+    {
+        let depth = self.transaction_depth();
+
+        let res = if depth == 0 {
+            first_transaction().await
+        } else {
+            let stmt = format!("SAVEPOINT asphalt_savepoint_{}", depth);
+            conn.simple_execute(&stmt).await
+        };
+
+        if res.is_ok() {
             self.depth.fetch_add(1, Ordering::Relaxed);
         }
-        query
+        res
     }
 
-    fn decrement_depth(&self, query: QueryResult<()>) -> QueryResult<()> {
-        if query.is_ok() {
-            self.depth.fetch_sub(1, Ordering::Relaxed);
+    /// Commits the innermost transaction.
+    ///
+    /// A transaction which fails to commit is rolled back, as it may still be open, e.g.
+    /// after a serialization failure.
+    pub async fn commit<Conn>(&self, conn: &Conn) -> QueryResult<()>
+    where
+        Conn: RawConnection,
+    {
+        let depth = self.transaction_depth();
+        let stmt = match depth {
+            0 => panic!("Tried to commit with no transaction opened!"),
+            1 => String::from("COMMIT"),
+            _ => format!("RELEASE SAVEPOINT asphalt_savepoint_{}", depth - 1),
+        };
+
+        match conn.simple_execute(&stmt).await {
+            Ok(()) => {
+                if depth == 1 {
+                    self.generation.fetch_add(1, Ordering::Release);
+                }
+                self.depth.fetch_sub(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(err) => {
+                self.rollback(conn).await?;
+                Err(err)
+            }
         }
-        query
     }
 
-    fn set_broken(&self) {
+    /// Rollbacks the innermost transaction.
+    ///
+    /// A failed rollback leaves the connection inside an unaborted transaction, so it is
+    /// marked as broken.
+    pub async fn rollback<Conn>(&self, conn: &Conn) -> QueryResult<()>
+    where
+        Conn: RawConnection,
+    {
+        let depth = self.transaction_depth();
+        let stmt = match depth {
+            0 => panic!("Tried to rollback with no transaction opened!"),
+            1 => String::from("ROLLBACK"),
+            _ => format!("ROLLBACK TO SAVEPOINT asphalt_savepoint_{}", depth - 1),
+        };
+
+        let res = conn.simple_execute(&stmt).await;
+        // Even failed rollbacks may have dropped the state of the transaction.
+        self.generation.fetch_add(1, Ordering::Release);
+        match res {
+            Ok(()) => {
+                self.depth.fetch_sub(1, Ordering::Relaxed);
+            }
+            Err(_) => self.mark_broken(),
+        }
+        res
+    }
+
+    /// Is the connection broken?
+    pub fn is_broken(&self) -> bool {
+        self.broken.load(Ordering::Relaxed)
+    }
+
+    /// Marks the connection as broken.
+    pub fn mark_broken(&self) {
         self.broken.store(true, Ordering::Release);
     }
+}
+
+/// An implementation of [`TransactionManager`] for the backends which use the ANSI syntax
+/// for transactions and savepoint, such as PostgreSQL and SQLite.
+#[derive(Debug, Default)]
+pub struct AnsiTransactionManager {
+    state: TransactionState,
+}
+
+impl AnsiTransactionManager {
+    /// Returns the number of open transactions, including savepoints.
+    ///
+    /// Zero means that the connection isn't inside a transaction.
+    pub fn transaction_depth(&self) -> u8 {
+        self.state.transaction_depth()
+    }
+
+    /// See [`TransactionState::transaction_generation`].
+    pub fn transaction_generation(&self) -> u64 {
+        self.state.transaction_generation()
+    }
 
     fn first_transaction<Db>(&self, config: TransactionConfig) -> String {
         let mut stmt = String::from("BEGIN");
@@ -64,68 +177,62 @@ where
         config: TransactionConfig,
         conn: &'c Conn,
     ) -> LocalBoxFuture<'c, QueryResult<()>> {
-        Box::pin(async move {
-            let depth = self.current_depth();
-
-            let stmt = if depth == 0 {
-                self.first_transaction::<Conn::Backend>(config)
-            } else {
-                format!("SAVEPOINT asphalt_savepoint_{}", depth)
-            };
-
-            let res = conn.simple_execute(&stmt).await;
-            self.increment_depth(res)
-        })
+        Box::pin(self.state.begin(conn, move || async move {
+            let stmt = self.first_transaction::<Conn::Backend>(config);
+            conn.simple_execute(&stmt).await
+        }))
     }
 
     fn commit_transaction<'c>(&'c self, conn: &'c Conn) -> LocalBoxFuture<'c, QueryResult<()>> {
-        Box::pin(async move {
-            let depth = self.current_depth();
-            match depth {
-                0 => panic!("Tried to commit with no transaction opened!"),
-                1 => match conn.simple_execute("COMMIT").await {
-                    Err(err) => {
-                        if err.kind().is_serialization_failure()
-                            || err.kind().is_read_only_transaction()
-                        {
-                            if let Err(err) =
-                                self.decrement_depth(conn.simple_execute("ROLLBACK").await)
-                            {
-                                self.set_broken();
-                                return Err(err);
-                            }
-                        }
-                        Err(err)
-                    }
-                    e => self.decrement_depth(e),
-                },
-                _ => {
-                    let qry = format!("RELEASE SAVEPOINT asphalt_savepoint_{}", depth - 1);
-                    let res = conn.simple_execute(&qry).await;
-
-                    self.decrement_depth(res)
-                }
-            }
-        })
+        Box::pin(self.state.commit(conn))
     }
 
     fn rollback_transaction<'c>(&'c self, conn: &'c Conn) -> LocalBoxFuture<'c, QueryResult<()>> {
-        Box::pin(async move {
-            let depth = self.current_depth();
-            match depth {
-                0 => panic!("Tried to rollback with no transaction opened!"),
-                1 => self.decrement_depth(conn.simple_execute("ROLLBACK").await),
-                _ => {
-                    let qry = format!("ROLLBACK TO SAVEPOINT asphalt_savepoint_{}", depth - 1);
-                    let res = conn.simple_execute(&qry).await;
-
-                    self.decrement_depth(res)
-                }
-            }
-        })
+        Box::pin(self.state.rollback(conn))
     }
 
     fn is_broken(&self) -> bool {
-        self.broken.load(Ordering::Relaxed)
+        self.state.is_broken()
+    }
+
+    fn mark_broken(&self) {
+        self.state.mark_broken();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connection::test_db::connection;
+    use crate::connection::RawConnection;
+    use crate::error::Error;
+    use futures_util::future::FutureExt;
+
+    #[test]
+    fn counts_the_transactions_which_drop_their_state() {
+        let conn = connection(&[]);
+        let generation = || {
+            conn.raw_connection()
+                .transaction_manager()
+                .transaction_generation()
+        };
+
+        let res = conn
+            .transaction(async {
+                // Released savepoints keep their state in the outer transaction.
+                conn.transaction(async { Ok::<_, Error>(()) }).await?;
+                assert_eq!(generation(), 0);
+
+                conn.transaction(async { Err::<(), _>(Error::rollback_transaction()) })
+                    .await
+                    .unwrap_err();
+                assert_eq!(generation(), 1);
+
+                Ok::<_, Error>(())
+            })
+            .now_or_never()
+            .unwrap();
+
+        assert!(res.is_ok());
+        assert_eq!(generation(), 2);
     }
 }
@@ -34,15 +34,42 @@ where
     type Nullable = T::Nullable;
 }
 
+/// Static information about a SQL type, e.g. to describe the columns of a table.
+pub trait SqlType {
+    /// The name of the type in SQL, e.g. `BIGINT`.
+    ///
+    /// The name is a portable label of the type, not DDL: backends may spell the type
+    /// differently, e.g. `DOUBLE` is `DOUBLE PRECISION` in PostgreSQL, and modifiers like
+    /// the length of a `VARCHAR` or the precision of a `NUMERIC` aren't included, as
+    /// the Rust types don't carry them.
+    ///
+    /// Arrays and nullable types have the name of their innermost type.
+    const NAME: &'static str;
+    /// The number of array dimensions of the type, `0` for non-array types.
+    const ARRAY_DIMENSIONS: u32 = 0;
+    /// Can values of the type be `NULL`?
+    const IS_NULLABLE: bool = false;
+}
+
 /// A nullable SQL type.
 ///
 /// By default, all types are assumed to be `NOT NULL`. This type wraps another one
 /// indicating that this can be null.
 pub struct Nullable<T: NotNull>(T);
 
+impl<T> SqlType for Nullable<T>
+where
+    T: NotNull + SqlType,
+{
+    const NAME: &'static str = T::NAME;
+    const ARRAY_DIMENSIONS: u32 = T::ARRAY_DIMENSIONS;
+    const IS_NULLABLE: bool = true;
+}
+
 /// Define a new SQL type.
 ///
-/// All types generated by this macro are marked as `NotNull`.
+/// All types generated by this macro are marked as `NotNull`, and are named in
+/// SQL by their Rust name.
 #[macro_export]
 macro_rules! define_sql_type {
     ($(#[$meta: meta])* $typ: ident $(<$($gen: ident),+>)?) => {
@@ -50,5 +77,9 @@ macro_rules! define_sql_type {
         pub struct $typ$(<$($gen),+>)?;
 
         impl$(<$($gen),+>)? $crate::types::NotNull for $typ$(<$($gen: ident),+>)? {};
+
+        impl$(<$($gen),+>)? $crate::types::SqlType for $typ$(<$($gen),+>)? {
+            const NAME: &'static str = stringify!($typ);
+        }
     };
 }
@@ -19,6 +19,10 @@ macro_rules! define_sql_types {
             
             impl $crate::types::NotNull for $sql_ty {}
 
+            impl $crate::types::SqlType for $sql_ty {
+                const NAME: &'static str = $sql_name;
+            }
+
             $(__define_aliases!($($alias_ty)+, $sql_ty, stringify!($sql_ty));)?
         )*
     };
@@ -46,3 +50,14 @@ define_sql_types! {
 
 /// The `ARRAY` SQL type.
 pub struct Array<SqlTy>(SqlTy);
+
+impl<SqlTy> crate::types::SqlType for Array<SqlTy>
+where
+    SqlTy: crate::types::SqlType,
+{
+    const NAME: &'static str = SqlTy::NAME;
+    const ARRAY_DIMENSIONS: u32 = SqlTy::ARRAY_DIMENSIONS + 1;
+}
+
+// The array itself can't be null, even if its elements can.
+impl<SqlTy> crate::types::NotNull for Array<SqlTy> {}
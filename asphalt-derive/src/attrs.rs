@@ -0,0 +1,105 @@
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Ident, LitStr, Result, Token, Type};
+
+/// A single item inside an `#[asphalt(...)]` attribute.
+enum Item {
+    /// `by_position`
+    ByPosition(Ident),
+    /// `sql_type = Type`
+    SqlType(Ident, Box<Type>),
+    /// `column = "name"`
+    Column(Ident, LitStr),
+}
+
+impl Item {
+    fn ident(&self) -> &Ident {
+        match self {
+            Self::ByPosition(ident) | Self::SqlType(ident, _) | Self::Column(ident, _) => ident,
+        }
+    }
+}
+
+impl Parse for Item {
+    fn parse(input: ParseStream<'_>) -> Result<Self> {
+        let ident: Ident = input.parse()?;
+
+        match ident.to_string().as_str() {
+            "by_position" => Ok(Self::ByPosition(ident)),
+            "sql_type" => {
+                input.parse::<Token![=]>()?;
+                Ok(Self::SqlType(ident, Box::new(input.parse()?)))
+            }
+            "column" => {
+                input.parse::<Token![=]>()?;
+                Ok(Self::Column(ident, input.parse()?))
+            }
+            _ => Err(syn::Error::new(
+                ident.span(),
+                format!("unknown asphalt attribute `{}`", ident),
+            )),
+        }
+    }
+}
+
+fn parse_items(attrs: &[Attribute]) -> Result<Vec<Item>> {
+    let mut items = Vec::new();
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("asphalt")) {
+        let parsed = attr.parse_args_with(Punctuated::<Item, Token![,]>::parse_terminated)?;
+        items.extend(parsed);
+    }
+
+    Ok(items)
+}
+
+fn unexpected(item: &Item) -> syn::Error {
+    syn::Error::new(
+        item.ident().span(),
+        format!("`{}` is not allowed here", item.ident()),
+    )
+}
+
+/// Attributes allowed in the struct.
+#[derive(Default)]
+pub(crate) struct StructAttrs {
+    pub(crate) by_position: bool,
+}
+
+impl StructAttrs {
+    pub(crate) fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut parsed = Self::default();
+
+        for item in parse_items(attrs)? {
+            match item {
+                Item::ByPosition(_) => parsed.by_position = true,
+                item => return Err(unexpected(&item)),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Attributes allowed in each field.
+#[derive(Default)]
+pub(crate) struct FieldAttrs {
+    pub(crate) sql_type: Option<Type>,
+    pub(crate) column: Option<LitStr>,
+}
+
+impl FieldAttrs {
+    pub(crate) fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut parsed = Self::default();
+
+        for item in parse_items(attrs)? {
+            match item {
+                Item::SqlType(_, ty) => parsed.sql_type = Some(*ty),
+                Item::Column(_, name) => parsed.column = Some(name),
+                item => return Err(unexpected(&item)),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,310 @@
+use crate::attrs::{FieldAttrs, StructAttrs};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{
+    Data, DeriveInput, Fields, GenericArgument, Ident, Lifetime, LifetimeDef, PathArguments,
+    Result, Type,
+};
+
+pub(crate) fn derive(input: DeriveInput) -> Result<TokenStream> {
+    let attrs = StructAttrs::parse(&input.attrs)?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "FromRow can only be derived for structs",
+            ))
+        }
+    };
+
+    if let Fields::Unnamed(_) = fields {
+        if !attrs.by_position {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "tuple structs need `#[asphalt(by_position)]` to derive FromRow",
+            ));
+        }
+    }
+
+    let mut bounds = Vec::with_capacity(fields.len());
+    let mut values = Vec::with_capacity(fields.len());
+
+    // Rows are borrowed for the lifetime of the struct, so that fields like `&str`
+    // can be used. Structs without lifetimes can be deserialized from any row.
+    let row_lt = match input.generics.lifetimes().next() {
+        Some(def) => def.lifetime.clone(),
+        None => Lifetime::new("'__r", Span::call_site()),
+    };
+
+    for (idx, field) in fields.iter().enumerate() {
+        let field_attrs = FieldAttrs::parse(&field.attrs)?;
+        let field_ty = &field.ty;
+        let sql_ty = match field_attrs.sql_type {
+            Some(sql_ty) if is_option(field_ty) && !is_nullable(&sql_ty) => {
+                quote!(::asphalt_core::types::Nullable<#sql_ty>)
+            }
+            Some(sql_ty) => quote!(#sql_ty),
+            None => default_sql_type(field_ty).ok_or_else(|| {
+                syn::Error::new(
+                    field_ty.span(),
+                    "can't infer the SQL type of this field, \
+                     use `#[asphalt(sql_type = ...)]` to give it",
+                )
+            })?,
+        };
+
+        let name = match &field.ident {
+            Some(ident) => ident.unraw().to_string(),
+            None => idx.to_string(),
+        };
+
+        let value = if attrs.by_position {
+            quote! {
+                ::asphalt_core::connection::get_field::<__R, #sql_ty, #field_ty>(row, #name, #idx)?
+            }
+        } else {
+            let column = field_attrs
+                .column
+                .map_or(name.clone(), |column| column.value());
+            quote! {
+                ::asphalt_core::connection::get_named_field::<__R, #sql_ty, #field_ty>(
+                    row, #name, #column,
+                )?
+            }
+        };
+
+        values.push(match &field.ident {
+            Some(ident) => quote!(#ident: #value),
+            None => value,
+        });
+        bounds.push(quote! {
+            __R::Backend: ::asphalt_core::backend::HasSqlType<#sql_ty>,
+            #field_ty: ::asphalt_core::types::FromSql<#row_lt, #sql_ty, __R::Backend>,
+        });
+    }
+
+    let body = match fields {
+        Fields::Named(_) => quote!(Self { #(#values,)* }),
+        Fields::Unnamed(_) => quote!(Self(#(#values,)*)),
+        Fields::Unit => quote!(Self),
+    };
+
+    let ident = &input.ident;
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let predicates = where_clause.map(|clause| &clause.predicates);
+
+    let mut generics = input.generics.clone();
+    if generics.lifetimes().next().is_none() {
+        generics
+            .params
+            .insert(0, LifetimeDef::new(row_lt.clone()).into());
+    }
+    generics.params.push(syn::parse_quote!(__R));
+    let (impl_generics, _, _) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::asphalt_core::connection::FromRow<#row_lt, __R> for #ident #ty_generics
+        where
+            __R: ::asphalt_core::connection::Row,
+            #(#bounds)*
+            #predicates
+        {
+            fn from_row(row: &#row_lt __R) -> ::asphalt_core::error::QueryResult<Self> {
+                Ok(#body)
+            }
+        }
+    })
+}
+
+/// Returns the SQL type of fields without `#[asphalt(sql_type = ...)]`.
+///
+/// Only the Rust types that are read from a single SQL type have a default, and
+/// `Option`s of them are read from the nullable type.
+fn default_sql_type(ty: &Type) -> Option<TokenStream> {
+    if let Some(inner) = type_argument(ty, "Option") {
+        let inner = default_sql_type(inner)?;
+        return Some(quote!(::asphalt_core::types::Nullable<#inner>));
+    }
+
+    let name = match ty {
+        Type::Reference(reference) => match &*reference.elem {
+            Type::Path(path) if path.path.is_ident("str") => "Text",
+            Type::Slice(slice) if is_u8(&slice.elem) => "Binary",
+            _ => return None,
+        },
+        Type::Path(path) if path.qself.is_none() && path.path.segments.len() == 1 => {
+            match path.path.segments[0].ident.to_string().as_str() {
+                "bool" => "Bool",
+                "i8" => "TinyInt",
+                "i16" => "SmallInt",
+                "i32" => "Integer",
+                "i64" => "BigInt",
+                "f32" => "Float",
+                "f64" => "Double",
+                "String" => "Text",
+                "Vec" if matches!(type_argument(ty, "Vec"), Some(arg) if is_u8(arg)) => "Binary",
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let name = Ident::new(name, Span::call_site());
+    Some(quote!(::asphalt_core::types::#name))
+}
+
+fn is_u8(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident("u8"))
+}
+
+/// Is `ty` an `Option<T>`?
+fn is_option(ty: &Type) -> bool {
+    type_argument(ty, "Option").is_some()
+}
+
+/// Is the SQL type `ty` already nullable?
+fn is_nullable(ty: &Type) -> bool {
+    type_argument(ty, "Nullable").is_some()
+}
+
+/// Returns `T` if `ty` is a `name<T>`.
+fn type_argument<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != name {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn error(input: DeriveInput) -> String {
+        derive(input).err().unwrap().to_string()
+    }
+
+    fn sql_type(ty: Type) -> Option<String> {
+        default_sql_type(&ty).map(|sql_ty| sql_ty.to_string())
+    }
+
+    #[test]
+    fn infers_the_sql_type_of_common_types() {
+        let expected = |sql_ty: TokenStream| Some(sql_ty.to_string());
+
+        assert_eq!(
+            sql_type(parse_quote!(String)),
+            expected(quote!(::asphalt_core::types::Text))
+        );
+        assert_eq!(
+            sql_type(parse_quote!(&'a str)),
+            expected(quote!(::asphalt_core::types::Text))
+        );
+        assert_eq!(
+            sql_type(parse_quote!(i32)),
+            expected(quote!(::asphalt_core::types::Integer))
+        );
+        assert_eq!(
+            sql_type(parse_quote!(i64)),
+            expected(quote!(::asphalt_core::types::BigInt))
+        );
+        assert_eq!(
+            sql_type(parse_quote!(bool)),
+            expected(quote!(::asphalt_core::types::Bool))
+        );
+        assert_eq!(
+            sql_type(parse_quote!(Vec<u8>)),
+            expected(quote!(::asphalt_core::types::Binary))
+        );
+        assert_eq!(
+            sql_type(parse_quote!(Option<i32>)),
+            expected(quote!(
+                ::asphalt_core::types::Nullable<::asphalt_core::types::Integer>
+            ))
+        );
+    }
+
+    #[test]
+    fn doesnt_infer_the_sql_type_of_other_types() {
+        assert_eq!(sql_type(parse_quote!(Vec<i32>)), None);
+        assert_eq!(sql_type(parse_quote!(Option<Uuid>)), None);
+        assert_eq!(sql_type(parse_quote!(u32)), None);
+        assert_eq!(sql_type(parse_quote!(std::string::String)), None);
+    }
+
+    #[test]
+    fn requires_the_sql_type_when_it_cant_be_inferred() {
+        let input = parse_quote! {
+            struct User {
+                id: Uuid,
+            }
+        };
+
+        assert!(error(input).starts_with("can't infer the SQL type of this field"));
+    }
+
+    #[test]
+    fn rejects_enums() {
+        let input = parse_quote! {
+            enum User {
+                Admin,
+            }
+        };
+
+        assert_eq!(error(input), "FromRow can only be derived for structs");
+    }
+
+    #[test]
+    fn rejects_tuple_structs_not_mapped_by_position() {
+        let input = parse_quote! {
+            struct User(i32, String);
+        };
+
+        assert_eq!(
+            error(input),
+            "tuple structs need `#[asphalt(by_position)]` to derive FromRow"
+        );
+    }
+
+    #[test]
+    fn rejects_misplaced_and_unknown_attributes() {
+        let input = parse_quote! {
+            #[asphalt(column = "id")]
+            struct User {
+                id: i32,
+            }
+        };
+        assert_eq!(error(input), "`column` is not allowed here");
+
+        let input = parse_quote! {
+            struct User {
+                #[asphalt(by_position)]
+                id: i32,
+            }
+        };
+        assert_eq!(error(input), "`by_position` is not allowed here");
+
+        let input = parse_quote! {
+            struct User {
+                #[asphalt(rename = "user_id")]
+                id: i32,
+            }
+        };
+        assert_eq!(error(input), "unknown asphalt attribute `rename`");
+    }
+}
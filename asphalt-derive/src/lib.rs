@@ -0,0 +1,24 @@
+//! Derive macros for asphalt.
+//!
+//! The macros in this crate are re-exported by `asphalt_core` when its `derive` feature
+//! is enabled, and should be used from there.
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod attrs;
+mod from_row;
+
+/// Implements `FromRow` for a struct.
+///
+/// See the documentation of `asphalt_core::connection::FromRow` for the available
+/// attributes.
+#[proc_macro_derive(FromRow, attributes(asphalt))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    from_row::derive(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
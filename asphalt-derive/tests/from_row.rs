@@ -0,0 +1,180 @@
+use asphalt_core::connection::{Connection, FromRow};
+use asphalt_core::error::{Error, ErrorKind, QueryResult};
+use asphalt_core::query::QueryBuilder;
+use asphalt_core::types::{BigInt, Nullable, Text};
+use asphalt_mock::{Mock, MockDatabase, MockRow, Response, Value};
+use futures_executor::block_on;
+use futures_util::TryStreamExt;
+
+#[derive(Debug, PartialEq, FromRow)]
+struct User {
+    id: i32,
+    #[asphalt(column = "user_name")]
+    name: String,
+    email: Option<String>,
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+#[asphalt(by_position)]
+struct Count(#[asphalt(sql_type = BigInt)] i64, Option<bool>);
+
+#[derive(Debug, PartialEq, FromRow)]
+struct Tagged {
+    #[asphalt(sql_type = Text)]
+    tag: String,
+    #[asphalt(sql_type = Nullable<Text>)]
+    description: Option<String>,
+    #[asphalt(sql_type = Text)]
+    r#type: Option<String>,
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+struct Borrowed<'a> {
+    name: &'a str,
+}
+
+fn connection(db: &MockDatabase) -> Connection<Mock> {
+    block_on(Connection::establish(db.clone())).unwrap()
+}
+
+fn query<'a>(conn: &'a Connection<Mock>, sql: &str) -> QueryBuilder<'a, 'static, Mock> {
+    let mut query = conn.query_builder();
+    query.push_sql(sql);
+    query
+}
+
+fn load<T>(db: &MockDatabase, sql: &str) -> QueryResult<Vec<T>>
+where
+    T: for<'r> FromRow<'r, MockRow>,
+{
+    let conn = connection(db);
+    block_on(conn.load(query(&conn, sql)))
+}
+
+/// Returns the message of a deserialization error.
+fn deserialization_error(err: Error) -> String {
+    match err.kind() {
+        ErrorKind::DeserializationError(err) => err.to_string(),
+        kind => panic!("Expected a deserialization error, got {:?}", kind),
+    }
+}
+
+#[test]
+fn reads_fields_by_name() {
+    let db = MockDatabase::new();
+    db.when("SELECT * FROM users").respond(Response::rows(
+        &["email", "user_name", "id"],
+        vec![
+            vec!["asphalt@example.com".into(), "asphalt".into(), 1.into()],
+            vec![Value::Null, "mock".into(), 2.into()],
+        ],
+    ));
+
+    let users: Vec<User> = load(&db, "SELECT * FROM users").unwrap();
+    assert_eq!(
+        users,
+        vec![
+            User {
+                id: 1,
+                name: String::from("asphalt"),
+                email: Some(String::from("asphalt@example.com")),
+            },
+            User {
+                id: 2,
+                name: String::from("mock"),
+                email: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn reads_fields_by_position() {
+    let db = MockDatabase::new();
+    db.when("SELECT count(*), bool_and(active) FROM users")
+        .respond(Response::rows(
+            &["count", "bool_and"],
+            vec![vec![2i64.into(), Value::Null]],
+        ));
+
+    let counts: Vec<Count> = load(&db, "SELECT count(*), bool_and(active) FROM users").unwrap();
+    assert_eq!(counts, vec![Count(2, None)]);
+}
+
+#[test]
+fn reads_fields_with_explicit_sql_types() {
+    let db = MockDatabase::new();
+    db.when("SELECT * FROM tags").respond(Response::rows(
+        &["tag", "description", "type"],
+        vec![vec!["rust".into(), Value::Null, "language".into()]],
+    ));
+
+    let tags: Vec<Tagged> = load(&db, "SELECT * FROM tags").unwrap();
+    assert_eq!(
+        tags,
+        vec![Tagged {
+            tag: String::from("rust"),
+            description: None,
+            r#type: Some(String::from("language")),
+        }]
+    );
+}
+
+#[test]
+fn borrows_fields_from_the_row() {
+    let db = MockDatabase::new();
+    db.when("SELECT name FROM users")
+        .respond(Response::rows(&["name"], vec![vec!["asphalt".into()]]));
+    let conn = connection(&db);
+
+    let rows: Vec<MockRow> = block_on(async {
+        conn.query(query(&conn, "SELECT name FROM users"))
+            .await?
+            .try_collect()
+            .await
+    })
+    .unwrap();
+
+    let user = Borrowed::from_row(&rows[0]).unwrap();
+    assert_eq!(user, Borrowed { name: "asphalt" });
+}
+
+#[test]
+fn names_the_field_that_failed() {
+    let db = MockDatabase::new();
+    db.when("SELECT * FROM users").respond(Response::rows(
+        &["id", "user_name", "email"],
+        vec![vec!["one".into(), "asphalt".into(), Value::Null]],
+    ));
+    db.when("SELECT id, email FROM users")
+        .respond(Response::rows(
+            &["id", "email"],
+            vec![vec![1.into(), Value::Null]],
+        ));
+
+    let err = load::<User>(&db, "SELECT * FROM users").err().unwrap();
+    assert!(deserialization_error(err).starts_with("Invalid value for field `id`: "));
+
+    let err = load::<User>(&db, "SELECT id, email FROM users")
+        .err()
+        .unwrap();
+    assert_eq!(
+        deserialization_error(err),
+        "No column `user_name` for field `name`"
+    );
+}
+
+#[test]
+fn reports_missing_columns_read_by_position() {
+    let db = MockDatabase::new();
+    db.when("SELECT count(*) FROM users")
+        .respond(Response::rows(&["count"], vec![vec![2i64.into()]]));
+
+    let err = load::<Count>(&db, "SELECT count(*) FROM users")
+        .err()
+        .unwrap();
+    assert_eq!(
+        deserialization_error(err),
+        "Expected at least 2 columns, but the row has 1"
+    );
+}
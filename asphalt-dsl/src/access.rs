@@ -1,5 +1,4 @@
-use crate::expressions::SqlTypeOf;
-use crate::schemas::{IsTable, AllColumns};
+use crate::schemas::{AllColumns, IsTable};
 use asphalt_core::backend::{Backend, HasSqlType};
 use asphalt_core::connection::Connection;
 use asphalt_core::types::Bool;
@@ -19,13 +18,12 @@ pub struct Access<Db: Backend> {
 // let user = conn.from(users::table).get_one(id).await?;
 // conn.update(users::table).set(..).filter(..).await?;
 // conn.insert_into(users::table).values(..).await?;
-// [user_id.eq(id): BoolOp<user_id, _, {Eq}>]
 //
 impl<Db: Backend> Access<Db> {
     /// Create a `SELECT` query from the provided table.
     pub fn from<T: IsTable>(&self) -> Select<'_, Db, T, AllColumns<T>>
     where
-        Db: HasSqlType<Bool> + HasSqlType<SqlTypeOf<T::AllColumns>>,
+        Db: HasSqlType<Bool>,
     {
         Select::from_table(self)
     }
@@ -1,8 +1,14 @@
 use super::Access;
 use crate::expressions::{Condition, IsExpression, PredicateOn, SqlTypeOf};
+use crate::query::QueryFragment;
 use crate::schemas::{IsTable, AllColumns};
 use asphalt_core::backend::{Backend, HasSqlType};
-use asphalt_core::types::Bool;
+use asphalt_core::connection::{FromRow, RowOf};
+use asphalt_core::error::QueryResult;
+use asphalt_core::query::{DebugQuery, QueryBuilder};
+use asphalt_core::types::{Bool, FromSql};
+use asphalt_core::LocalBoxFuture;
+use futures_core::stream::BoxStream;
 use std::marker::PhantomData;
 
 /// A `SELECT` SQL query.
@@ -15,7 +21,7 @@ use std::marker::PhantomData;
 pub struct Select<'a, Db, T, Sel>
 where
     Sel: IsExpression,
-    Db: Backend + HasSqlType<Bool> + HasSqlType<SqlTypeOf<Sel>>,
+    Db: Backend + HasSqlType<Bool>,
 {
     access: &'a Access<Db>,
     selection: Sel,
@@ -25,13 +31,13 @@ where
 
 impl<'a, Db, T> Select<'a, Db, T, AllColumns<T>>
 where
-    Db: Backend + HasSqlType<Bool> + HasSqlType<SqlTypeOf<AllColumns<T>>>,
+    Db: Backend + HasSqlType<Bool>,
     T: IsTable,
 {
     pub(crate) fn from_table(access: &'a Access<Db>) -> Self {
         Select {
             access,
-            selection: Default::default(),
+            selection: T::ALL_COLUMNS,
             where_clause: Condition::r#true(),
             _phantom: PhantomData,
         }
@@ -41,7 +47,7 @@ where
 impl<'a, Db, T, Sel> Select<'a, Db, T, Sel>
 where
     Sel: IsExpression,
-    Db: Backend + HasSqlType<Bool> + HasSqlType<SqlTypeOf<Sel>>,
+    Db: Backend + HasSqlType<Bool>,
     T: IsTable,
 {
     /// Filter the current query with the given predicate.
@@ -80,3 +86,259 @@ where
         }
     }
 }
+
+impl<'a, Db, T, Sel> Select<'a, Db, T, Sel>
+where
+    Sel: IsExpression + QueryFragment<Db>,
+    Db: Backend + HasSqlType<Bool>,
+    T: IsTable,
+{
+    /// Renders the query for debugging.
+    ///
+    /// See [`DebugQuery`] for more info.
+    pub async fn debug_query(&self) -> QueryResult<DebugQuery>
+    where
+        Db: 'static,
+    {
+        Ok(self.to_query().await?.debug_query())
+    }
+
+    /// Executes the query, deserializing the single returned row.
+    ///
+    /// Fails with [`ErrorKind::NotFound`](asphalt_core::error::ErrorKind::NotFound) if
+    /// the query returns no rows, and with
+    /// [`ErrorKind::TooManyRows`](asphalt_core::error::ErrorKind::TooManyRows) if it
+    /// returns more than one.
+    pub async fn get_result<R>(&self) -> QueryResult<R>
+    where
+        Db: 'static,
+        R: for<'r> FromRow<'r, RowOf<Db>>,
+    {
+        self.access.conn.get_result(self.to_query().await?).await
+    }
+
+    /// Executes the query, deserializing the first returned row, if any.
+    pub async fn get_optional<R>(&self) -> QueryResult<Option<R>>
+    where
+        Db: 'static,
+        R: for<'r> FromRow<'r, RowOf<Db>>,
+    {
+        self.access.conn.get_optional(self.to_query().await?).await
+    }
+
+    /// Executes the query, deserializing all the returned rows.
+    pub async fn load<R>(&self) -> QueryResult<Vec<R>>
+    where
+        Db: 'static,
+        R: for<'r> FromRow<'r, RowOf<Db>>,
+    {
+        self.access.conn.load(self.to_query().await?).await
+    }
+
+    /// Executes the query, returning a stream of the deserialized rows.
+    pub async fn load_stream<R>(&self) -> QueryResult<BoxStream<'a, QueryResult<R>>>
+    where
+        Db: 'static,
+        R: for<'r> FromRow<'r, RowOf<Db>> + Send + 'a,
+    {
+        let access: &'a Access<Db> = self.access;
+        access.conn.load_stream(self.to_query().await?).await
+    }
+
+    /// Executes the query, deserializing the first selected column of the first returned row.
+    ///
+    /// Fails with [`ErrorKind::NotFound`](asphalt_core::error::ErrorKind::NotFound) if
+    /// the query returns no rows.
+    pub async fn get_scalar<R>(&self) -> QueryResult<R>
+    where
+        Db: HasSqlType<SqlTypeOf<Sel>> + 'static,
+        R: for<'r> FromSql<'r, SqlTypeOf<Sel>, Db>,
+    {
+        self.access
+            .conn
+            .get_scalar::<SqlTypeOf<Sel>, R>(self.to_query().await?)
+            .await
+    }
+
+    /// Builds the query with a new query builder.
+    async fn to_query(&self) -> QueryResult<QueryBuilder<'a, 'static, Db>>
+    where
+        Db: 'static,
+    {
+        let access: &'a Access<Db> = self.access;
+        let mut query = access.conn.query_builder();
+        self.build_query(query.reborrow()).await?;
+
+        Ok(query)
+    }
+}
+
+impl<Db, T, Sel> QueryFragment<Db> for Select<'_, Db, T, Sel>
+where
+    Sel: IsExpression + QueryFragment<Db>,
+    Db: Backend + HasSqlType<Bool>,
+    T: IsTable,
+{
+    fn build_query<'s, 'q: 's>(
+        &'s self,
+        mut out: QueryBuilder<'q, 's, Db>,
+    ) -> LocalBoxFuture<'s, QueryResult<()>> {
+        Box::pin(async move {
+            out.push_sql("SELECT ");
+            self.selection.build_query(out.reborrow()).await?;
+            out.push_sql(" FROM ");
+            T::DESCRIPTION.ident.build_query(out.reborrow()).await?;
+
+            if !self.where_clause.is_always_true() {
+                out.push_sql(" WHERE ");
+                self.where_clause.build_query(out.reborrow()).await?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::{Bound, CompareExpression, PredicateOn};
+    use asphalt_core::connection::Connection;
+    use asphalt_core::types::{Integer, Text};
+    use asphalt_mock::{Mock, MockDatabase};
+    use futures_executor::block_on;
+
+    mod schema {
+        use crate::table;
+        use asphalt_core::types::*;
+
+        table!(public.users {
+            pk user_id: Integer,
+            name: Text,
+        });
+
+        table!(public.accounts {
+            pk account_id: Integer,
+            #[sensitive] password: Text,
+        });
+    }
+
+    use schema::{accounts, users};
+
+    fn access() -> Access<Mock> {
+        let conn = block_on(Connection::establish(MockDatabase::new())).unwrap();
+        Access { conn }
+    }
+
+    fn int(value: i32) -> Bound<'static, Mock, Integer> {
+        Bound::Own(Box::new(value))
+    }
+
+    fn text(value: &'static str) -> Bound<'static, Mock, Text> {
+        Bound::Own(Box::new(value))
+    }
+
+    /// Renders the query selecting the users matching `predicate`.
+    fn render<'a, P>(access: &'a Access<Mock>, predicate: P) -> String
+    where
+        P: PredicateOn<'a, Mock, users::table> + 'a,
+    {
+        let select = access.from::<users::table>().filter(predicate);
+        block_on(select.debug_query()).unwrap().to_string()
+    }
+
+    #[test]
+    fn renders_comparisons() {
+        let access = access();
+
+        assert_eq!(
+            render(&access, users::user_id.eq(int(1))),
+            "SELECT \"public\".\"users\".\"user_id\", \"public\".\"users\".\"name\" \
+             FROM \"public\".\"users\" WHERE \"public\".\"users\".\"user_id\" = $1 \
+             -- binds: [1 (INTEGER)]"
+        );
+        assert_eq!(
+            render(&access, users::name.is_distinct_from(text("asphalt"))),
+            "SELECT \"public\".\"users\".\"user_id\", \"public\".\"users\".\"name\" \
+             FROM \"public\".\"users\" WHERE \"public\".\"users\".\"name\" IS DISTINCT FROM $1 \
+             -- binds: ['asphalt' (TEXT)]"
+        );
+    }
+
+    #[test]
+    fn renders_between() {
+        let access = access();
+
+        assert_eq!(
+            render(&access, users::user_id.between(int(1), int(10))),
+            "SELECT \"public\".\"users\".\"user_id\", \"public\".\"users\".\"name\" \
+             FROM \"public\".\"users\" WHERE \"public\".\"users\".\"user_id\" BETWEEN $1 AND $2 \
+             -- binds: [1 (INTEGER), 10 (INTEGER)]"
+        );
+    }
+
+    #[test]
+    fn renders_in() {
+        let access = access();
+
+        assert_eq!(
+            render(&access, users::user_id.eq_any(vec![int(1), int(2), int(3)])),
+            "SELECT \"public\".\"users\".\"user_id\", \"public\".\"users\".\"name\" \
+             FROM \"public\".\"users\" WHERE \"public\".\"users\".\"user_id\" IN ($1, $2, $3) \
+             -- binds: [1 (INTEGER), 2 (INTEGER), 3 (INTEGER)]"
+        );
+        assert_eq!(
+            render(
+                &access,
+                users::user_id.eq_any(Vec::<Bound<Mock, Integer>>::new())
+            ),
+            "SELECT \"public\".\"users\".\"user_id\", \"public\".\"users\".\"name\" \
+             FROM \"public\".\"users\" WHERE FALSE -- binds: []"
+        );
+    }
+
+    #[test]
+    fn doesnt_cache_queries_with_in() {
+        let access = access();
+
+        let select = access
+            .from::<users::table>()
+            .filter(users::user_id.eq(int(1)));
+        assert!(block_on(select.to_query()).unwrap().is_safe_to_cache());
+
+        let select = access
+            .from::<users::table>()
+            .filter(users::user_id.eq_any(vec![int(1), int(2)]));
+        assert!(!block_on(select.to_query()).unwrap().is_safe_to_cache());
+    }
+
+    #[test]
+    fn redacts_values_compared_with_sensitive_columns() {
+        let access = access();
+
+        let select = access
+            .from::<accounts::table>()
+            .filter(accounts::password.eq(text("hunter2")))
+            .filter(accounts::account_id.eq(int(1)));
+        let query = block_on(select.debug_query()).unwrap();
+        assert_eq!(
+            query.to_string(),
+            "SELECT \"public\".\"accounts\".\"account_id\", \
+             \"public\".\"accounts\".\"password\" FROM \"public\".\"accounts\" \
+             WHERE (\"public\".\"accounts\".\"password\" = $1 \
+             AND \"public\".\"accounts\".\"account_id\" = $2) \
+             -- binds: [<redacted> (TEXT), 1 (INTEGER)]"
+        );
+        let inlined = query.inline_binds().to_string();
+        assert!(inlined.contains("\"password\" = NULL /* <redacted> */ AND "));
+        assert!(inlined.ends_with("\"account_id\" = 1)"));
+
+        let select = access
+            .from::<accounts::table>()
+            .filter(accounts::password.eq_any(vec![text("hunter2"), text("letmein")]));
+        assert!(block_on(select.debug_query())
+            .unwrap()
+            .to_string()
+            .ends_with("-- binds: [<redacted> (TEXT), <redacted> (TEXT)]"));
+    }
+}
@@ -1,18 +1,57 @@
+use crate::query::QueryFragment;
 use crate::schemas::{AppearsOnTable, IsTable};
 use asphalt_core::backend::{Backend, HasSqlType};
+use asphalt_core::error::QueryResult;
+use asphalt_core::query::QueryBuilder;
 use asphalt_core::types::{Bool, ToSql};
+use asphalt_core::LocalBoxFuture;
 
 mod comparisons;
 #[doc(inline)]
-pub use self::comparisons::Condition;
+pub use self::comparisons::{Between, CompareExpression, CompareOp, Comparison, Condition, In};
 
 /// A boxed SQL expression tree.
 pub struct Expression<'a, Db: Backend + HasSqlType<SqlTy>, SqlTy> {
     expr: ExpressionTree<'a, Db, SqlTy>,
 }
 
+impl<'a, Db: Backend + HasSqlType<SqlTy>, SqlTy> Expression<'a, Db, SqlTy> {
+    /// Boxes an expression of type `SqlTy`, erasing its type.
+    fn boxed<E>(expr: E) -> Self
+    where
+        E: IsExpression<Type = SqlTy> + QueryFragment<Db> + 'a,
+    {
+        Self {
+            expr: ExpressionTree::Fragment(Box::new(expr)),
+        }
+    }
+}
+
 enum ExpressionTree<'a, Db: Backend + HasSqlType<SqlTy>, SqlTy> {
     Bound(Bound<'a, Db, SqlTy>),
+    Fragment(Box<dyn QueryFragment<Db> + 'a>),
+}
+
+impl<Db, SqlTy> QueryFragment<Db> for Expression<'_, Db, SqlTy>
+where
+    Db: Backend + HasSqlType<SqlTy>,
+{
+    fn build_query<'s, 'q: 's>(
+        &'s self,
+        out: QueryBuilder<'q, 's, Db>,
+    ) -> LocalBoxFuture<'s, QueryResult<()>> {
+        match &self.expr {
+            ExpressionTree::Bound(bound) => bound.build_query(out),
+            ExpressionTree::Fragment(fragment) => fragment.build_query(out),
+        }
+    }
+
+    fn is_sensitive(&self) -> bool {
+        match &self.expr {
+            ExpressionTree::Bound(_) => false,
+            ExpressionTree::Fragment(fragment) => fragment.is_sensitive(),
+        }
+    }
 }
 
 /// Trait for types that represent a SQL expression.
@@ -77,21 +116,50 @@ where
     T: IsTable,
     E: AppearsOnTable<T> + AsExpression<'a, Bool>,
     E::Expression: Into<Condition<'a, Db>>,
-    Db: Backend + HasSqlType<Bool> + HasSqlType<SqlTypeOf<T::AllColumns>> + 'a,
+    Db: Backend + HasSqlType<Bool> + 'a,
 {
 }
 
 /// The type for bound variables.
 pub enum Bound<'a, Db: Backend + HasSqlType<SqlTy>, SqlTy> {
     /// We took the variable by reference.
-    Ref(&'a dyn ToSql<Db, SqlTy>),
+    Ref(&'a dyn ToSql<SqlTy, Db>),
     /// We own the variable.
-    Own(Box<dyn ToSql<Db, SqlTy>>)
+    Own(Box<dyn ToSql<SqlTy, Db>>)
+}
+
+impl<Db, SqlTy> IsExpression for Bound<'_, Db, SqlTy>
+where
+    Db: Backend + HasSqlType<SqlTy>,
+{
+    type Type = SqlTy;
+}
+
+/// Bound variables don't depend on any table.
+impl<T, Db, SqlTy> AppearsOnTable<T> for Bound<'_, Db, SqlTy>
+where
+    T: IsTable,
+    Db: Backend + HasSqlType<SqlTy>,
+{
 }
 
-impl<SqlTy, Ty, Db> AsExpression<'_, SqlTy> for Ty
+/// Bound variables are written as bind parameters.
+impl<Db, SqlTy> QueryFragment<Db> for Bound<'_, Db, SqlTy>
 where
-    Ty: ToSql<SqlTy, Db>,
     Db: Backend + HasSqlType<SqlTy>,
 {
+    fn build_query<'s, 'q: 's>(
+        &'s self,
+        mut out: QueryBuilder<'q, 's, Db>,
+    ) -> LocalBoxFuture<'s, QueryResult<()>> {
+        Box::pin(async move {
+            let value: &dyn ToSql<SqlTy, Db> = match self {
+                Self::Ref(value) => *value,
+                Self::Own(value) => &**value,
+            };
+            out.push_bind_param::<SqlTy, _>(value).await?;
+
+            Ok(())
+        })
+    }
 }
@@ -1,6 +1,11 @@
-use super::{Expression, IsExpression};
+use super::{AsExpression, Expression, IsExpression, SqlTypeOf};
+use crate::query::QueryFragment;
+use crate::schemas::{AppearsOnTable, IsTable};
 use asphalt_core::backend::{Backend, HasSqlType};
+use asphalt_core::error::QueryResult;
+use asphalt_core::query::QueryBuilder;
 use asphalt_core::types::Bool;
+use asphalt_core::LocalBoxFuture;
 
 /// An opaque SQL condition expression.
 ///
@@ -58,6 +63,21 @@ impl<'a, Db: Backend + HasSqlType<Bool>> Condition<'a, Db> {
         }
     }
 
+    /// Boxes a boolean expression into a condition.
+    fn from_expression<E>(expr: E) -> Self
+    where
+        E: IsExpression<Type = Bool> + QueryFragment<Db> + 'a,
+    {
+        Self {
+            tree: ConditionTree::Expr(Expression::boxed(expr)),
+        }
+    }
+
+    /// Is this condition always true?
+    pub fn is_always_true(&self) -> bool {
+        matches!(self.tree, ConditionTree::Lit(true))
+    }
+
     /// Does an `OR` of both conditions.
     pub fn or(self, other: Self) -> Self {
         use ConditionTree::*;
@@ -94,3 +114,413 @@ enum ConditionTree<'a, Db: Backend + HasSqlType<Bool>> {
     Expr(Expression<'a, Db, Bool>),
     Lit(bool),
 }
+
+impl<Db: Backend + HasSqlType<Bool>> QueryFragment<Db> for Condition<'_, Db> {
+    fn build_query<'s, 'q: 's>(
+        &'s self,
+        out: QueryBuilder<'q, 's, Db>,
+    ) -> LocalBoxFuture<'s, QueryResult<()>> {
+        self.tree.build_query(out)
+    }
+}
+
+/// Nested conditions are always parenthesized, so that the precedence of
+/// the operators doesn't matter.
+impl<Db: Backend + HasSqlType<Bool>> QueryFragment<Db> for ConditionTree<'_, Db> {
+    fn build_query<'s, 'q: 's>(
+        &'s self,
+        mut out: QueryBuilder<'q, 's, Db>,
+    ) -> LocalBoxFuture<'s, QueryResult<()>> {
+        use ConditionTree::*;
+        Box::pin(async move {
+            let (operator, conditions) = match self {
+                And(conditions) => (" AND ", conditions),
+                Or(conditions) => (" OR ", conditions),
+                Expr(expr) => return expr.build_query(out).await,
+                Lit(true) => {
+                    out.push_sql("TRUE");
+                    return Ok(());
+                }
+                Lit(false) => {
+                    out.push_sql("FALSE");
+                    return Ok(());
+                }
+            };
+
+            out.push_sql("(");
+            for (idx, condition) in conditions.iter().enumerate() {
+                if idx > 0 {
+                    out.push_sql(operator);
+                }
+                condition.build_query(out.reborrow()).await?;
+            }
+            out.push_sql(")");
+
+            Ok(())
+        })
+    }
+}
+
+/// Comparison methods, available for every expression.
+///
+/// The operands must have the same SQL type of the expression, and can be other
+/// expressions or [`Bound`](super::Bound) values:
+///
+/// ```
+/// use asphalt_core::backend::{Backend, HasSqlType};
+/// use asphalt_core::types::{Bool, ToSql, Uuid};
+/// use asphalt_dsl::expressions::{Bound, CompareExpression, Condition};
+///
+/// mod schema {
+///     use asphalt_core::types::*;
+///     use asphalt_dsl::table;
+///
+///     table!(public.users {
+///         pk user_id: Uuid,
+///     });
+/// }
+///
+/// use schema::users;
+///
+/// fn by_id<'a, Db>(id: &'a dyn ToSql<Uuid, Db>) -> Condition<'a, Db>
+/// where
+///     Db: Backend + HasSqlType<Bool> + HasSqlType<Uuid> + 'a,
+/// {
+///     users::user_id.eq(Bound::Ref(id)).into()
+/// }
+///
+/// fn in_range<'a, Db>(
+///     lower: &'a dyn ToSql<Uuid, Db>,
+///     upper: &'a dyn ToSql<Uuid, Db>,
+/// ) -> Condition<'a, Db>
+/// where
+///     Db: Backend + HasSqlType<Bool> + HasSqlType<Uuid> + 'a,
+/// {
+///     users::user_id.between(Bound::Ref(lower), Bound::Ref(upper)).into()
+/// }
+/// ```
+pub trait CompareExpression: IsExpression + Sized {
+    /// `self = other`.
+    fn eq<'a, R>(self, other: R) -> Comparison<Self, R::Expression, { CompareOp::Eq }>
+    where
+        R: AsExpression<'a, SqlTypeOf<Self>>,
+    {
+        Comparison::new(self, other.as_expression())
+    }
+
+    /// `self <> other`.
+    fn ne<'a, R>(self, other: R) -> Comparison<Self, R::Expression, { CompareOp::Ne }>
+    where
+        R: AsExpression<'a, SqlTypeOf<Self>>,
+    {
+        Comparison::new(self, other.as_expression())
+    }
+
+    /// `self < other`.
+    fn lt<'a, R>(self, other: R) -> Comparison<Self, R::Expression, { CompareOp::Lt }>
+    where
+        R: AsExpression<'a, SqlTypeOf<Self>>,
+    {
+        Comparison::new(self, other.as_expression())
+    }
+
+    /// `self <= other`.
+    fn le<'a, R>(self, other: R) -> Comparison<Self, R::Expression, { CompareOp::Le }>
+    where
+        R: AsExpression<'a, SqlTypeOf<Self>>,
+    {
+        Comparison::new(self, other.as_expression())
+    }
+
+    /// `self > other`.
+    fn gt<'a, R>(self, other: R) -> Comparison<Self, R::Expression, { CompareOp::Gt }>
+    where
+        R: AsExpression<'a, SqlTypeOf<Self>>,
+    {
+        Comparison::new(self, other.as_expression())
+    }
+
+    /// `self >= other`.
+    fn ge<'a, R>(self, other: R) -> Comparison<Self, R::Expression, { CompareOp::Ge }>
+    where
+        R: AsExpression<'a, SqlTypeOf<Self>>,
+    {
+        Comparison::new(self, other.as_expression())
+    }
+
+    /// `self IS DISTINCT FROM other`, i.e. `<>` treating `NULL`s as equal values.
+    ///
+    /// MySQL doesn't support this operator.
+    #[allow(clippy::wrong_self_convention)]
+    fn is_distinct_from<'a, R>(
+        self,
+        other: R,
+    ) -> Comparison<Self, R::Expression, { CompareOp::IsDistinctFrom }>
+    where
+        R: AsExpression<'a, SqlTypeOf<Self>>,
+    {
+        Comparison::new(self, other.as_expression())
+    }
+
+    /// `self BETWEEN lower AND upper`, with both bounds included.
+    fn between<'a, L, U>(self, lower: L, upper: U) -> Between<Self, L::Expression, U::Expression>
+    where
+        L: AsExpression<'a, SqlTypeOf<Self>>,
+        U: AsExpression<'a, SqlTypeOf<Self>>,
+    {
+        Between {
+            expr: self,
+            lower: lower.as_expression(),
+            upper: upper.as_expression(),
+        }
+    }
+
+    /// `self IN (values...)`, i.e. `self` is equal to any of `values`.
+    ///
+    /// An empty list of values is always false.
+    fn eq_any<'a, I>(
+        self,
+        values: I,
+    ) -> In<Self, <I::Item as AsExpression<'a, SqlTypeOf<Self>>>::Expression>
+    where
+        I: IntoIterator,
+        I::Item: AsExpression<'a, SqlTypeOf<Self>>,
+    {
+        In {
+            expr: self,
+            values: values.into_iter().map(|value| value.as_expression()).collect(),
+        }
+    }
+}
+
+impl<E: IsExpression> CompareExpression for E {}
+
+/// A binary comparison operator.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    IsDistinctFrom,
+}
+
+impl CompareOp {
+    /// The SQL of the operator, surrounded by spaces.
+    const fn sql(self) -> &'static str {
+        match self {
+            Self::Eq => " = ",
+            Self::Ne => " <> ",
+            Self::Lt => " < ",
+            Self::Le => " <= ",
+            Self::Gt => " > ",
+            Self::Ge => " >= ",
+            Self::IsDistinctFrom => " IS DISTINCT FROM ",
+        }
+    }
+}
+
+/// A comparison of two expressions of the same SQL type, built with the methods of
+/// [`CompareExpression`].
+pub struct Comparison<L, R, const OP: CompareOp> {
+    lhs: L,
+    rhs: R,
+}
+
+impl<L, R, const OP: CompareOp> Comparison<L, R, OP>
+where
+    L: IsExpression,
+    R: IsExpression<Type = L::Type>,
+{
+    fn new(lhs: L, rhs: R) -> Self {
+        Self { lhs, rhs }
+    }
+}
+
+impl<L, R, const OP: CompareOp> IsExpression for Comparison<L, R, OP> {
+    type Type = Bool;
+}
+
+/// A comparison appears on a table if both of its operands do.
+impl<T, L, R, const OP: CompareOp> AppearsOnTable<T> for Comparison<L, R, OP>
+where
+    T: IsTable,
+    L: AppearsOnTable<T>,
+    R: AppearsOnTable<T>,
+{
+}
+
+/// The values compared with a sensitive operand are sensitive too.
+impl<L, R, Db, const OP: CompareOp> QueryFragment<Db> for Comparison<L, R, OP>
+where
+    L: QueryFragment<Db>,
+    R: QueryFragment<Db>,
+    Db: Backend,
+{
+    fn build_query<'s, 'q: 's>(
+        &'s self,
+        mut out: QueryBuilder<'q, 's, Db>,
+    ) -> LocalBoxFuture<'s, QueryResult<()>>
+    where
+        Db: 's,
+    {
+        Box::pin(async move {
+            let first_bind = out.bind_count();
+            self.lhs.build_query(out.reborrow()).await?;
+            out.push_sql(OP.sql());
+            self.rhs.build_query(out.reborrow()).await?;
+
+            if self.lhs.is_sensitive() || self.rhs.is_sensitive() {
+                out.mark_sensitive_since(first_bind);
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<'a, L, R, Db, const OP: CompareOp> From<Comparison<L, R, OP>> for Condition<'a, Db>
+where
+    Comparison<L, R, OP>: QueryFragment<Db> + 'a,
+    Db: Backend + HasSqlType<Bool>,
+{
+    fn from(comparison: Comparison<L, R, OP>) -> Self {
+        Self::from_expression(comparison)
+    }
+}
+
+/// A `BETWEEN` expression, built with [`CompareExpression::between`].
+pub struct Between<E, L, U> {
+    expr: E,
+    lower: L,
+    upper: U,
+}
+
+impl<E, L, U> IsExpression for Between<E, L, U> {
+    type Type = Bool;
+}
+
+/// A `BETWEEN` appears on a table if the expression and both bounds do.
+impl<T, E, L, U> AppearsOnTable<T> for Between<E, L, U>
+where
+    T: IsTable,
+    E: AppearsOnTable<T>,
+    L: AppearsOnTable<T>,
+    U: AppearsOnTable<T>,
+{
+}
+
+/// The bounds of a sensitive expression are sensitive too.
+impl<E, L, U, Db> QueryFragment<Db> for Between<E, L, U>
+where
+    E: QueryFragment<Db>,
+    L: QueryFragment<Db>,
+    U: QueryFragment<Db>,
+    Db: Backend,
+{
+    fn build_query<'s, 'q: 's>(
+        &'s self,
+        mut out: QueryBuilder<'q, 's, Db>,
+    ) -> LocalBoxFuture<'s, QueryResult<()>>
+    where
+        Db: 's,
+    {
+        Box::pin(async move {
+            let first_bind = out.bind_count();
+            self.expr.build_query(out.reborrow()).await?;
+            out.push_sql(" BETWEEN ");
+            self.lower.build_query(out.reborrow()).await?;
+            out.push_sql(" AND ");
+            self.upper.build_query(out.reborrow()).await?;
+
+            if self.expr.is_sensitive() || self.lower.is_sensitive() || self.upper.is_sensitive()
+            {
+                out.mark_sensitive_since(first_bind);
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<'a, E, L, U, Db> From<Between<E, L, U>> for Condition<'a, Db>
+where
+    Between<E, L, U>: QueryFragment<Db> + 'a,
+    Db: Backend + HasSqlType<Bool>,
+{
+    fn from(between: Between<E, L, U>) -> Self {
+        Self::from_expression(between)
+    }
+}
+
+/// An `IN` expression, built with [`CompareExpression::eq_any`].
+pub struct In<E, V> {
+    expr: E,
+    values: Vec<V>,
+}
+
+impl<E, V> IsExpression for In<E, V> {
+    type Type = Bool;
+}
+
+/// An `IN` appears on a table if the expression and all values do.
+impl<T, E, V> AppearsOnTable<T> for In<E, V>
+where
+    T: IsTable,
+    E: AppearsOnTable<T>,
+    V: AppearsOnTable<T>,
+{
+}
+
+/// `IN ()` isn't valid SQL, so an empty `IN` is written as `FALSE`.
+///
+/// The SQL depends on the number of values, so queries with an `IN` aren't cached.
+/// The values of a sensitive expression are sensitive too.
+impl<E, V, Db> QueryFragment<Db> for In<E, V>
+where
+    E: QueryFragment<Db>,
+    V: QueryFragment<Db>,
+    Db: Backend,
+{
+    fn build_query<'s, 'q: 's>(
+        &'s self,
+        mut out: QueryBuilder<'q, 's, Db>,
+    ) -> LocalBoxFuture<'s, QueryResult<()>>
+    where
+        Db: 's,
+    {
+        Box::pin(async move {
+            if self.values.is_empty() {
+                out.push_sql("FALSE");
+                return Ok(());
+            }
+
+            out.unsafe_to_cache();
+            let first_bind = out.bind_count();
+            self.expr.build_query(out.reborrow()).await?;
+            out.push_sql(" IN (");
+            for (idx, value) in self.values.iter().enumerate() {
+                if idx > 0 {
+                    out.push_sql(", ");
+                }
+                value.build_query(out.reborrow()).await?;
+            }
+            out.push_sql(")");
+
+            if self.expr.is_sensitive() || self.values.iter().any(|value| value.is_sensitive()) {
+                out.mark_sensitive_since(first_bind);
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<'a, E, V, Db> From<In<E, V>> for Condition<'a, Db>
+where
+    In<E, V>: QueryFragment<Db> + 'a,
+    Db: Backend + HasSqlType<Bool>,
+{
+    fn from(in_: In<E, V>) -> Self {
+        Self::from_expression(in_)
+    }
+}
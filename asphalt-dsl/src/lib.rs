@@ -4,9 +4,20 @@
     generic_associated_types,
     marker_trait_attr
 )]
-mod access;
-mod expressions;
-mod schemas;
+/// Entry points of the DSL and the queries built by them.
+pub mod access;
+/// SQL expressions.
+pub mod expressions;
+/// Traits used to build SQL queries from the DSL.
+pub mod query;
+/// Description of the database schema.
+pub mod schemas;
+/// Implementations for tuples of expressions, e.g. the columns of a table.
+mod tuples;
+
+mod macros;
+#[doc(hidden)]
+pub use self::macros::__private;
 
 //
 // let conn = pool.get().await?;
@@ -14,5 +25,4 @@ mod schemas;
 // let user = conn.from(users::table).get_one(id).await?;
 // conn.update(users::table).set(..).filter(..).await?;
 // conn.insert_into(users::table).values(..).await?;
-// [user_id.eq(id): BoolOp<user_id, _, {Eq}>]
 //
@@ -0,0 +1,372 @@
+/// Declares a table of the database.
+///
+/// The table is declared by its schema and name, followed by its columns and their
+/// SQL types. Columns in the primary key are marked with `pk`, and foreign keys with
+/// `fk`, followed by the referenced table: `-> table` for a table declared in the same
+/// module, or `-> schema.table` for a table declared in the module `schema`, next to
+/// the current module. Tables and columns whose names in the database aren't valid
+/// Rust identifiers can be renamed with `#[sql_name = "..."]`.
+///
+/// Columns can also be given the SQL expression of their default value, with
+/// `#[default = "..."]`, and be marked as unique with `#[unique]`. Unique constraints
+/// of multiple columns, and indexes, are declared on the table with
+/// `#[unique(column, ...)]` and `#[index("name", column, ...)]`. Columns holding
+/// secrets, e.g. passwords, are marked with `#[sensitive]`, so the values compared
+/// with them aren't shown when queries are rendered for debugging.
+///
+/// ```
+/// mod schema {
+///     pub mod auth {
+///         use asphalt_core::types::*;
+///         use asphalt_dsl::table;
+///
+///         table!(auth.tenants {
+///             pk tenant_id: Uuid,
+///             #[unique] name: Text,
+///         });
+///     }
+///
+///     pub mod public {
+///         use asphalt_core::types::*;
+///         use asphalt_dsl::table;
+///
+///         table!(
+///             #[sql_name = "Users"]
+///             #[unique(tenant_id, email)]
+///             #[index("users_created_at_idx", created_at)]
+///             public.users {
+///                 pk user_id: Uuid,
+///                 fk tenant_id: Uuid -> auth.tenants,
+///                 email: Text,
+///                 #[sql_name = "type"] type_: Nullable<Text>,
+///                 #[sensitive] password_hash: Text,
+///                 #[default = "now()"] created_at: TimestampTz,
+///             }
+///         );
+///
+///         table!(public.sessions {
+///             pk session_id: Uuid,
+///             fk user_id: Uuid -> users,
+///         });
+///     }
+/// }
+///
+/// use asphalt_dsl::schemas::IsTable;
+///
+/// let users = schema::public::users::table::DESCRIPTION;
+/// assert_eq!(users.primary_key, ["user_id"]);
+/// assert_eq!(users.unique_constraints[0].columns, ["tenant_id", "email"]);
+///
+/// let created_at = users.column("created_at").unwrap();
+/// assert_eq!(created_at.sql_type, "TIMESTAMPTZ");
+/// assert_eq!(created_at.default, Some("now()"));
+/// assert!(users.column("password_hash").unwrap().sensitive);
+///
+/// let references = users.column("tenant_id").unwrap().references.unwrap();
+/// assert_eq!(references.table.schema(), "auth");
+/// assert_eq!(references.table.name(), "tenants");
+/// assert_eq!(references.columns, ["tenant_id"]);
+///
+/// let sessions = schema::public::sessions::table::DESCRIPTION;
+/// let references = sessions.column("user_id").unwrap().references.unwrap();
+/// assert_eq!(references.table.name(), "Users");
+/// ```
+///
+/// The macro generates a module with the name of the table, containing:
+///
+/// * the unit struct `table`, implementing [`IsTable`](crate::schemas::IsTable); and
+/// * an unit struct for each column, implementing [`IsColumn`](crate::schemas::IsColumn),
+///   and also [`IsForeignKey`](crate::schemas::IsForeignKey) for foreign keys.
+///
+/// The module imports everything of its parent module, where the SQL types of the
+/// columns must be in scope. The SQL types must implement
+/// [`SqlType`](asphalt_core::types::SqlType). Tables referenced with their schema
+/// must be declared in a module named after the schema, as generated by
+/// `asphalt-introspect`.
+///
+/// Tables can have up to 64 columns, and must have a primary key. The SQL types
+/// of the columns can't contain commas, outside of parenthesis.
+#[macro_export]
+macro_rules! table {
+    // The attributes of the table are parsed into:
+    //
+    //   { schema name [sql_name] [unique constraints] [indexes] }
+    (@table [] $unique:tt $indexes:tt #[sql_name = $sql_name:literal] $($rest:tt)+) => {
+        $crate::table!(@table [$sql_name] $unique $indexes $($rest)+);
+    };
+    (@table $sql_name:tt [$($unique:tt)*] $indexes:tt
+        #[unique($($column:ident),+ $(,)?)] $($rest:tt)+) => {
+        $crate::table!(@table $sql_name [$($unique)* [$($column)+]] $indexes $($rest)+);
+    };
+    (@table $sql_name:tt $unique:tt [$($indexes:tt)*]
+        #[index($name:literal, $($column:ident),+ $(,)?)] $($rest:tt)+) => {
+        $crate::table!(@table $sql_name $unique [$($indexes)* [$name $($column)+]] $($rest)+);
+    };
+    (@table $sql_name:tt $unique:tt $indexes:tt #[$($attr:tt)*] $($rest:tt)+) => {
+        compile_error!(concat!(
+            "Invalid or duplicated table attribute: `#[", stringify!($($attr)*), "]`"
+        ));
+    };
+    (@table $sql_name:tt $unique:tt $indexes:tt $schema:ident . $table:ident { $($columns:tt)* }) => {
+        $crate::table!(@columns { $schema $table $sql_name $unique $indexes } [] [] $($columns)*);
+    };
+    (@table $sql_name:tt $unique:tt $indexes:tt $($rest:tt)*) => {
+        compile_error!(concat!("Invalid table declaration: `", stringify!($($rest)*), "`"));
+    };
+
+    // The columns are parsed one at a time, into:
+    //
+    //   { name { [sql_name] [default] [unique] [sensitive] [pk] }
+    //     [type tokens] [referenced table] }
+    //
+    // while collecting the names of the columns in the primary key.
+    (@columns $table:tt $columns:tt $pk:tt) => {
+        $crate::table!(@emit $table $columns $pk);
+    };
+    (@columns $table:tt $columns:tt $pk:tt $($rest:tt)+) => {
+        $crate::table!(@attrs $table $columns $pk [] [] [] [] $($rest)+);
+    };
+
+    (@attrs $table:tt $columns:tt $pk:tt [] $default:tt $unique:tt $sensitive:tt
+        #[sql_name = $sql_name:literal] $($rest:tt)+) => {
+        $crate::table!(
+            @attrs $table $columns $pk [$sql_name] $default $unique $sensitive $($rest)+
+        );
+    };
+    (@attrs $table:tt $columns:tt $pk:tt $sql_name:tt [] $unique:tt $sensitive:tt
+        #[default = $default:literal] $($rest:tt)+) => {
+        $crate::table!(
+            @attrs $table $columns $pk $sql_name [$default] $unique $sensitive $($rest)+
+        );
+    };
+    (@attrs $table:tt $columns:tt $pk:tt $sql_name:tt $default:tt [] $sensitive:tt
+        #[unique] $($rest:tt)+) => {
+        $crate::table!(@attrs $table $columns $pk $sql_name $default [unique] $sensitive $($rest)+);
+    };
+    (@attrs $table:tt $columns:tt $pk:tt $sql_name:tt $default:tt $unique:tt []
+        #[sensitive] $($rest:tt)+) => {
+        $crate::table!(@attrs $table $columns $pk $sql_name $default $unique [sensitive] $($rest)+);
+    };
+    (@attrs $table:tt $columns:tt $pk:tt $sql_name:tt $default:tt $unique:tt $sensitive:tt
+        #[$($attr:tt)*] $($rest:tt)*) => {
+        compile_error!(concat!(
+            "Invalid or duplicated column attribute: `#[", stringify!($($attr)*), "]`"
+        ));
+    };
+    (@attrs $table:tt $columns:tt $pk:tt $sql_name:tt $default:tt $unique:tt $sensitive:tt
+        $($rest:tt)+) => {
+        $crate::table!(
+            @column $table $columns $pk { $sql_name $default $unique $sensitive } $($rest)+
+        );
+    };
+
+    (@column $table:tt $columns:tt [$($pk:ident)*] { $($attrs:tt)* } pk fk $name:ident : $($rest:tt)+) => {
+        $crate::table!(@fk_type $table $columns [$($pk)* $name] { $($attrs)* [pk] } $name [] $($rest)+);
+    };
+    (@column $table:tt $columns:tt [$($pk:ident)*] { $($attrs:tt)* } pk $name:ident : $($rest:tt)+) => {
+        $crate::table!(@type $table $columns [$($pk)* $name] { $($attrs)* [pk] } $name [] $($rest)+);
+    };
+    (@column $table:tt $columns:tt $pk:tt { $($attrs:tt)* } fk $name:ident : $($rest:tt)+) => {
+        $crate::table!(@fk_type $table $columns $pk { $($attrs)* [] } $name [] $($rest)+);
+    };
+    (@column $table:tt $columns:tt $pk:tt { $($attrs:tt)* } $name:ident : $($rest:tt)+) => {
+        $crate::table!(@type $table $columns $pk { $($attrs)* [] } $name [] $($rest)+);
+    };
+    (@column $table:tt $columns:tt $pk:tt $attrs:tt $($rest:tt)*) => {
+        compile_error!(concat!("Invalid column declaration: `", stringify!($($rest)*), "`"));
+    };
+
+    // The type of the column, which ends at the next comma.
+    (@type $table:tt [$($columns:tt)*] $pk:tt $attrs:tt $name:ident [$($ty:tt)+] $(, $($rest:tt)*)?) => {
+        $crate::table!(
+            @columns $table [$($columns)* { $name $attrs [$($ty)+] [] }] $pk $($($rest)*)?
+        );
+    };
+    (@type $table:tt $columns:tt $pk:tt $attrs:tt $name:ident $ty:tt -> $($rest:tt)*) => {
+        compile_error!(concat!(
+            "The foreign key `", stringify!($name), "` must be declared with `fk`"
+        ));
+    };
+    (@type $table:tt $columns:tt $pk:tt $attrs:tt $name:ident [$($ty:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::table!(@type $table $columns $pk $attrs $name [$($ty)* $next] $($rest)*);
+    };
+
+    // The type of the foreign key, which ends at the referenced table. The table is
+    // stored with the path of its parent module, relative to the parent module of the
+    // declared table.
+    (@fk_type $table:tt [$($columns:tt)*] $pk:tt $attrs:tt $name:ident [$($ty:tt)+]
+        -> $schema:ident . $target:ident $(, $($rest:tt)*)?) => {
+        $crate::table!(
+            @columns $table [$($columns)* { $name $attrs [$($ty)+] [[super $schema] $target] }]
+            $pk $($($rest)*)?
+        );
+    };
+    (@fk_type $table:tt [$($columns:tt)*] $pk:tt $attrs:tt $name:ident [$($ty:tt)+]
+        -> $target:ident $(, $($rest:tt)*)?) => {
+        $crate::table!(
+            @columns $table [$($columns)* { $name $attrs [$($ty)+] [[] $target] }] $pk $($($rest)*)?
+        );
+    };
+    (@fk_type $table:tt $columns:tt $pk:tt $attrs:tt $name:ident $ty:tt $(, $($rest:tt)*)?) => {
+        compile_error!(concat!(
+            "Missing the table referenced by the foreign key `", stringify!($name), "`"
+        ));
+    };
+    (@fk_type $table:tt $columns:tt $pk:tt $attrs:tt $name:ident [$($ty:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::table!(@fk_type $table $columns $pk $attrs $name [$($ty)* $next] $($rest)*);
+    };
+
+    (@sql_name $name:ident) => { stringify!($name) };
+    (@sql_name $name:ident $sql_name:literal) => { $sql_name };
+
+    (@pk) => { compile_error!("Tables must have a primary key") };
+    (@pk $pk:ident) => { $pk };
+    (@pk $($pk:ident)+) => { ($($pk,)+) };
+
+    (@flag) => { false };
+    (@flag $flag:ident) => { true };
+
+    (@default) => { None };
+    (@default $default:literal) => { Some($default) };
+
+    (@unique unique $column:ident) => {
+        UniqueConstraint { columns: &[$column::NAME] }
+    };
+
+    (@references) => { None };
+    (@references [$($module:tt)*] $target:ident) => {
+        Some(ForeignKey {
+            table: super::$($module::)*$target::table::IDENT,
+            columns: super::$($module::)*$target::table::PRIMARY_KEY,
+        })
+    };
+
+    (@emit
+        {
+            $schema:ident $table:ident [$($table_sql_name:literal)?]
+            [$([$($unique_column:ident)+])*]
+            [$([$index:literal $($index_column:ident)+])*]
+        }
+        [$({
+            $column:ident
+            {
+                [$($column_sql_name:literal)?] [$($default:literal)?] [$($unique:ident)?]
+                [$($sensitive:ident)?] [$($is_pk:ident)?]
+            }
+            [$($ty:tt)+]
+            [$([$($module:tt)*] $target:ident)?]
+        })*]
+        [$($pk:ident)*]
+    ) => {
+        #[allow(non_camel_case_types, unused_imports)]
+        pub mod $table {
+            use super::*;
+            use $crate::__private::{Backend, LocalBoxFuture, QueryBuilder, QueryResult, SqlType};
+            use $crate::expressions::IsExpression;
+            use $crate::query::QueryFragment;
+            use $crate::schemas::{
+                Column, ForeignKey, HasTable, Ident, Index, IsColumn, IsForeignKey, IsTable, Table,
+                UniqueConstraint,
+            };
+
+            #[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+            pub struct table;
+
+            // Referenced by the foreign keys of other tables, which can't use the
+            // description of the table without making the constants cyclic.
+            impl table {
+                #[doc(hidden)]
+                pub const IDENT: Ident = Ident::new(
+                    stringify!($schema),
+                    $crate::table!(@sql_name $table $($table_sql_name)?),
+                );
+                #[doc(hidden)]
+                pub const PRIMARY_KEY: &'static [&'static str] = &[$($pk::NAME),*];
+            }
+
+            impl IsTable for table {
+                const DESCRIPTION: &'static Table = &Table {
+                    ident: Self::IDENT,
+                    all_columns: Self::COLUMNS,
+                    primary_key: Self::PRIMARY_KEY,
+                    unique_constraints: &[
+                        $($($crate::table!(@unique $unique $column),)?)*
+                        $(UniqueConstraint { columns: &[$($unique_column::NAME),+] },)*
+                    ],
+                    indexes: &[$(Index { name: $index, columns: &[$($index_column::NAME),+] }),*],
+                };
+                const COLUMNS: &'static [Column] = &[$($column::COLUMN),*];
+
+                type PrimaryKey = $crate::table!(@pk $($pk)*);
+                type AllColumns = ($($column,)*);
+
+                const ALL_COLUMNS: Self::AllColumns = ($($column,)*);
+            }
+
+            $(
+                #[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+                pub struct $column;
+
+                impl $column {
+                    const NAME: &'static str = $crate::table!(@sql_name $column $($column_sql_name)?);
+                    const COLUMN: Column = Column {
+                        name: Self::NAME,
+                        sql_type: <$($ty)+ as SqlType>::NAME,
+                        array_dimensions: <$($ty)+ as SqlType>::ARRAY_DIMENSIONS,
+                        nullable: <$($ty)+ as SqlType>::IS_NULLABLE,
+                        default: $crate::table!(@default $($default)?),
+                        primary_key: $crate::table!(@flag $($is_pk)?),
+                        unique: $crate::table!(@flag $($unique)?),
+                        sensitive: $crate::table!(@flag $($sensitive)?),
+                        references: $crate::table!(@references $([$($module)*] $target)?),
+                    };
+                }
+
+                impl HasTable for $column {
+                    type Table = table;
+                }
+
+                impl IsExpression for $column {
+                    type Type = $($ty)+;
+                }
+
+                impl IsColumn for $column {
+                    const DESCRIPTION: &'static Column = &Self::COLUMN;
+                }
+
+                impl<Db: Backend> QueryFragment<Db> for $column {
+                    fn build_query<'s, 'q: 's>(
+                        &'s self,
+                        out: QueryBuilder<'q, 's, Db>,
+                    ) -> LocalBoxFuture<'s, QueryResult<()>> {
+                        $crate::schemas::build_column_query::<Self, Db>(out)
+                    }
+
+                    fn is_sensitive(&self) -> bool {
+                        Self::COLUMN.sensitive
+                    }
+                }
+
+                $(
+                    impl IsForeignKey for $column {
+                        type Target = super::$($module::)*$target::table;
+                    }
+                )?
+            )*
+        }
+    };
+
+    ($($declaration:tt)+) => {
+        $crate::table!(@table [] [] [] $($declaration)+);
+    };
+}
+
+/// Items used by the code generated by the macros.
+#[doc(hidden)]
+pub mod __private {
+    pub use asphalt_core::backend::Backend;
+    pub use asphalt_core::error::QueryResult;
+    pub use asphalt_core::query::QueryBuilder;
+    pub use asphalt_core::types::SqlType;
+    pub use asphalt_core::LocalBoxFuture;
+}
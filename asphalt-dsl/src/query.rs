@@ -0,0 +1,46 @@
+use asphalt_core::backend::Backend;
+use asphalt_core::error::QueryResult;
+use asphalt_core::query::QueryBuilder;
+use asphalt_core::LocalBoxFuture;
+
+/// A part of a SQL query, e.g. an expression or a clause.
+pub trait QueryFragment<Db: Backend> {
+    /// Write this fragment to the end of the query being constructed.
+    ///
+    /// Bind parameters may need to lookup type metadata in the database, so this
+    /// is asynchronous.
+    fn build_query<'s, 'q: 's>(
+        &'s self,
+        out: QueryBuilder<'q, 's, Db>,
+    ) -> LocalBoxFuture<'s, QueryResult<()>>
+    where
+        Db: 's;
+
+    /// Are the values compared with this fragment sensitive, e.g. passwords?
+    ///
+    /// Their bind parameters are then redacted when the query is rendered for
+    /// debugging, see [`QueryBuilder::debug_query`].
+    fn is_sensitive(&self) -> bool {
+        false
+    }
+}
+
+impl<T, Db> QueryFragment<Db> for &'_ T
+where
+    T: QueryFragment<Db> + ?Sized,
+    Db: Backend,
+{
+    fn build_query<'s, 'q: 's>(
+        &'s self,
+        out: QueryBuilder<'q, 's, Db>,
+    ) -> LocalBoxFuture<'s, QueryResult<()>>
+    where
+        Db: 's,
+    {
+        (**self).build_query(out)
+    }
+
+    fn is_sensitive(&self) -> bool {
+        (**self).is_sensitive()
+    }
+}
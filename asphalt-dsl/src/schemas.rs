@@ -1,4 +1,9 @@
 use crate::expressions::IsExpression;
+use crate::query::QueryFragment;
+use asphalt_core::backend::Backend;
+use asphalt_core::error::QueryResult;
+use asphalt_core::query::QueryBuilder;
+use asphalt_core::LocalBoxFuture;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Ident {
@@ -7,6 +12,13 @@ pub struct Ident {
 }
 
 impl Ident {
+    /// Creates the identifier `name`, in `schema`.
+    ///
+    /// An empty `schema` means the default schema of the database.
+    pub const fn new(schema: &'static str, name: &'static str) -> Self {
+        Self { name, schema }
+    }
+
     pub const fn name(self) -> &'static str {
         self.name
     }
@@ -14,53 +26,42 @@ impl Ident {
     pub const fn schema(self) -> &'static str {
         self.schema
     }
+
+    fn push_to<Db: Backend>(self, out: &mut QueryBuilder<'_, '_, Db>) {
+        if !self.schema.is_empty() {
+            out.push_identifier(self.schema);
+            out.push_sql(".");
+        }
+        out.push_identifier(self.name);
+    }
 }
 
-//
-// table!(public.users {
-//     pk user_id: Uuid,
-//     fk tenant_id: Uuid -> public.tenant,
-// })
-// --
-//
-// mod users {
-//     #[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
-//     pub struct table;
-//
-//     impl IsTable for table {
-//         const DESCRIPTION: &'static Table = &Table {
-//             ident: Ident { name: "users", schema: "public" },
-//             all_columns: &[Column {
-//                 name: "user_id"
-//             }],
-//         };
-//
-//         type PrimaryKey = user_id;
-//     }
-//
-//     #[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
-//     pub struct user_id;
-//
-//     mod user_id {
-//         impl HasTable for user_id {
-//             type Table = table;
-//         }
-//
-//         impl HasType for user_id {
-//              type Type = Uuid;
-//         }
-//
-//         impl IsColumn for user_id {
-//             const DESCRIPTION: &'static Column = &table.all_columns[0];
-//         }
-//     }
-// }
+/// Identifiers are written qualified by their schema, if any.
+impl<Db: Backend> QueryFragment<Db> for Ident {
+    fn build_query<'s, 'q: 's>(
+        &'s self,
+        mut out: QueryBuilder<'q, 's, Db>,
+    ) -> LocalBoxFuture<'s, QueryResult<()>> {
+        self.push_to(&mut out);
+
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// A table of the database.
+///
+/// Tables are declared with the [`table!`](crate::table) macro.
 pub trait IsTable: Default {
     const DESCRIPTION: &'static Table;
     const COLUMNS: &'static [Column];
 
-    type PrimaryKey: IsColumn;
-    type AllColumns: AppearsOnTable<Self> + Default;
+    /// The primary key, a column or a tuple of columns.
+    type PrimaryKey: AppearsOnTable<Self>;
+    /// The tuple of all the columns, in the order they were declared.
+    type AllColumns: AppearsOnTable<Self>;
+
+    /// All the columns, e.g. the default selection of queries from the table.
+    const ALL_COLUMNS: Self::AllColumns;
 }
 
 /// The primary key of the table.
@@ -69,9 +70,23 @@ pub type Pk<T> = <T as IsTable>::PrimaryKey;
 /// All the columns of the table.
 pub type AllColumns<T> = <T as IsTable>::AllColumns;
 
+/// The description of a table, as declared with [`table!`](crate::table).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Table {
     pub ident: Ident,
     pub all_columns: &'static [Column],
+    /// The names of the columns in the primary key, in order.
+    pub primary_key: &'static [&'static str],
+    /// The unique constraints, including the ones of single columns.
+    pub unique_constraints: &'static [UniqueConstraint],
+    pub indexes: &'static [Index],
+}
+
+impl Table {
+    /// Returns the column named `name`, if any.
+    pub fn column(&self, name: &str) -> Option<&'static Column> {
+        self.all_columns.iter().find(|column| column.name == name)
+    }
 }
 
 pub trait HasTable {
@@ -82,8 +97,79 @@ pub trait IsColumn: HasTable + IsExpression + Default {
     const DESCRIPTION: &'static Column;
 }
 
+/// The description of a column, as declared with [`table!`](crate::table).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Column {
     pub name: &'static str,
+    /// The name of the SQL type, see [`SqlType::NAME`].
+    ///
+    /// This is a portable label, which isn't necessarily valid DDL for the backend.
+    ///
+    /// [`SqlType::NAME`]: asphalt_core::types::SqlType::NAME
+    pub sql_type: &'static str,
+    /// The number of array dimensions of the SQL type, `0` if it isn't an array.
+    pub array_dimensions: u32,
+    pub nullable: bool,
+    /// The SQL expression of the default value, if any.
+    pub default: Option<&'static str>,
+    /// Is the column part of the primary key?
+    pub primary_key: bool,
+    /// Is the column, by itself, unique?
+    pub unique: bool,
+    /// Are the values of the column sensitive, e.g. passwords?
+    ///
+    /// The values compared with the column are redacted when queries are rendered
+    /// for debugging.
+    pub sensitive: bool,
+    /// The foreign key referencing another table, if any.
+    pub references: Option<ForeignKey>,
+}
+
+/// A foreign key of a column, referencing the primary key of another table.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ForeignKey {
+    pub table: Ident,
+    /// The names of the referenced columns, i.e. the primary key of `table`.
+    pub columns: &'static [&'static str],
+}
+
+/// A unique constraint of a table.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct UniqueConstraint {
+    pub columns: &'static [&'static str],
+}
+
+/// An index of a table.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Index {
+    pub name: &'static str,
+    /// The names of the indexed columns, in order.
+    pub columns: &'static [&'static str],
+}
+
+/// A column which references the primary key of another table, allowing
+/// both tables to be joined.
+pub trait IsForeignKey: IsColumn {
+    /// The referenced table.
+    type Target: IsTable;
+}
+
+/// Writes the column `C`, qualified by its table.
+///
+/// Used by the columns declared with [`table!`](crate::table).
+#[doc(hidden)]
+pub fn build_column_query<'s, 'q: 's, C, Db>(
+    mut out: QueryBuilder<'q, 's, Db>,
+) -> LocalBoxFuture<'s, QueryResult<()>>
+where
+    C: IsColumn,
+    Db: Backend,
+{
+    <C::Table as IsTable>::DESCRIPTION.ident.push_to(&mut out);
+    out.push_sql(".");
+    out.push_identifier(C::DESCRIPTION.name);
+
+    Box::pin(async { Ok(()) })
 }
 
 #[marker]
@@ -96,3 +182,90 @@ where
     C: IsColumn<Table = T>,
 {
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod schema {
+        use crate::table;
+        use asphalt_core::types::*;
+
+        table!(
+            #[index("posts_author_idx", author_id, published_at)]
+            #[unique(author_id, slug)]
+            #[index("posts_slug_idx", slug)]
+            #[unique(author_id, title)]
+            public.posts {
+                pk post_id: BigInt,
+                author_id: Integer,
+                #[unique] #[sql_name = "Slug"] slug: Text,
+                title: Text,
+                #[default = "'{}'"] tags: Array<Text>,
+                #[sql_name = "views"] #[default = "0"] #[unique] view_count: Integer,
+                published_at: Nullable<TimestampTz>,
+            }
+        );
+    }
+
+    use schema::posts;
+
+    #[test]
+    fn describes_defaults() {
+        let posts = posts::table::DESCRIPTION;
+
+        let tags = posts.column("tags").unwrap();
+        assert_eq!(tags.default, Some("'{}'"));
+        assert_eq!((tags.sql_type, tags.array_dimensions), ("TEXT", 1));
+
+        let views = posts.column("views").unwrap();
+        assert_eq!(views.default, Some("0"));
+        assert!(views.unique);
+
+        let published_at = posts.column("published_at").unwrap();
+        assert_eq!(published_at.default, None);
+        assert!(published_at.nullable);
+    }
+
+    #[test]
+    fn describes_unique_constraints() {
+        let posts = posts::table::DESCRIPTION;
+
+        let constraints: Vec<_> = posts
+            .unique_constraints
+            .iter()
+            .map(|constraint| constraint.columns)
+            .collect();
+        assert_eq!(
+            constraints,
+            [
+                &["Slug"][..],
+                &["views"],
+                &["author_id", "Slug"],
+                &["author_id", "title"],
+            ]
+        );
+
+        assert!(posts.column("Slug").unwrap().unique);
+        assert!(!posts.column("title").unwrap().unique);
+    }
+
+    #[test]
+    fn describes_indexes() {
+        let posts = posts::table::DESCRIPTION;
+
+        assert_eq!(
+            posts.indexes,
+            [
+                Index {
+                    name: "posts_author_idx",
+                    columns: &["author_id", "published_at"],
+                },
+                Index {
+                    name: "posts_slug_idx",
+                    columns: &["Slug"],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use crate::expressions::IsExpression;
+use crate::query::QueryFragment;
+use crate::schemas::{AppearsOnTable, IsTable};
+use asphalt_core::backend::Backend;
+use asphalt_core::error::QueryResult;
+use asphalt_core::query::QueryBuilder;
+use asphalt_core::LocalBoxFuture;
+
+macro_rules! tuple_impls {
+    (@impl $($T:ident)+) => {
+        /// The SQL type of a tuple is the tuple of the SQL types of its expressions.
+        impl<$($T: IsExpression),+> IsExpression for ($($T,)+) {
+            type Type = ($($T::Type,)+);
+        }
+
+        /// A tuple appears on a table if all of its expressions do.
+        impl<Tbl: IsTable, $($T: AppearsOnTable<Tbl>),+> AppearsOnTable<Tbl> for ($($T,)+) {}
+
+        /// Tuples are written as a comma separated list of its expressions.
+        impl<Db: Backend + 'static, $($T: QueryFragment<Db>),+> QueryFragment<Db> for ($($T,)+) {
+            #[allow(non_snake_case)]
+            fn build_query<'s, 'q: 's>(
+                &'s self,
+                mut out: QueryBuilder<'q, 's, Db>,
+            ) -> LocalBoxFuture<'s, QueryResult<()>> {
+                let ($($T,)+) = self;
+                Box::pin(async move {
+                    let fragments: &[&dyn QueryFragment<Db>] = &[$($T),+];
+                    for (idx, fragment) in fragments.iter().enumerate() {
+                        if idx > 0 {
+                            out.push_sql(", ");
+                        }
+                        fragment.build_query(out.reborrow()).await?;
+                    }
+
+                    Ok(())
+                })
+            }
+        }
+    };
+    () => {};
+    ($first:ident $($rest:ident)*) => {
+        tuple_impls!(@impl $first $($rest)*);
+        tuple_impls!($($rest)*);
+    };
+}
+
+// Tables with up to 64 columns are supported.
+tuple_impls!(
+    T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11 T12 T13 T14 T15
+    T16 T17 T18 T19 T20 T21 T22 T23 T24 T25 T26 T27 T28 T29 T30 T31
+    T32 T33 T34 T35 T36 T37 T38 T39 T40 T41 T42 T43 T44 T45 T46 T47
+    T48 T49 T50 T51 T52 T53 T54 T55 T56 T57 T58 T59 T60 T61 T62 T63
+);
@@ -0,0 +1,340 @@
+use crate::{ColumnInfo, TableInfo};
+use std::fmt::Write;
+
+/// Rust keywords, which can't be used as the names of tables or columns.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "union", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Generates the Rust source of a schema module, declaring the tables with `table!`.
+///
+/// The tables are declared in a module per database schema, named after it, which
+/// imports the SQL types of `asphalt_core::types` and the `table!` macro of
+/// `asphalt_dsl`. Foreign keys to the tables of other schemas are resolved through
+/// these modules. Tables and columns whose names aren't valid Rust identifiers are
+/// renamed, with the original name given by `#[sql_name = "..."]`.
+///
+/// Some things can't be expressed by `table!`, and are left as comments to be handled
+/// by hand: tables without a primary key, or in schemas whose names aren't valid Rust
+/// identifiers, and columns of types without a corresponding SQL type in
+/// `asphalt_core::types`. Only foreign keys made of a single column are declared.
+pub fn generate(tables: &[TableInfo]) -> String {
+    let mut out = String::new();
+    out.push_str("// @generated by asphalt-introspect, do not edit by hand.\n");
+
+    // The schemas, in the order of their first table.
+    let mut schemas: Vec<&str> = Vec::new();
+    for table in tables {
+        if !schemas.contains(&table.schema.as_str()) {
+            schemas.push(&table.schema);
+        }
+    }
+
+    for schema in schemas {
+        let schema_tables = tables.iter().filter(|table| table.schema == schema);
+        out.push('\n');
+
+        if !is_identifier(schema) {
+            for table in schema_tables {
+                let _ = writeln!(
+                    out,
+                    "// Skipped `{}.{}`: the schema name isn't a valid identifier.",
+                    table.schema, table.name
+                );
+            }
+            continue;
+        }
+
+        let _ = writeln!(out, "pub mod {} {{", schema);
+        out.push_str("    use asphalt_core::types::*;\n");
+        out.push_str("    use asphalt_dsl::table;\n");
+
+        for table in schema_tables {
+            let mut declaration = String::new();
+            generate_table(&mut declaration, table, tables);
+
+            out.push('\n');
+            for line in declaration.lines() {
+                let _ = writeln!(out, "    {}", line);
+            }
+        }
+
+        out.push_str("}\n");
+    }
+
+    out
+}
+
+/// Can `table!` be used to declare the table?
+fn is_declarable(table: &TableInfo) -> bool {
+    is_identifier(&table.schema) && !table.primary_key.is_empty()
+}
+
+fn generate_table(out: &mut String, table: &TableInfo, tables: &[TableInfo]) {
+    if table.primary_key.is_empty() {
+        let _ = writeln!(
+            out,
+            "// Skipped `{}.{}`: the table has no primary key.",
+            table.schema, table.name
+        );
+        return;
+    }
+
+    out.push_str("table!(");
+    let name = identifier(&table.name);
+    push_sql_name(out, &table.name, &name);
+    let _ = writeln!(out, "{}.{} {{", table.schema, name);
+
+    for column in &table.columns {
+        generate_column(out, table, column, tables);
+    }
+
+    out.push_str("});\n");
+}
+
+fn generate_column(out: &mut String, table: &TableInfo, column: &ColumnInfo, tables: &[TableInfo]) {
+    let sql_type = match sql_type(&column.type_name) {
+        Some(sql_type) if column.nullable => format!("Nullable<{}>", sql_type),
+        Some(sql_type) => sql_type,
+        None => {
+            let _ = writeln!(
+                out,
+                "    // {}: {}, unsupported type",
+                column.name, column.type_name
+            );
+            return;
+        }
+    };
+
+    out.push_str("    ");
+    let name = identifier(&column.name);
+    push_sql_name(out, &column.name, &name);
+
+    if table.primary_key.contains(&column.name) {
+        out.push_str("pk ");
+    }
+
+    // Foreign keys can only reference tables declared with `table!` too.
+    let referenced = table.foreign_key_of(&column.name).and_then(|fk| {
+        tables.iter().find(|referenced| {
+            referenced.schema == fk.referenced_schema
+                && referenced.name == fk.referenced_table
+                && is_declarable(referenced)
+        })
+    });
+
+    // Tables of other schemas are referenced through the module of their schema.
+    let _ = match referenced {
+        Some(referenced) if referenced.schema == table.schema => writeln!(
+            out,
+            "fk {}: {} -> {},",
+            name,
+            sql_type,
+            identifier(&referenced.name)
+        ),
+        Some(referenced) => writeln!(
+            out,
+            "fk {}: {} -> {}.{},",
+            name,
+            sql_type,
+            referenced.schema,
+            identifier(&referenced.name)
+        ),
+        None => writeln!(out, "{}: {},", name, sql_type),
+    };
+}
+
+/// Returns the name of the SQL type in `asphalt_core::types` of a Postgres type.
+fn sql_type(type_name: &str) -> Option<String> {
+    if let Some(element) = type_name.strip_prefix('_') {
+        return sql_type(element).map(|element| format!("Array<{}>", element));
+    }
+
+    let sql_type = match type_name {
+        "bool" => "Bool",
+        "char" => "TinyInt",
+        "int2" => "SmallInt",
+        "int4" => "Integer",
+        "int8" => "BigInt",
+        "float4" => "Float",
+        "float8" => "Double",
+        "numeric" => "Numeric",
+        "text" | "varchar" | "bpchar" | "name" | "citext" => "Text",
+        "bytea" => "Binary",
+        "date" => "Date",
+        "time" => "Time",
+        "timestamp" => "Timestamp",
+        "timestamptz" => "TimestampTz",
+        "interval" => "Interval",
+        "uuid" => "Uuid",
+        "json" | "jsonb" => "Json",
+        _ => return None,
+    };
+
+    Some(sql_type.to_string())
+}
+
+/// Is `name` usable as a Rust identifier, as is?
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let valid_start = chars
+        .next()
+        .map_or(false, |ch| ch == '_' || ch.is_ascii_lowercase());
+
+    valid_start
+        && name != "_"
+        && chars.all(|ch| ch == '_' || ch.is_ascii_lowercase() || ch.is_ascii_digit())
+        && !KEYWORDS.contains(&name)
+}
+
+/// Returns the Rust identifier used for `name`, which is renamed if it isn't one.
+fn identifier(name: &str) -> String {
+    if is_identifier(name) {
+        return name.to_string();
+    }
+
+    let mut ident: String = name
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() {
+                ch.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|ch: char| ch.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if !is_identifier(&ident) {
+        ident.push('_');
+    }
+
+    ident
+}
+
+/// Writes the `#[sql_name]` attribute of renamed identifiers.
+fn push_sql_name(out: &mut String, name: &str, ident: &str) {
+    if name != ident {
+        let _ = write!(out, "#[sql_name = {:?}] ", name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ForeignKeyInfo;
+
+    fn column(name: &str, type_name: &str, nullable: bool) -> ColumnInfo {
+        ColumnInfo {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            nullable,
+        }
+    }
+
+    #[test]
+    fn generates_table_declarations() {
+        let users = TableInfo {
+            schema: "public".to_string(),
+            name: "users".to_string(),
+            columns: vec![
+                column("user_id", "uuid", false),
+                column("tenant_id", "uuid", false),
+                column("type", "varchar", true),
+                column("tags", "_text", false),
+                column("aliases", "_varchar", true),
+                column("search", "tsvector", false),
+            ],
+            primary_key: vec!["user_id".to_string()],
+            foreign_keys: vec![ForeignKeyInfo {
+                name: "users_tenant_id_fkey".to_string(),
+                columns: vec!["tenant_id".to_string()],
+                referenced_schema: "auth".to_string(),
+                referenced_table: "Tenants".to_string(),
+                referenced_columns: vec!["tenant_id".to_string()],
+            }],
+        };
+        let tenants = TableInfo {
+            schema: "auth".to_string(),
+            name: "Tenants".to_string(),
+            columns: vec![column("tenant_id", "uuid", false)],
+            primary_key: vec!["tenant_id".to_string()],
+            foreign_keys: vec![],
+        };
+        let sessions = TableInfo {
+            schema: "public".to_string(),
+            name: "sessions".to_string(),
+            columns: vec![
+                column("session_id", "uuid", false),
+                column("user_id", "uuid", false),
+            ],
+            primary_key: vec!["session_id".to_string()],
+            foreign_keys: vec![ForeignKeyInfo {
+                name: "sessions_user_id_fkey".to_string(),
+                columns: vec!["user_id".to_string()],
+                referenced_schema: "public".to_string(),
+                referenced_table: "users".to_string(),
+                referenced_columns: vec!["user_id".to_string()],
+            }],
+        };
+        let logs = TableInfo {
+            schema: "public".to_string(),
+            name: "logs".to_string(),
+            columns: vec![column("message", "text", false)],
+            primary_key: vec![],
+            foreign_keys: vec![],
+        };
+        let archived = TableInfo {
+            schema: "Archive".to_string(),
+            name: "users".to_string(),
+            columns: vec![column("user_id", "uuid", false)],
+            primary_key: vec!["user_id".to_string()],
+            foreign_keys: vec![],
+        };
+
+        let expected = "\
+// @generated by asphalt-introspect, do not edit by hand.
+
+pub mod public {
+    use asphalt_core::types::*;
+    use asphalt_dsl::table;
+
+    table!(public.users {
+        pk user_id: Uuid,
+        fk tenant_id: Uuid -> auth.tenants,
+        #[sql_name = \"type\"] type_: Nullable<Text>,
+        tags: Array<Text>,
+        aliases: Nullable<Array<Text>>,
+        // search: tsvector, unsupported type
+    });
+
+    table!(public.sessions {
+        pk session_id: Uuid,
+        fk user_id: Uuid -> users,
+    });
+
+    // Skipped `public.logs`: the table has no primary key.
+}
+
+pub mod auth {
+    use asphalt_core::types::*;
+    use asphalt_dsl::table;
+
+    table!(#[sql_name = \"Tenants\"] auth.tenants {
+        pk tenant_id: Uuid,
+    });
+}
+
+// Skipped `Archive.users`: the schema name isn't a valid identifier.
+";
+        assert_eq!(
+            generate(&[users, tenants, sessions, logs, archived]),
+            expected
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! Schema introspection for asphalt.
+//!
+//! Reads the tables of a Postgres database with [`introspect`], and generates their
+//! `table!` declarations with [`generate`]. The same is available from the command line,
+//! with the `asphalt-introspect` binary.
+mod codegen;
+mod postgres;
+mod schema;
+
+#[doc(inline)]
+pub use self::codegen::generate;
+#[doc(inline)]
+pub use self::postgres::introspect;
+#[doc(inline)]
+pub use self::schema::{ColumnInfo, ForeignKeyInfo, TableInfo};
@@ -0,0 +1,94 @@
+use asphalt_core::connection::Connection;
+use asphalt_postgres::{Config, ConnectionConfig, Pg};
+use std::error::Error as StdError;
+use std::path::PathBuf;
+
+const USAGE: &str = "\
+Generates the `table!` declarations of the tables of a Postgres database.
+
+USAGE:
+    asphalt-introspect <DATABASE_URL> [--schema <NAME>]... [--output <FILE>]
+
+OPTIONS:
+    -s, --schema <NAME>    Schema to introspect, can be repeated [default: public]
+    -o, --output <FILE>    File to write the declarations to [default: stdout]
+    -h, --help             Prints this message";
+
+struct Args {
+    database_url: String,
+    schemas: Vec<String>,
+    output: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut database_url = None;
+        let mut schemas = Vec::new();
+        let mut output = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("Missing value of `{}`", name))
+            };
+
+            match arg.as_str() {
+                "-s" | "--schema" => schemas.push(value(&arg)?),
+                "-o" | "--output" => output = Some(PathBuf::from(value(&arg)?)),
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with('-') => return Err(format!("Unknown option `{}`", arg)),
+                _ if database_url.is_none() => database_url = Some(arg),
+                _ => return Err(format!("Unexpected argument `{}`", arg)),
+            }
+        }
+
+        if schemas.is_empty() {
+            schemas.push("public".to_string());
+        }
+
+        Ok(Self {
+            database_url: database_url.ok_or_else(|| USAGE.to_string())?,
+            schemas,
+            output,
+        })
+    }
+}
+
+async fn run(args: Args) -> Result<(), Box<dyn StdError>> {
+    let config: ConnectionConfig = args.database_url.parse()?;
+    let conn = Connection::<Pg>::establish(Config::new(config)).await?;
+
+    let tables = asphalt_introspect::introspect(&conn, &args.schemas).await?;
+    let source = asphalt_introspect::generate(&tables);
+
+    match args.output {
+        Some(path) => std::fs::write(path, source)?,
+        None => print!("{}", source),
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            std::process::exit(2);
+        }
+    };
+
+    let mut runtime = tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()
+        .expect("Failed to start the tokio runtime");
+
+    if let Err(err) = runtime.block_on(run(args)) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{ColumnInfo, ForeignKeyInfo, TableInfo};
+use asphalt_core::connection::{get_field, Connection, FromRow};
+use asphalt_core::error::QueryResult;
+use asphalt_core::types::{Bool, Text};
+use asphalt_postgres::{Pg, PgRow};
+
+/// The columns of the ordinary tables of a schema.
+const COLUMNS_QUERY: &str = "\
+    SELECT c.table_name::text, c.column_name::text, c.udt_name::text, c.is_nullable = 'YES' \
+    FROM information_schema.columns c \
+    JOIN information_schema.tables t \
+        ON t.table_schema = c.table_schema AND t.table_name = c.table_name \
+    WHERE t.table_type = 'BASE TABLE' AND c.table_schema = ";
+const COLUMNS_ORDER: &str = " ORDER BY c.table_name, c.ordinal_position";
+
+/// The columns of the primary keys of the tables of a schema.
+const PRIMARY_KEYS_QUERY: &str = "\
+    SELECT cl.relname::text, a.attname::text \
+    FROM pg_catalog.pg_constraint con \
+    JOIN pg_catalog.pg_class cl ON cl.oid = con.conrelid \
+    JOIN pg_catalog.pg_namespace n ON n.oid = cl.relnamespace \
+    CROSS JOIN LATERAL unnest(con.conkey) WITH ORDINALITY AS k(attnum, position) \
+    JOIN pg_catalog.pg_attribute a ON a.attrelid = cl.oid AND a.attnum = k.attnum \
+    WHERE con.contype = 'p' AND n.nspname = ";
+const PRIMARY_KEYS_ORDER: &str = " ORDER BY cl.relname, k.position";
+
+/// The columns of the foreign keys of the tables of a schema, and the ones they reference.
+const FOREIGN_KEYS_QUERY: &str = "\
+    SELECT con.conname::text, cl.relname::text, a.attname::text, \
+        fn.nspname::text, fcl.relname::text, fa.attname::text \
+    FROM pg_catalog.pg_constraint con \
+    JOIN pg_catalog.pg_class cl ON cl.oid = con.conrelid \
+    JOIN pg_catalog.pg_namespace n ON n.oid = cl.relnamespace \
+    JOIN pg_catalog.pg_class fcl ON fcl.oid = con.confrelid \
+    JOIN pg_catalog.pg_namespace fn ON fn.oid = fcl.relnamespace \
+    CROSS JOIN LATERAL unnest(con.conkey, con.confkey) \
+        WITH ORDINALITY AS k(attnum, fattnum, position) \
+    JOIN pg_catalog.pg_attribute a ON a.attrelid = cl.oid AND a.attnum = k.attnum \
+    JOIN pg_catalog.pg_attribute fa ON fa.attrelid = fcl.oid AND fa.attnum = k.fattnum \
+    WHERE con.contype = 'f' AND n.nspname = ";
+const FOREIGN_KEYS_ORDER: &str = " ORDER BY cl.relname, con.conname, k.position";
+
+/// Reads the ordinary tables of the given schemas, ordered by schema and name.
+///
+/// Views, foreign tables and partitions aren't included.
+pub async fn introspect(conn: &Connection<Pg>, schemas: &[String]) -> QueryResult<Vec<TableInfo>> {
+    let mut tables = Vec::new();
+    for schema in schemas {
+        tables.extend(introspect_schema(conn, schema).await?);
+    }
+
+    Ok(tables)
+}
+
+async fn introspect_schema(conn: &Connection<Pg>, schema: &str) -> QueryResult<Vec<TableInfo>> {
+    let mut tables: Vec<TableInfo> = Vec::new();
+
+    let columns: Vec<ColumnRow> = load(conn, COLUMNS_QUERY, schema, COLUMNS_ORDER).await?;
+    for ColumnRow { table, column } in columns {
+        match tables.last_mut() {
+            Some(last) if last.name == table => last.columns.push(column),
+            _ => tables.push(TableInfo {
+                schema: schema.to_string(),
+                name: table,
+                columns: vec![column],
+                primary_key: Vec::new(),
+                foreign_keys: Vec::new(),
+            }),
+        }
+    }
+
+    let primary_keys: Vec<PrimaryKeyRow> =
+        load(conn, PRIMARY_KEYS_QUERY, schema, PRIMARY_KEYS_ORDER).await?;
+    for PrimaryKeyRow { table, column } in primary_keys {
+        if let Some(table) = tables.iter_mut().find(|t| t.name == table) {
+            table.primary_key.push(column);
+        }
+    }
+
+    let foreign_keys: Vec<ForeignKeyRow> =
+        load(conn, FOREIGN_KEYS_QUERY, schema, FOREIGN_KEYS_ORDER).await?;
+    for row in foreign_keys {
+        let table = match tables.iter_mut().find(|t| t.name == row.table) {
+            Some(table) => table,
+            None => continue,
+        };
+
+        match table.foreign_keys.last_mut() {
+            Some(last) if last.name == row.constraint => {
+                last.columns.push(row.column);
+                last.referenced_columns.push(row.referenced_column);
+            }
+            _ => table.foreign_keys.push(ForeignKeyInfo {
+                name: row.constraint,
+                columns: vec![row.column],
+                referenced_schema: row.referenced_schema,
+                referenced_table: row.referenced_table,
+                referenced_columns: vec![row.referenced_column],
+            }),
+        }
+    }
+
+    Ok(tables)
+}
+
+/// Loads the rows of a catalog query, filtered by `schema`.
+async fn load<T>(
+    conn: &Connection<Pg>,
+    sql: &str,
+    schema: &str,
+    order_by: &str,
+) -> QueryResult<Vec<T>>
+where
+    T: for<'r> FromRow<'r, PgRow>,
+{
+    let mut query = conn.query_builder();
+    query.push_sql(sql);
+    query.push_bind_param::<Text, _>(&schema).await?;
+    query.push_sql(order_by);
+
+    conn.load(query).await
+}
+
+struct ColumnRow {
+    table: String,
+    column: ColumnInfo,
+}
+
+impl<'r> FromRow<'r, PgRow> for ColumnRow {
+    fn from_row(row: &'r PgRow) -> QueryResult<Self> {
+        Ok(Self {
+            table: get_field::<_, Text, _>(row, "table", 0)?,
+            column: ColumnInfo {
+                name: get_field::<_, Text, _>(row, "name", 1)?,
+                type_name: get_field::<_, Text, _>(row, "type_name", 2)?,
+                nullable: get_field::<_, Bool, _>(row, "nullable", 3)?,
+            },
+        })
+    }
+}
+
+struct PrimaryKeyRow {
+    table: String,
+    column: String,
+}
+
+impl<'r> FromRow<'r, PgRow> for PrimaryKeyRow {
+    fn from_row(row: &'r PgRow) -> QueryResult<Self> {
+        Ok(Self {
+            table: get_field::<_, Text, _>(row, "table", 0)?,
+            column: get_field::<_, Text, _>(row, "column", 1)?,
+        })
+    }
+}
+
+struct ForeignKeyRow {
+    constraint: String,
+    table: String,
+    column: String,
+    referenced_schema: String,
+    referenced_table: String,
+    referenced_column: String,
+}
+
+impl<'r> FromRow<'r, PgRow> for ForeignKeyRow {
+    fn from_row(row: &'r PgRow) -> QueryResult<Self> {
+        Ok(Self {
+            constraint: get_field::<_, Text, _>(row, "constraint", 0)?,
+            table: get_field::<_, Text, _>(row, "table", 1)?,
+            column: get_field::<_, Text, _>(row, "column", 2)?,
+            referenced_schema: get_field::<_, Text, _>(row, "referenced_schema", 3)?,
+            referenced_table: get_field::<_, Text, _>(row, "referenced_table", 4)?,
+            referenced_column: get_field::<_, Text, _>(row, "referenced_column", 5)?,
+        })
+    }
+}
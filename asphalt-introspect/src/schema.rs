@@ -0,0 +1,43 @@
+/// A table read from the database catalog.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TableInfo {
+    pub schema: String,
+    pub name: String,
+    /// The columns, in the order they were declared.
+    pub columns: Vec<ColumnInfo>,
+    /// The names of the columns in the primary key, in order.
+    pub primary_key: Vec<String>,
+    pub foreign_keys: Vec<ForeignKeyInfo>,
+}
+
+impl TableInfo {
+    /// Returns the foreign key made only by `column`, if any.
+    pub fn foreign_key_of(&self, column: &str) -> Option<&ForeignKeyInfo> {
+        self.foreign_keys
+            .iter()
+            .find(|fk| fk.columns.len() == 1 && fk.columns[0] == column)
+    }
+}
+
+/// A column read from the database catalog.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ColumnInfo {
+    pub name: String,
+    /// The name of the type in the database, e.g. `int4`.
+    ///
+    /// Array types are prefixed by `_`, e.g. `_int4`, as in `pg_type`.
+    pub type_name: String,
+    pub nullable: bool,
+}
+
+/// A foreign key constraint read from the database catalog.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ForeignKeyInfo {
+    /// The name of the constraint.
+    pub name: String,
+    pub columns: Vec<String>,
+    pub referenced_schema: String,
+    pub referenced_table: String,
+    /// The referenced columns, in the same order as `columns`.
+    pub referenced_columns: Vec<String>,
+}
@@ -0,0 +1,161 @@
+//! Procedural macros for `asphalt_migrations`.
+//!
+//! The macros in this crate are re-exported by `asphalt_migrations`, and should be used
+//! from there.
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use syn::{parse_macro_input, LitStr};
+
+/// Embeds the migrations of a directory in the binary.
+///
+/// See the documentation of `asphalt_migrations::embed_migrations` for more info.
+#[proc_macro]
+pub fn embed_migrations(input: TokenStream) -> TokenStream {
+    let dir = if input.is_empty() {
+        "migrations".to_string()
+    } else {
+        parse_macro_input!(input as LitStr).value()
+    };
+
+    expand(dir)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand(dir: String) -> syn::Result<proc_macro2::TokenStream> {
+    let error = |msg: String| syn::Error::new(Span::call_site(), msg);
+
+    let root = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| error("CARGO_MANIFEST_DIR is not set".to_string()))?;
+    let dir = PathBuf::from(root).join(dir);
+
+    let entries = std::fs::read_dir(&dir)
+        .map_err(|err| error(format!("Failed to read `{}`: {}", dir.display(), err)))?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|err| error(format!("Failed to read `{}`: {}", dir.display(), err)))?
+            .path();
+
+        if path.is_file() && path.extension().map_or(false, |ext| ext == "sql") {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    let names: Vec<_> = files
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    validate(&names).map_err(error)?;
+
+    // Only the file names are read here, the files are embedded with `include_str!` so
+    // that changes trigger a rebuild.
+    let paths = files.iter().map(|path| path.to_string_lossy().into_owned());
+
+    Ok(quote! {
+        ::asphalt_migrations::Migrations::from_sources(::std::vec![
+            #((#names, ::std::include_str!(#paths))),*
+        ])
+    })
+}
+
+/// Checks the names of the migration files as `Migrations::from_sources` does, so that
+/// invalid files are reported at compile time.
+fn validate(file_names: &[String]) -> Result<(), String> {
+    // The name of each version, and whether it has an up script.
+    let mut versions = BTreeMap::new();
+
+    for file_name in file_names {
+        let (version, name, up) = parse_file_name(file_name).ok_or_else(|| {
+            format!(
+                "Invalid migration file name `{}`, expected `<version>_<name>.(up|down).sql`",
+                file_name
+            )
+        })?;
+
+        let (migration_name, has_up) = versions.entry(version).or_insert((name, false));
+        if *migration_name != name || (up && *has_up) {
+            return Err(format!("More than one migration with version {}", version));
+        }
+        *has_up |= up;
+    }
+
+    match versions.iter().find(|(_, (_, has_up))| !has_up) {
+        Some((version, _)) => Err(format!("Migration {} has no up script", version)),
+        None => Ok(()),
+    }
+}
+
+/// Parses a file name in the `<version>_<name>.(up|down).sql` format, returning whether
+/// it is an up script.
+///
+/// Keep in sync with the parser of `asphalt_migrations`.
+fn parse_file_name(file_name: &str) -> Option<(i64, &str, bool)> {
+    let (stem, up) = if let Some(stem) = file_name.strip_suffix(".up.sql") {
+        (stem, true)
+    } else if let Some(stem) = file_name.strip_suffix(".down.sql") {
+        (stem, false)
+    } else {
+        return None;
+    };
+
+    let separator = stem.find('_')?;
+    let (version, name) = (&stem[..separator], &stem[separator + 1..]);
+
+    let valid_name = name
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+    if version.is_empty() || !version.bytes().all(|b| b.is_ascii_digit()) || !valid_name {
+        return None;
+    }
+
+    Some((version.parse().ok()?, name, up))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate_names(file_names: &[&str]) -> Result<(), String> {
+        let file_names: Vec<_> = file_names.iter().map(|name| name.to_string()).collect();
+        validate(&file_names)
+    }
+
+    #[test]
+    fn accepts_valid_migrations() {
+        let res = validate_names(&[
+            "1_create_users.down.sql",
+            "1_create_users.up.sql",
+            "2_add_email.up.sql",
+        ]);
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn rejects_invalid_migrations() {
+        assert_eq!(
+            validate_names(&["create_users.up.sql"]).unwrap_err(),
+            "Invalid migration file name `create_users.up.sql`, expected \
+             `<version>_<name>.(up|down).sql`"
+        );
+        assert_eq!(
+            validate_names(&["1_a.up.sql", "1_b.up.sql"]).unwrap_err(),
+            "More than one migration with version 1"
+        );
+        assert_eq!(
+            validate_names(&["1_a.up.sql", "1_a.up.sql"]).unwrap_err(),
+            "More than one migration with version 1"
+        );
+        assert_eq!(
+            validate_names(&["1_a.down.sql"]).unwrap_err(),
+            "Migration 1 has no up script"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use asphalt_core::backend::Backend;
+use asphalt_core::connection::Connection;
+use asphalt_core::error::QueryResult;
+use asphalt_core::LocalBoxFuture;
+
+/// Backends whose schema can be migrated by a [`Migrator`](crate::Migrator).
+pub trait MigrationBackend: Backend + 'static {
+    /// Acquires the lock preventing concurrent runs of migrations, waiting for it if
+    /// held by another connection.
+    fn lock(conn: &Connection<Self>) -> LocalBoxFuture<'_, QueryResult<()>>;
+
+    /// Releases the lock acquired by [`lock`](MigrationBackend::lock).
+    fn unlock(conn: &Connection<Self>) -> LocalBoxFuture<'_, QueryResult<()>>;
+}
+
+#[cfg(feature = "postgres")]
+mod postgres {
+    use super::*;
+    use asphalt_postgres::Pg;
+
+    /// The key of the advisory lock, "asphalt" in ASCII.
+    const LOCK_KEY: i64 = 0x0061_7370_6861_6c74;
+
+    /// Uses a session-level advisory lock.
+    impl MigrationBackend for Pg {
+        fn lock(conn: &Connection<Self>) -> LocalBoxFuture<'_, QueryResult<()>> {
+            Box::pin(async move {
+                let sql = format!("SELECT pg_advisory_lock({})", LOCK_KEY);
+                conn.batch_execute(&sql).await
+            })
+        }
+
+        fn unlock(conn: &Connection<Self>) -> LocalBoxFuture<'_, QueryResult<()>> {
+            Box::pin(async move {
+                let sql = format!("SELECT pg_advisory_unlock({})", LOCK_KEY);
+                conn.batch_execute(&sql).await
+            })
+        }
+    }
+}
+
+#[cfg(feature = "mysql")]
+mod mysql {
+    use super::*;
+    use asphalt_mysql::MySql;
+
+    /// Uses a named lock, waiting for it without a timeout.
+    impl MigrationBackend for MySql {
+        fn lock(conn: &Connection<Self>) -> LocalBoxFuture<'_, QueryResult<()>> {
+            Box::pin(conn.batch_execute("SELECT GET_LOCK('asphalt_migrations', -1)"))
+        }
+
+        fn unlock(conn: &Connection<Self>) -> LocalBoxFuture<'_, QueryResult<()>> {
+            Box::pin(conn.batch_execute("SELECT RELEASE_LOCK('asphalt_migrations')"))
+        }
+    }
+}
+
+#[cfg(any(test, feature = "sqlite"))]
+mod sqlite {
+    use super::*;
+    use asphalt_sqlite::Sqlite;
+
+    /// SQLite has no advisory locks. Concurrent runs are still safe, as writes lock the
+    /// whole database and each migration is recorded in the same transaction that
+    /// applies it, so a migration applied concurrently fails to be recorded twice.
+    impl MigrationBackend for Sqlite {
+        fn lock(_conn: &Connection<Self>) -> LocalBoxFuture<'_, QueryResult<()>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn unlock(_conn: &Connection<Self>) -> LocalBoxFuture<'_, QueryResult<()>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+}
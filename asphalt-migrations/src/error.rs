@@ -0,0 +1,80 @@
+use asphalt_core::error::Error;
+use std::error::Error as StdError;
+
+/// Errors returned when loading or running migrations.
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The name of a migration file doesn't follow `<version>_<name>.(up|down).sql`.
+    InvalidFileName(String),
+    /// More than one migration has the same version.
+    DuplicateVersion(i64),
+    /// A migration has a `down` script, but no `up` script.
+    MissingUp(i64),
+    /// Failed to read the migrations directory.
+    Io(std::io::Error),
+    /// An applied migration was edited after being applied.
+    ChecksumMismatch { version: i64, name: String },
+    /// An applied migration doesn't exist anymore.
+    UnknownVersion(i64),
+    /// The migration can't be reverted, as it has no `down` script.
+    Irreversible(i64),
+    /// A migration script failed.
+    Failed { version: i64, source: Error },
+    /// Failed to query or update the applied migrations.
+    Database(Error),
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidFileName(name) => write!(
+                f,
+                "Invalid migration file name `{}`, expected `<version>_<name>.(up|down).sql`",
+                name
+            ),
+            Self::DuplicateVersion(version) => {
+                write!(f, "More than one migration with version {}", version)
+            }
+            Self::MissingUp(version) => write!(f, "Migration {} has no up script", version),
+            Self::Io(err) => write!(f, "Error while reading migrations: {}", err),
+            Self::ChecksumMismatch { version, name } => write!(
+                f,
+                "Migration {}_{} was edited after being applied",
+                version, name
+            ),
+            Self::UnknownVersion(version) => {
+                write!(f, "Applied migration {} doesn't exist", version)
+            }
+            Self::Irreversible(version) => {
+                write!(f, "Migration {} has no down script", version)
+            }
+            Self::Failed { version, source } => {
+                write!(f, "Migration {} failed: {}", version, source)
+            }
+            Self::Database(err) => write!(f, "Error while managing migrations: {}", err),
+        }
+    }
+}
+
+impl StdError for MigrationError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Failed { source, .. } => Some(source),
+            Self::Database(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for MigrationError {
+    fn from(error: Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+impl From<std::io::Error> for MigrationError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
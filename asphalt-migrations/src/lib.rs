@@ -0,0 +1,33 @@
+//! Schema migrations for asphalt.
+//!
+//! Migrations are plain SQL scripts, read from a directory at runtime with
+//! [`Migrations::from_directory`], or embedded in the binary with [`embed_migrations!`],
+//! and applied by a [`Migrator`]:
+//!
+//! ```ignore
+//! let migrations = asphalt_migrations::embed_migrations!("migrations")?;
+//! Migrator::new(migrations).run(&conn).await?;
+//! ```
+//!
+//! The backends supported are enabled by the `postgres`, `mysql` and `sqlite` features.
+mod backend;
+mod error;
+mod migration;
+mod migrator;
+
+#[doc(inline)]
+pub use self::backend::MigrationBackend;
+#[doc(inline)]
+pub use self::error::MigrationError;
+#[doc(inline)]
+pub use self::migration::{Migration, Migrations};
+#[doc(inline)]
+pub use self::migrator::{AppliedMigration, Migrator};
+
+/// Embeds the migrations of a directory in the binary, returning a
+/// `Result<Migrations, MigrationError>`.
+///
+/// The directory is relative to the root of the crate, and defaults to `migrations`.
+/// See [`Migrations`] for how the files must be named. Invalid file names, duplicate
+/// versions and migrations without an up script are reported at compile time.
+pub use asphalt_migrations_macros::embed_migrations;
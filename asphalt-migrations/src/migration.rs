@@ -0,0 +1,229 @@
+use crate::MigrationError;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// A schema migration.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    version: i64,
+    name: String,
+    up: Cow<'static, str>,
+    down: Option<Cow<'static, str>>,
+    checksum: String,
+}
+
+impl Migration {
+    /// The version of the migration, which defines the order migrations are applied.
+    pub fn version(&self) -> i64 {
+        self.version
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The SQL script applying the migration.
+    pub fn up(&self) -> &str {
+        &self.up
+    }
+
+    /// The SQL script reverting the migration, if any.
+    pub fn down(&self) -> Option<&str> {
+        self.down.as_deref()
+    }
+
+    /// The SHA-256 hash of the `up` script, in hex.
+    ///
+    /// It is stored when the migration is applied, to detect migrations edited after that.
+    pub fn checksum(&self) -> &str {
+        &self.checksum
+    }
+}
+
+/// An ordered set of migrations.
+///
+/// Each migration is made of two files, `<version>_<name>.up.sql`, applying it, and
+/// `<version>_<name>.down.sql`, reverting it. The latter is optional, but migrations
+/// without it can't be reverted. The version is a positive integer, usually the
+/// timestamp of when the migration was created, e.g. `20200815093000_create_users.up.sql`.
+#[derive(Debug, Clone, Default)]
+pub struct Migrations {
+    /// Sorted by version.
+    migrations: Vec<Migration>,
+}
+
+impl Migrations {
+    /// Creates the migrations from a list of file names and their contents.
+    pub fn from_sources<I, N, S>(sources: I) -> Result<Self, MigrationError>
+    where
+        I: IntoIterator<Item = (N, S)>,
+        N: AsRef<str>,
+        S: Into<Cow<'static, str>>,
+    {
+        let mut scripts = BTreeMap::new();
+
+        for (file_name, sql) in sources {
+            let file_name = file_name.as_ref();
+            let (version, name, direction) = parse_file_name(file_name)
+                .ok_or_else(|| MigrationError::InvalidFileName(file_name.to_string()))?;
+
+            let (migration_name, up, down) = scripts
+                .entry(version)
+                .or_insert_with(|| (name.to_string(), None, None));
+            if migration_name != name {
+                return Err(MigrationError::DuplicateVersion(version));
+            }
+
+            let script = match direction {
+                Direction::Up => up,
+                Direction::Down => down,
+            };
+            if script.replace(sql.into()).is_some() {
+                return Err(MigrationError::DuplicateVersion(version));
+            }
+        }
+
+        let migrations = scripts
+            .into_iter()
+            .map(|(version, (name, up, down))| {
+                let up: Cow<'static, str> = up.ok_or(MigrationError::MissingUp(version))?;
+
+                Ok(Migration {
+                    version,
+                    name,
+                    checksum: checksum(&up),
+                    up,
+                    down,
+                })
+            })
+            .collect::<Result<_, MigrationError>>()?;
+
+        Ok(Self { migrations })
+    }
+
+    /// Reads the migrations from the `.sql` files of a directory.
+    ///
+    /// Other files and subdirectories are ignored.
+    pub fn from_directory(dir: impl AsRef<Path>) -> Result<Self, MigrationError> {
+        let mut sources = Vec::new();
+
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_file() || path.extension().map_or(true, |ext| ext != "sql") {
+                continue;
+            }
+
+            let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+            sources.push((file_name, std::fs::read_to_string(&path)?));
+        }
+
+        Self::from_sources(sources)
+    }
+
+    /// Returns the migration with the given version, if any.
+    pub fn get(&self, version: i64) -> Option<&Migration> {
+        self.migrations
+            .binary_search_by_key(&version, Migration::version)
+            .ok()
+            .map(|idx| &self.migrations[idx])
+    }
+
+    /// Iterates over the migrations, in order.
+    pub fn iter(&self) -> std::slice::Iter<'_, Migration> {
+        self.migrations.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.migrations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.migrations.is_empty()
+    }
+}
+
+impl<'m> IntoIterator for &'m Migrations {
+    type Item = &'m Migration;
+    type IntoIter = std::slice::Iter<'m, Migration>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Direction {
+    Up,
+    Down,
+}
+
+/// Parses a file name in the `<version>_<name>.(up|down).sql` format.
+///
+/// `embed_migrations!` validates the file names at compile time with a copy of this
+/// parser, keep them in sync.
+fn parse_file_name(file_name: &str) -> Option<(i64, &str, Direction)> {
+    let (stem, direction) = if let Some(stem) = file_name.strip_suffix(".up.sql") {
+        (stem, Direction::Up)
+    } else if let Some(stem) = file_name.strip_suffix(".down.sql") {
+        (stem, Direction::Down)
+    } else {
+        return None;
+    };
+
+    let separator = stem.find('_')?;
+    let (version, name) = (&stem[..separator], &stem[separator + 1..]);
+
+    let valid_name = name
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+    if version.is_empty() || !version.bytes().all(|b| b.is_ascii_digit()) || !valid_name {
+        return None;
+    }
+
+    Some((version.parse().ok()?, name, direction))
+}
+
+fn checksum(sql: &str) -> String {
+    Sha256::digest(sql.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_up_and_down_scripts() {
+        let migrations = Migrations::from_sources(vec![
+            ("2_add_email.up.sql", "ALTER TABLE users ADD email TEXT"),
+            ("1_create_users.down.sql", "DROP TABLE users"),
+            ("1_create_users.up.sql", "CREATE TABLE users (id INTEGER)"),
+        ])
+        .unwrap();
+
+        let versions: Vec<_> = migrations.iter().map(Migration::version).collect();
+        assert_eq!(versions, vec![1, 2]);
+
+        let create = migrations.get(1).unwrap();
+        assert_eq!(create.name(), "create_users");
+        assert_eq!(create.down(), Some("DROP TABLE users"));
+        assert_eq!(migrations.get(2).unwrap().down(), None);
+    }
+
+    #[test]
+    fn rejects_invalid_sources() {
+        let err = Migrations::from_sources(vec![("create_users.up.sql", "")]).unwrap_err();
+        assert!(matches!(err, MigrationError::InvalidFileName(_)));
+
+        let err =
+            Migrations::from_sources(vec![("1_a.up.sql", ""), ("1_b.up.sql", "")]).unwrap_err();
+        assert!(matches!(err, MigrationError::DuplicateVersion(1)));
+
+        let err = Migrations::from_sources(vec![("1_a.down.sql", "")]).unwrap_err();
+        assert!(matches!(err, MigrationError::MissingUp(1)));
+    }
+}
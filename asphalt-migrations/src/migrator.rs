@@ -0,0 +1,379 @@
+use crate::{Migration, MigrationBackend, MigrationError, Migrations};
+use asphalt_core::backend::HasSqlType;
+use asphalt_core::connection::{get_field, Connection, FromRow, Row, RowOf};
+use asphalt_core::error::QueryResult;
+use asphalt_core::types::{BigInt, FromSql, Text};
+use std::future::Future;
+
+/// The default name of the table tracking the applied migrations.
+const DEFAULT_TABLE_NAME: &str = "__asphalt_migrations";
+
+/// A migration recorded as applied in the database.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    /// The checksum of the migration when it was applied.
+    pub checksum: String,
+}
+
+impl<'r, R> FromRow<'r, R> for AppliedMigration
+where
+    R: Row,
+    R::Backend: HasSqlType<BigInt> + HasSqlType<Text>,
+    i64: FromSql<'r, BigInt, R::Backend>,
+    String: FromSql<'r, Text, R::Backend>,
+{
+    fn from_row(row: &'r R) -> QueryResult<Self> {
+        Ok(Self {
+            version: get_field::<_, BigInt, _>(row, "version", 0)?,
+            name: get_field::<_, Text, _>(row, "name", 1)?,
+            checksum: get_field::<_, Text, _>(row, "checksum", 2)?,
+        })
+    }
+}
+
+/// Applies and reverts [`Migrations`].
+///
+/// The applied migrations are tracked in a bookkeeping table, created on the first run,
+/// which records the version, name and checksum of each one. Before doing anything, the
+/// migrator checks that all the applied migrations still exist and weren't edited since.
+///
+/// Each migration runs inside its own transaction, together with its bookkeeping, so a
+/// failed migration leaves no trace. Note that some backends, like MySQL, implicitly
+/// commit schema changes, so failed migrations may need to be fixed by hand there.
+///
+/// Concurrent runs, e.g. by multiple instances of an application starting at the same
+/// time, are serialized with the lock provided by the [`MigrationBackend`].
+#[derive(Debug, Clone)]
+pub struct Migrator {
+    migrations: Migrations,
+    table_name: String,
+}
+
+impl Migrator {
+    pub fn new(migrations: Migrations) -> Self {
+        Self {
+            migrations,
+            table_name: DEFAULT_TABLE_NAME.to_string(),
+        }
+    }
+
+    /// Sets the name of the bookkeeping table, `__asphalt_migrations` by default.
+    ///
+    /// The name is quoted as an identifier, so it can't be qualified with a schema.
+    pub fn table_name(mut self, table_name: impl Into<String>) -> Self {
+        self.table_name = table_name.into();
+        self
+    }
+
+    pub fn migrations(&self) -> &Migrations {
+        &self.migrations
+    }
+
+    /// Returns the applied migrations, in order.
+    pub async fn applied<Db>(
+        &self,
+        conn: &Connection<Db>,
+    ) -> Result<Vec<AppliedMigration>, MigrationError>
+    where
+        Db: MigrationBackend,
+        AppliedMigration: for<'r> FromRow<'r, RowOf<Db>>,
+    {
+        self.create_table(conn).await?;
+        self.load_applied(conn).await
+    }
+
+    /// Returns the migrations not applied yet, in order.
+    pub async fn pending<Db>(
+        &self,
+        conn: &Connection<Db>,
+    ) -> Result<Vec<&Migration>, MigrationError>
+    where
+        Db: MigrationBackend,
+        AppliedMigration: for<'r> FromRow<'r, RowOf<Db>>,
+    {
+        let applied = self.applied(conn).await?;
+        self.validate(&applied)?;
+
+        Ok(self.pending_of(&applied))
+    }
+
+    /// Applies all the pending migrations, returning their versions.
+    pub async fn run<Db>(&self, conn: &Connection<Db>) -> Result<Vec<i64>, MigrationError>
+    where
+        Db: MigrationBackend,
+        AppliedMigration: for<'r> FromRow<'r, RowOf<Db>>,
+    {
+        self.locked(conn, async {
+            let applied = self.applied(conn).await?;
+            self.validate(&applied)?;
+
+            let mut versions = Vec::new();
+            for migration in self.pending_of(&applied) {
+                self.apply(conn, migration).await?;
+                versions.push(migration.version());
+            }
+
+            Ok(versions)
+        })
+        .await
+    }
+
+    /// Reverts the last applied migration, returning its version, if any.
+    pub async fn revert_last<Db>(
+        &self,
+        conn: &Connection<Db>,
+    ) -> Result<Option<i64>, MigrationError>
+    where
+        Db: MigrationBackend,
+        AppliedMigration: for<'r> FromRow<'r, RowOf<Db>>,
+    {
+        self.locked(conn, async {
+            let applied = self.applied(conn).await?;
+            self.validate(&applied)?;
+
+            let last = match applied.last() {
+                Some(last) => last,
+                None => return Ok(None),
+            };
+
+            // The migration exists, it was validated above.
+            let migration = self.migrations.get(last.version).unwrap();
+            self.revert(conn, migration).await?;
+
+            Ok(Some(last.version))
+        })
+        .await
+    }
+
+    /// Runs `fut` while holding the migrations lock.
+    async fn locked<Db, F, T>(&self, conn: &Connection<Db>, fut: F) -> Result<T, MigrationError>
+    where
+        Db: MigrationBackend,
+        F: Future<Output = Result<T, MigrationError>>,
+    {
+        Db::lock(conn).await?;
+        let res = fut.await;
+        let unlocked = Db::unlock(conn).await;
+
+        // Errors of the migrations are more relevant than the ones releasing the lock.
+        let value = res?;
+        unlocked?;
+
+        Ok(value)
+    }
+
+    async fn create_table<Db: MigrationBackend>(
+        &self,
+        conn: &Connection<Db>,
+    ) -> Result<(), MigrationError> {
+        let sql = self.table_statement(
+            conn,
+            "CREATE TABLE IF NOT EXISTS ",
+            " (\
+                version BIGINT NOT NULL PRIMARY KEY, \
+                name VARCHAR(255) NOT NULL, \
+                checksum VARCHAR(64) NOT NULL, \
+                applied_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP\
+            )",
+        );
+
+        Ok(conn.batch_execute(&sql).await?)
+    }
+
+    async fn load_applied<Db>(
+        &self,
+        conn: &Connection<Db>,
+    ) -> Result<Vec<AppliedMigration>, MigrationError>
+    where
+        Db: MigrationBackend,
+        AppliedMigration: for<'r> FromRow<'r, RowOf<Db>>,
+    {
+        let mut query = conn.query_builder();
+        query.push_sql("SELECT version, name, checksum FROM ");
+        query.push_identifier(&self.table_name);
+        query.push_sql(" ORDER BY version");
+
+        Ok(conn.load(query).await?)
+    }
+
+    /// Returns the statement `prefix table_name suffix`, quoting the name of the
+    /// bookkeeping table for the backend.
+    fn table_statement<Db: MigrationBackend>(
+        &self,
+        conn: &Connection<Db>,
+        prefix: &str,
+        suffix: &str,
+    ) -> String {
+        let mut query = conn.query_builder();
+        query.push_sql(prefix);
+        query.push_identifier(&self.table_name);
+        query.push_sql(suffix);
+
+        query.sql().to_string()
+    }
+
+    /// Checks that the applied migrations exist, and weren't edited.
+    fn validate(&self, applied: &[AppliedMigration]) -> Result<(), MigrationError> {
+        for applied in applied {
+            let migration = self
+                .migrations
+                .get(applied.version)
+                .ok_or(MigrationError::UnknownVersion(applied.version))?;
+
+            if migration.checksum() != applied.checksum {
+                return Err(MigrationError::ChecksumMismatch {
+                    version: applied.version,
+                    name: applied.name.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn pending_of(&self, applied: &[AppliedMigration]) -> Vec<&Migration> {
+        self.migrations
+            .iter()
+            .filter(|migration| {
+                applied
+                    .binary_search_by_key(&migration.version(), |applied| applied.version)
+                    .is_err()
+            })
+            .collect()
+    }
+
+    async fn apply<Db: MigrationBackend>(
+        &self,
+        conn: &Connection<Db>,
+        migration: &Migration,
+    ) -> Result<(), MigrationError> {
+        let version = migration.version();
+        // The name and checksum only contain alphanumeric characters, `_` and `-`, so
+        // they can be safely inlined.
+        let record = self.table_statement(
+            conn,
+            "INSERT INTO ",
+            &format!(
+                " (version, name, checksum) VALUES ({}, '{}', '{}')",
+                version,
+                migration.name(),
+                migration.checksum()
+            ),
+        );
+
+        conn.transaction(async {
+            conn.batch_execute(migration.up())
+                .await
+                .map_err(|source| MigrationError::Failed { version, source })?;
+            conn.batch_execute(&record).await?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn revert<Db: MigrationBackend>(
+        &self,
+        conn: &Connection<Db>,
+        migration: &Migration,
+    ) -> Result<(), MigrationError> {
+        let version = migration.version();
+        let down = migration
+            .down()
+            .ok_or(MigrationError::Irreversible(version))?;
+        let forget = self.table_statement(
+            conn,
+            "DELETE FROM ",
+            &format!(" WHERE version = {}", version),
+        );
+
+        conn.transaction(async {
+            conn.batch_execute(down)
+                .await
+                .map_err(|source| MigrationError::Failed { version, source })?;
+            conn.batch_execute(&forget).await?;
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asphalt_sqlite::{Config, Sqlite};
+    use futures_executor::block_on;
+
+    fn migrations(create_users: &'static str) -> Migrations {
+        Migrations::from_sources(vec![
+            ("1_create_users.up.sql", create_users),
+            ("1_create_users.down.sql", "DROP TABLE users"),
+            ("2_create_posts.up.sql", "CREATE TABLE posts (id INTEGER)"),
+            ("2_create_posts.down.sql", "DROP TABLE posts"),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn applies_and_reverts_migrations() {
+        let conn = block_on(Connection::<Sqlite>::establish(Config::Memory)).unwrap();
+        let migrator = Migrator::new(migrations("CREATE TABLE users (id INTEGER)"));
+
+        assert_eq!(block_on(migrator.run(&conn)).unwrap(), vec![1, 2]);
+        assert_eq!(block_on(migrator.run(&conn)).unwrap(), Vec::<i64>::new());
+
+        assert_eq!(block_on(migrator.revert_last(&conn)).unwrap(), Some(2));
+        let applied = block_on(migrator.applied(&conn)).unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].name, "create_users");
+
+        // The posts table was dropped, so it can be created again.
+        assert_eq!(block_on(migrator.run(&conn)).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn rolls_back_failed_migrations() {
+        let conn = block_on(Connection::<Sqlite>::establish(Config::Memory)).unwrap();
+        let migrator = Migrator::new(
+            Migrations::from_sources(vec![
+                ("1_create_users.up.sql", "CREATE TABLE users (id INTEGER)"),
+                ("2_broken.up.sql", "ALTER TABLE missing ADD email TEXT"),
+            ])
+            .unwrap(),
+        );
+
+        let err = block_on(migrator.run(&conn)).unwrap_err();
+        assert!(matches!(err, MigrationError::Failed { version: 2, .. }));
+
+        let pending = block_on(migrator.pending(&conn)).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].version(), 2);
+    }
+
+    #[test]
+    fn quotes_the_table_name() {
+        let conn = block_on(Connection::<Sqlite>::establish(Config::Memory)).unwrap();
+        let migrator = Migrator::new(migrations("CREATE TABLE users (id INTEGER)"))
+            .table_name("schema \"history\"; DROP TABLE users");
+
+        assert_eq!(block_on(migrator.run(&conn)).unwrap(), vec![1, 2]);
+        assert_eq!(block_on(migrator.revert_last(&conn)).unwrap(), Some(2));
+        assert_eq!(block_on(migrator.applied(&conn)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn detects_edited_migrations() {
+        let conn = block_on(Connection::<Sqlite>::establish(Config::Memory)).unwrap();
+        block_on(Migrator::new(migrations("CREATE TABLE users (id INTEGER)")).run(&conn)).unwrap();
+
+        let edited = Migrator::new(migrations("CREATE TABLE users (id BIGINT)"));
+        let err = block_on(edited.run(&conn)).unwrap_err();
+        assert!(matches!(
+            err,
+            MigrationError::ChecksumMismatch { version: 1, .. }
+        ));
+    }
+}
@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+/// Configuration of a [`Pool`](crate::Pool).
+#[derive(Debug, Copy, Clone)]
+pub struct PoolConfig {
+    /// Minimum number of connections the pool tries to keep open.
+    pub min_size: usize,
+    /// Maximum number of connections, idle or checked out, managed by the pool.
+    pub max_size: usize,
+    /// How long a connection can stay idle before being closed.
+    ///
+    /// Idle connections are never closed if this would shrink the pool below `min_size`.
+    pub idle_timeout: Option<Duration>,
+    /// Maximum lifetime of a connection, after which it is closed instead of returned to the pool.
+    pub max_lifetime: Option<Duration>,
+    /// How long to wait for a connection when checking one out of the pool.
+    pub checkout_timeout: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 0,
+            max_size: 10,
+            idle_timeout: Some(Duration::from_secs(10 * 60)),
+            max_lifetime: Some(Duration::from_secs(30 * 60)),
+            checkout_timeout: Duration::from_secs(30),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::error::Error as StdError;
+
+/// Errors returned when checking out a connection from a [`Pool`](crate::Pool).
+#[derive(Debug)]
+pub enum PoolError<E> {
+    /// No connection became available before the checkout timeout.
+    Timeout,
+    /// Failed to establish a new connection.
+    Establish(E),
+}
+
+impl<E> std::fmt::Display for PoolError<E>
+where
+    E: std::fmt::Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Timeout => f.write_str("Timed out while waiting for a connection"),
+            Self::Establish(err) => write!(f, "Error while establishing connection: {}", err),
+        }
+    }
+}
+
+impl<E> StdError for PoolError<E>
+where
+    E: StdError + 'static,
+{
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Timeout => None,
+            Self::Establish(err) => Some(err),
+        }
+    }
+}
@@ -0,0 +1,10 @@
+mod config;
+mod error;
+mod pool;
+
+#[doc(inline)]
+pub use self::config::PoolConfig;
+#[doc(inline)]
+pub use self::error::PoolError;
+#[doc(inline)]
+pub use self::pool::{Pool, PooledConnection};
@@ -0,0 +1,518 @@
+use crate::{PoolConfig, PoolError};
+use asphalt_core::backend::Backend;
+use asphalt_core::connection::{Connection, RawConnection};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+type ConnConfig<Db> = <<Db as Backend>::RawConnection as RawConnection>::Config;
+type EstablishError<Db> = <<Db as Backend>::RawConnection as RawConnection>::EstablishError;
+
+/// A pool of [`Connection`]s.
+///
+/// Connections are established lazily, up to `max_size`, and reused between checkouts.
+/// Checkouts also establish idle connections when the pool has less than `min_size` of
+/// them, e.g. after some were closed. Connections in a broken state (see
+/// [`Connection::is_broken`]) are closed when returned instead of being put back in the
+/// pool.
+///
+/// Cloning a pool is cheap, and all the clones share the same connections.
+pub struct Pool<Db: Backend> {
+    inner: Arc<PoolInner<Db>>,
+}
+
+struct PoolInner<Db: Backend> {
+    config: PoolConfig,
+    conn_config: ConnConfig<Db>,
+    idle: Mutex<VecDeque<IdleConnection<Db>>>,
+    /// Limits the number of checked out connections.
+    semaphore: Arc<Semaphore>,
+    /// Number of open connections, idle or checked out, including the ones being
+    /// established.
+    size: AtomicUsize,
+    /// Notified when a connection is put back in the pool, or is closed.
+    available: Notify,
+}
+
+struct IdleConnection<Db: Backend> {
+    conn: Connection<Db>,
+    created_at: Instant,
+    idle_since: Instant,
+}
+
+impl<Db: Backend> Clone for Pool<Db> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<Db> Pool<Db>
+where
+    Db: Backend,
+    ConnConfig<Db>: Clone,
+{
+    /// Create a new pool, establishing `min_size` connections upfront.
+    ///
+    /// # Panics
+    ///
+    /// If `max_size` is zero or smaller than `min_size`.
+    pub async fn new(
+        config: PoolConfig,
+        conn_config: ConnConfig<Db>,
+    ) -> Result<Self, PoolError<EstablishError<Db>>> {
+        assert!(
+            config.max_size > 0,
+            "Pool max_size must be greater than zero"
+        );
+        assert!(
+            config.min_size <= config.max_size,
+            "Pool min_size must not be greater than max_size"
+        );
+
+        let pool = Self {
+            inner: Arc::new(PoolInner {
+                config,
+                conn_config,
+                idle: Mutex::new(VecDeque::with_capacity(config.max_size)),
+                semaphore: Arc::new(Semaphore::new(config.max_size)),
+                size: AtomicUsize::new(0),
+                available: Notify::new(),
+            }),
+        };
+
+        pool.inner.replenish().await?;
+
+        Ok(pool)
+    }
+
+    /// Check out a connection from the pool.
+    ///
+    /// If there is no idle connection, a new one is established, unless the pool is
+    /// already at `max_size`, in which case this waits for a connection to be returned.
+    /// Fails with [`PoolError::Timeout`] if no connection is available before the
+    /// configured checkout timeout.
+    pub async fn get(&self) -> Result<PooledConnection<Db>, PoolError<EstablishError<Db>>> {
+        let timeout = self.inner.config.checkout_timeout;
+
+        match tokio::time::timeout(timeout, self.checkout()).await {
+            Ok(res) => res,
+            Err(_) => Err(PoolError::Timeout),
+        }
+    }
+
+    /// Returns the number of open connections, idle or checked out.
+    pub fn size(&self) -> usize {
+        self.inner.size.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of idle connections.
+    pub fn idle_connections(&self) -> usize {
+        self.inner.idle.lock().len()
+    }
+
+    async fn checkout(&self) -> Result<PooledConnection<Db>, PoolError<EstablishError<Db>>> {
+        let permit = self.inner.semaphore.clone().acquire_owned().await;
+
+        self.inner.reap();
+        let replenished = self.inner.replenish().await;
+
+        let (conn, created_at) = loop {
+            // Prefer the most recently used connection, letting the older ones expire.
+            let idle = self.inner.idle.lock().pop_back();
+            if let Some(idle) = idle {
+                break (idle.conn, idle.created_at);
+            }
+
+            if let Err(err) = replenished {
+                return Err(err);
+            }
+            if self.inner.reserve_below(self.inner.config.max_size) {
+                break (self.inner.establish().await?, Instant::now());
+            }
+
+            // The pool is full, but the connections which aren't checked out are still
+            // being established by other checkouts.
+            self.inner.available.notified().await;
+        };
+
+        Ok(PooledConnection {
+            conn: Some(conn),
+            created_at,
+            pool: self.inner.clone(),
+            _permit: permit,
+        })
+    }
+}
+
+impl<Db> PoolInner<Db>
+where
+    Db: Backend,
+    ConnConfig<Db>: Clone,
+{
+    /// Establish a connection, in a slot reserved with [`reserve_below`].
+    ///
+    /// The slot is given back if the connection fails.
+    ///
+    /// [`reserve_below`]: PoolInner::reserve_below
+    async fn establish(&self) -> Result<Connection<Db>, PoolError<EstablishError<Db>>> {
+        Connection::establish(self.conn_config.clone())
+            .await
+            .map_err(|err| {
+                self.free_slot();
+                PoolError::Establish(err)
+            })
+    }
+
+    /// Establish idle connections until the pool has `min_size` connections.
+    ///
+    /// Broken and expired connections are closed when found, so this is what keeps
+    /// `min_size` as a floor of the pool size.
+    async fn replenish(&self) -> Result<(), PoolError<EstablishError<Db>>> {
+        while self.reserve_below(self.config.min_size) {
+            let conn = self.establish().await?;
+            let now = Instant::now();
+
+            self.idle.lock().push_back(IdleConnection {
+                conn,
+                created_at: now,
+                idle_since: now,
+            });
+            self.available.notify();
+        }
+
+        Ok(())
+    }
+}
+
+impl<Db: Backend> PoolInner<Db> {
+    fn is_expired(&self, created_at: Instant, now: Instant) -> bool {
+        self.config
+            .max_lifetime
+            .map_or(false, |lifetime| now.duration_since(created_at) >= lifetime)
+    }
+
+    /// Count a new connection if the pool has less than `limit` connections.
+    ///
+    /// Reserving before establishing prevents concurrent checkouts from overshooting.
+    fn reserve_below(&self, limit: usize) -> bool {
+        self.size
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |size| {
+                if size < limit {
+                    Some(size + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+
+    /// Stop counting a connection which was closed, or failed to be established.
+    fn free_slot(&self) {
+        self.size.fetch_sub(1, Ordering::AcqRel);
+        self.available.notify();
+    }
+
+    /// Close the idle connections that exceeded their lifetime or idle timeout.
+    fn reap(&self) {
+        let now = Instant::now();
+        let mut idle = self.idle.lock();
+
+        idle.retain(|conn| {
+            let timed_out = self.config.idle_timeout.map_or(false, |timeout| {
+                now.duration_since(conn.idle_since) >= timeout
+                    && self.size.load(Ordering::Acquire) > self.config.min_size
+            });
+
+            if timed_out || self.is_expired(conn.created_at, now) {
+                self.free_slot();
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    fn release(&self, conn: Connection<Db>, created_at: Instant) {
+        let now = Instant::now();
+
+        if conn.is_broken() || self.is_expired(created_at, now) {
+            self.free_slot();
+            return;
+        }
+
+        self.idle.lock().push_back(IdleConnection {
+            conn,
+            created_at,
+            idle_since: now,
+        });
+        self.available.notify();
+    }
+}
+
+/// A connection checked out from a [`Pool`].
+///
+/// The connection is returned to the pool when this guard is dropped.
+pub struct PooledConnection<Db: Backend> {
+    conn: Option<Connection<Db>>,
+    created_at: Instant,
+    pool: Arc<PoolInner<Db>>,
+    // Released only after the connection is back in the pool.
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<Db: Backend> Deref for PooledConnection<Db> {
+    type Target = Connection<Db>;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn
+            .as_ref()
+            .expect("Used a released PooledConnection!")
+    }
+}
+
+impl<Db: Backend> DerefMut for PooledConnection<Db> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn
+            .as_mut()
+            .expect("Used a released PooledConnection!")
+    }
+}
+
+impl<Db: Backend> Drop for PooledConnection<Db> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.release(conn, self.created_at);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asphalt_core::error::{DatabaseErrorKind, QueryResult};
+    use asphalt_mock::{Mock, MockDatabase, Response};
+    use std::time::Duration;
+
+    fn config() -> PoolConfig {
+        PoolConfig {
+            min_size: 0,
+            max_size: 2,
+            idle_timeout: None,
+            max_lifetime: None,
+            checkout_timeout: Duration::from_millis(50),
+        }
+    }
+
+    async fn pool(config: PoolConfig, db: &MockDatabase) -> Pool<Mock> {
+        Pool::new(config, db.clone()).await.unwrap()
+    }
+
+    /// Leaves the connection broken, by failing to rollback a transaction.
+    async fn break_connection(conn: &Connection<Mock>, db: &MockDatabase) {
+        db.when("DELETE FROM users").once().respond(Response::error(
+            DatabaseErrorKind::CheckViolation,
+            "can't delete users",
+        ));
+        db.when("ROLLBACK").once().respond(Response::error(
+            DatabaseErrorKind::ConnectionFailure,
+            "connection reset",
+        ));
+
+        let res: QueryResult<()> = conn
+            .transaction(conn.batch_execute("DELETE FROM users"))
+            .await;
+        assert!(res.is_err());
+        assert!(conn.is_broken());
+    }
+
+    #[tokio::test]
+    async fn establishes_min_size_connections() {
+        let db = MockDatabase::new();
+        let pool = pool(
+            PoolConfig {
+                min_size: 2,
+                max_size: 3,
+                ..config()
+            },
+            &db,
+        )
+        .await;
+
+        assert_eq!(pool.size(), 2);
+        assert_eq!(pool.idle_connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn reuses_returned_connections() {
+        let db = MockDatabase::new();
+        let pool = pool(config(), &db).await;
+
+        drop(pool.get().await.unwrap());
+        assert_eq!(pool.idle_connections(), 1);
+
+        let _conn = pool.get().await.unwrap();
+        assert_eq!(pool.size(), 1);
+        assert_eq!(pool.idle_connections(), 0);
+    }
+
+    #[tokio::test]
+    async fn times_out_at_max_size() {
+        let db = MockDatabase::new();
+        let pool = pool(config(), &db).await;
+
+        let first = pool.get().await.unwrap();
+        let _second = pool.get().await.unwrap();
+
+        let err = pool.get().await.err().unwrap();
+        assert!(matches!(err, PoolError::Timeout));
+        assert_eq!(pool.size(), 2);
+
+        drop(first);
+        let _third = pool.get().await.unwrap();
+        assert_eq!(pool.size(), 2);
+    }
+
+    #[tokio::test]
+    async fn doesnt_exceed_max_size_with_concurrent_checkouts() {
+        let db = MockDatabase::new();
+        let pool = pool(
+            PoolConfig {
+                min_size: 1,
+                ..config()
+            },
+            &db,
+        )
+        .await;
+
+        let conn = pool.get().await.unwrap();
+        break_connection(&conn, &db).await;
+        drop(conn);
+        assert_eq!(pool.size(), 0);
+
+        // The first checkout replenishes the pool up to `min_size`, while the second one
+        // establishes its own connection, both at the same time.
+        db.slow_connections(true);
+        let (first, second, third) = tokio::join!(pool.get(), pool.get(), pool.get());
+
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+        assert!(matches!(third.err().unwrap(), PoolError::Timeout));
+        assert_eq!(pool.size(), 2);
+
+        drop((first, second));
+        assert_eq!(pool.idle_connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn gives_back_the_slots_of_failed_connections() {
+        let db = MockDatabase::new();
+        let pool = pool(config(), &db).await;
+
+        db.refuse_connections(true);
+        let err = pool.get().await.err().unwrap();
+        assert!(matches!(err, PoolError::Establish(_)));
+        assert_eq!(pool.size(), 0);
+
+        db.refuse_connections(false);
+        let _first = pool.get().await.unwrap();
+        let _second = pool.get().await.unwrap();
+        assert_eq!(pool.size(), 2);
+    }
+
+    #[tokio::test]
+    async fn discards_broken_connections() {
+        let db = MockDatabase::new();
+        let pool = pool(config(), &db).await;
+
+        let conn = pool.get().await.unwrap();
+        break_connection(&conn, &db).await;
+        drop(conn);
+
+        assert_eq!(pool.size(), 0);
+        assert_eq!(pool.idle_connections(), 0);
+        assert!(!pool.get().await.unwrap().is_broken());
+    }
+
+    #[tokio::test]
+    async fn replenishes_up_to_min_size() {
+        let db = MockDatabase::new();
+        let pool = pool(
+            PoolConfig {
+                min_size: 2,
+                ..config()
+            },
+            &db,
+        )
+        .await;
+
+        let conn = pool.get().await.unwrap();
+        break_connection(&conn, &db).await;
+        drop(conn);
+        assert_eq!(pool.size(), 1);
+
+        let _conn = pool.get().await.unwrap();
+        assert_eq!(pool.size(), 2);
+        assert_eq!(pool.idle_connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn closes_idle_connections_above_min_size() {
+        let db = MockDatabase::new();
+        let pool = pool(
+            PoolConfig {
+                min_size: 1,
+                idle_timeout: Some(Duration::from_millis(20)),
+                ..config()
+            },
+            &db,
+        )
+        .await;
+
+        let first = pool.get().await.unwrap();
+        let second = pool.get().await.unwrap();
+        drop((first, second));
+        assert_eq!(pool.idle_connections(), 2);
+
+        tokio::time::delay_for(Duration::from_millis(40)).await;
+        db.refuse_connections(true);
+
+        // The connection kept to honor `min_size` is reused.
+        let _conn = pool.get().await.unwrap();
+        assert_eq!(pool.size(), 1);
+        assert_eq!(pool.idle_connections(), 0);
+    }
+
+    #[tokio::test]
+    async fn closes_expired_connections() {
+        let db = MockDatabase::new();
+        let pool = pool(
+            PoolConfig {
+                max_lifetime: Some(Duration::from_millis(20)),
+                ..config()
+            },
+            &db,
+        )
+        .await;
+
+        // Expired when returned.
+        let conn = pool.get().await.unwrap();
+        tokio::time::delay_for(Duration::from_millis(40)).await;
+        drop(conn);
+        assert_eq!(pool.size(), 0);
+
+        // Expired while idle.
+        drop(pool.get().await.unwrap());
+        tokio::time::delay_for(Duration::from_millis(40)).await;
+        db.refuse_connections(true);
+
+        let err = pool.get().await.err().unwrap();
+        assert!(matches!(err, PoolError::Establish(_)));
+        assert_eq!(pool.size(), 0);
+    }
+}
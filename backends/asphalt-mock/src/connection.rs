@@ -0,0 +1,518 @@
+use crate::database::Response;
+use crate::{Mock, MockDatabase, MockValue, Value};
+use asphalt_core::backend::{HasSqlType, TypeMetadata};
+use asphalt_core::connection::{
+    ColumnDescriptor, ColumnTypeMismatch, EstablishResult, RawConnection, Row, RowStream,
+};
+use asphalt_core::error::{AnyResult, DatabaseErrorKind, Error, QueryResult};
+use asphalt_core::query::Query;
+use asphalt_core::sql::AnsiTransactionManager;
+use asphalt_core::types::FromSql;
+use asphalt_core::LocalBoxFuture;
+use futures_util::future::{self, FutureExt};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::task::Poll;
+
+/// Returns `Pending` once, waking the task right away.
+async fn yield_now() {
+    let mut yielded = false;
+    future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+pub struct MockRawConnection {
+    db: MockDatabase,
+    manager: AnsiTransactionManager,
+}
+
+impl RawConnection for MockRawConnection {
+    type Backend = Mock;
+    type TransactionManager = AnsiTransactionManager;
+    type Row = MockRow;
+    type Config = MockDatabase;
+    type EstablishError = Error;
+
+    fn establish(config: Self::Config) -> LocalBoxFuture<'static, EstablishResult<Self>> {
+        Box::pin(async move {
+            if config.is_slowing_connections() {
+                yield_now().await;
+            }
+
+            if config.is_refusing_connections() {
+                return Err(Error::database_error(
+                    DatabaseErrorKind::ConnectionFailure,
+                    String::from("Connection refused by the mock database"),
+                ));
+            }
+
+            Ok(Self {
+                db: config,
+                manager: AnsiTransactionManager::default(),
+            })
+        })
+    }
+
+    fn transaction_manager(&self) -> &Self::TransactionManager {
+        &self.manager
+    }
+
+    fn simple_execute<'s>(&'s self, sql: &'s str) -> LocalBoxFuture<'s, QueryResult<()>> {
+        Box::pin(async move {
+            self.db.execute(sql, Vec::new()).into_result(sql)?;
+            Ok(())
+        })
+    }
+
+    fn execute(&self, query: Query<Self::Backend>) -> LocalBoxFuture<'_, QueryResult<u64>> {
+        Box::pin(async move {
+            let sql = query.inner.sql;
+            let response = self
+                .db
+                .execute(&sql, query.binds.into_binds())
+                .into_result(&sql)?;
+
+            match response {
+                Response::Rows { rows, .. } => Ok(rows.len() as u64),
+                Response::Affected(count) => Ok(count),
+                Response::Error { .. } => unreachable!("Errors are returned by into_result"),
+            }
+        })
+    }
+
+    fn query(
+        &self,
+        query: Query<Self::Backend>,
+    ) -> LocalBoxFuture<'_, QueryResult<RowStream<'_, Self>>> {
+        Box::pin(async move {
+            let sql = query.inner.sql;
+            let response = self
+                .db
+                .execute(&sql, query.binds.into_binds())
+                .into_result(&sql)?;
+
+            let rows = match response {
+                Response::Rows { columns, rows } => {
+                    let columns: Arc<[String]> = columns.into();
+                    rows.into_iter()
+                        .map(|values| {
+                            Ok(MockRow {
+                                columns: columns.clone(),
+                                values,
+                            })
+                        })
+                        .collect()
+                }
+                Response::Affected(_) => Vec::new(),
+                Response::Error { .. } => unreachable!("Errors are returned by into_result"),
+            };
+
+            Ok(Box::pin(futures_util::stream::iter(rows)) as RowStream<'_, Self>)
+        })
+    }
+
+    fn metadata_lookup(&self) -> &<Self::Backend as TypeMetadata>::MetadataLookup {
+        &()
+    }
+}
+
+/// A row scripted with [`Response::rows`](crate::Response::rows).
+#[derive(Debug, Clone)]
+pub struct MockRow {
+    columns: Arc<[String]>,
+    values: Vec<Value>,
+}
+
+impl Row for MockRow {
+    type Backend = Mock;
+
+    fn n_columns(&self) -> usize {
+        self.values.len()
+    }
+
+    fn column(&self, idx: usize) -> Option<ColumnDescriptor<'_, Mock>> {
+        // Like in SQLite, the type of the column is the type of its value in this row.
+        let value = self.values.get(idx)?;
+        let name = self.columns.get(idx)?;
+        Some(ColumnDescriptor::new(name, value.type_name()))
+    }
+
+    fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|col| col == name)
+    }
+
+    fn get_column<'a, SqlTy, RustTy>(&'a self, idx: usize) -> AnyResult<RustTy>
+    where
+        Self::Backend: HasSqlType<SqlTy>,
+        RustTy: FromSql<'a, SqlTy, Self::Backend>,
+    {
+        let value = self
+            .values
+            .get(idx)
+            .ok_or_else(|| format!("Column index {} out of bounds", idx))?;
+
+        // The metadata of the mock types is always available immediately.
+        let expected = <Mock as HasSqlType<SqlTy>>::metadata(&()).now_or_never();
+        if let Some(expected) = expected.transpose()? {
+            if !crate::types::is_compatible(expected, value.type_name()) {
+                return Err(Box::new(ColumnTypeMismatch {
+                    column: self.columns.get(idx).cloned().unwrap_or_default(),
+                    expected: expected.to_string(),
+                    actual: value.type_name().to_string(),
+                }));
+            }
+        }
+
+        self.get_column_unchecked::<SqlTy, RustTy>(idx)
+    }
+
+    fn get_column_unchecked<'a, SqlTy, RustTy>(&'a self, idx: usize) -> AnyResult<RustTy>
+    where
+        Self::Backend: HasSqlType<SqlTy>,
+        RustTy: FromSql<'a, SqlTy, Self::Backend>,
+    {
+        let value = self
+            .values
+            .get(idx)
+            .ok_or_else(|| format!("Column index {} out of bounds", idx))?;
+
+        RustTy::from_sql(&value.type_name(), MockValue(Cow::Borrowed(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Pattern, RecordedStatement};
+    use asphalt_core::connection::{get_field, Connection, FromRow};
+    use asphalt_core::error::ErrorKind;
+    use asphalt_core::types::{BigInt, Integer, Nullable, Text};
+    use futures_executor::block_on;
+    use futures_util::TryStreamExt;
+
+    fn connection(db: &MockDatabase) -> Connection<Mock> {
+        block_on(Connection::establish(db.clone())).unwrap()
+    }
+
+    async fn insert_user(
+        conn: &Connection<Mock>,
+        name: &str,
+        age: Option<i32>,
+    ) -> QueryResult<u64> {
+        let mut query = conn.query_builder();
+        query.push_sql("INSERT INTO ");
+        query.push_identifier("users");
+        query.push_sql(" (name, age) VALUES (");
+        query.push_bind_param::<Text, _>(&name).await?;
+        query.push_sql(", ");
+        query.push_bind_param::<Nullable<Integer>, _>(&age).await?;
+        query.push_sql(")");
+
+        conn.executes(query).await
+    }
+
+    fn select(conn: &Connection<Mock>, sql: &str) -> QueryResult<Vec<MockRow>> {
+        let mut query = conn.query_builder();
+        query.push_sql(sql);
+
+        block_on(async { conn.query(query).await?.try_collect().await })
+    }
+
+    #[test]
+    fn records_statements_and_binds() {
+        let db = MockDatabase::new();
+        let conn = connection(&db);
+
+        block_on(insert_user(&conn, "asphalt", None)).unwrap();
+
+        assert_eq!(
+            db.recorded(),
+            vec![RecordedStatement {
+                sql: String::from("INSERT INTO \"users\" (name, age) VALUES ($1, $2)"),
+                binds: vec![Value::from("asphalt"), Value::Null],
+            }]
+        );
+    }
+
+    #[test]
+    fn returns_scripted_rows() {
+        let db = MockDatabase::new();
+        db.when(Pattern::prefix("SELECT")).respond(Response::rows(
+            &["id", "name"],
+            vec![
+                vec![1i64.into(), "asphalt".into()],
+                vec![2i64.into(), "mock".into()],
+            ],
+        ));
+        let conn = connection(&db);
+
+        let rows = select(&conn, "SELECT id, name FROM users").unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].get_column::<BigInt, i64>(0).unwrap(), 2);
+        assert_eq!(
+            rows[1].get_column_by_name::<Text, &str>("name").unwrap(),
+            "mock"
+        );
+
+        let err = rows[0].get_column::<Text, String>(0).unwrap_err();
+        assert!(err.is::<ColumnTypeMismatch>());
+
+        // Unmatched statements succeed without returning rows.
+        assert!(select(&conn, "DELETE FROM users").unwrap().is_empty());
+    }
+
+    #[test]
+    fn returns_scripted_errors() {
+        let db = MockDatabase::new();
+        db.when(Pattern::contains("users"))
+            .once()
+            .respond(Response::error(
+                DatabaseErrorKind::UniqueViolation,
+                "duplicate key",
+            ));
+        db.when(Pattern::contains("users"))
+            .respond(Response::affected(1));
+        let conn = connection(&db);
+
+        let err = block_on(insert_user(&conn, "asphalt", Some(42))).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::DatabaseError(DatabaseErrorKind::UniqueViolation, _)
+        ));
+        assert_eq!(
+            err.query(),
+            Some("INSERT INTO \"users\" (name, age) VALUES ($1, $2)")
+        );
+
+        assert_eq!(
+            block_on(insert_user(&conn, "asphalt", Some(42))).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn records_transaction_statements() {
+        let db = MockDatabase::new();
+        db.when(Pattern::contains("'fail'"))
+            .respond(Response::error(
+                DatabaseErrorKind::CheckViolation,
+                "invalid name",
+            ));
+        let conn = connection(&db);
+
+        let res: QueryResult<()> = block_on(conn.transaction(async {
+            insert_user(&conn, "asphalt", None).await?;
+
+            let nested: QueryResult<()> = conn
+                .transaction(async {
+                    let mut query = conn.query_builder();
+                    query.push_sql("UPDATE users SET name = 'fail'");
+                    conn.executes(query).await?;
+                    Ok(())
+                })
+                .await;
+            assert!(nested.is_err());
+
+            Ok(())
+        }));
+        res.unwrap();
+
+        db.assert_statements(&[
+            "BEGIN",
+            "INSERT INTO \"users\" (name, age) VALUES ($1, $2)",
+            "SAVEPOINT asphalt_savepoint_1",
+            "UPDATE users SET name = 'fail'",
+            "ROLLBACK TO SAVEPOINT asphalt_savepoint_1",
+            "COMMIT",
+        ]);
+        db.assert_sequence(&[
+            Pattern::from("BEGIN"),
+            Pattern::prefix("UPDATE"),
+            Pattern::from("COMMIT"),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected statements executed")]
+    fn panics_on_unexpected_statements() {
+        let db = MockDatabase::new();
+        let conn = connection(&db);
+
+        block_on(insert_user(&conn, "asphalt", None)).unwrap();
+
+        db.assert_statements(&["BEGIN"]);
+    }
+
+    #[test]
+    fn renders_debug_queries() {
+        let db = MockDatabase::new();
+        let conn = connection(&db);
+
+        let mut query = conn.query_builder();
+        block_on(async {
+            query.push_sql("UPDATE users SET password = ");
+            query
+                .push_sensitive_bind_param::<Text, _>(&"hunter2")
+                .await?;
+            query.push_sql(" WHERE name = ");
+            let name = query.push_bind_param::<Text, _>(&"o'brien").await?;
+            query.push_sql(" OR alias = ");
+            query.push_bind_name(&name);
+            query.push_sql(" OR age = ");
+            query
+                .push_bind_param::<Nullable<Integer>, _>(&None::<i32>)
+                .await?;
+            QueryResult::Ok(())
+        })
+        .unwrap();
+
+        let debug = query.debug_query();
+        assert_eq!(
+            debug.to_string(),
+            "UPDATE users SET password = $1 WHERE name = $2 OR alias = $2 OR age = $3 \
+             -- binds: [<redacted> (TEXT), 'o''brien' (TEXT), NULL (INTEGER)]"
+        );
+        assert_eq!(
+            debug.inline_binds().to_string(),
+            "UPDATE users SET password = NULL /* <redacted> */ \
+             WHERE name = 'o''brien' OR alias = 'o''brien' OR age = NULL"
+        );
+    }
+
+    #[test]
+    fn executes_prepared_statements_with_new_binds() {
+        let db = MockDatabase::new();
+        let conn = connection(&db);
+
+        block_on(async {
+            let mut query = conn.query_builder();
+            query.push_sql("DELETE FROM users WHERE name = ");
+            query.push_bind_param::<Text, _>(&"asphalt").await?;
+
+            let (stmt, binds) = conn.prepare(query).await?;
+            stmt.execute(binds).await?;
+
+            let mut binds = stmt.binds();
+            binds.bind::<Text, _>(&"mock").await?;
+            stmt.execute(binds).await?;
+
+            stmt.deallocate().await
+        })
+        .unwrap();
+
+        let expected = "DELETE FROM users WHERE name = $1";
+        assert_eq!(
+            db.recorded(),
+            vec![
+                RecordedStatement {
+                    sql: String::from(expected),
+                    binds: vec![Value::from("asphalt")],
+                },
+                RecordedStatement {
+                    sql: String::from(expected),
+                    binds: vec![Value::from("mock")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_deserialized_rows() {
+        struct User(i64, String);
+
+        impl FromRow<'_, MockRow> for User {
+            fn from_row(row: &MockRow) -> QueryResult<Self> {
+                Ok(User(
+                    get_field::<_, BigInt, _>(row, "0", 0)?,
+                    get_field::<_, Text, _>(row, "1", 1)?,
+                ))
+            }
+        }
+
+        let db = MockDatabase::new();
+        db.when("SELECT id, name FROM users")
+            .respond(Response::rows(
+                &["id", "name"],
+                vec![
+                    vec![1i64.into(), "asphalt".into()],
+                    vec![2i64.into(), "mock".into()],
+                ],
+            ));
+        db.when("SELECT id, name FROM users WHERE id = 1")
+            .respond(Response::rows(
+                &["id", "name"],
+                vec![vec![1i64.into(), "asphalt".into()]],
+            ));
+        db.when("SELECT count(*) FROM users")
+            .respond(Response::rows(&["count"], vec![vec![2i64.into()]]));
+        let conn = connection(&db);
+
+        let query = |sql: &str| {
+            let mut query = conn.query_builder();
+            query.push_sql(sql);
+            query
+        };
+
+        block_on(async {
+            let users: Vec<User> = conn.load(query("SELECT id, name FROM users")).await?;
+            assert_eq!(users.len(), 2);
+            assert_eq!((users[1].0, &*users[1].1), (2, "mock"));
+
+            let user: User = conn
+                .get_result(query("SELECT id, name FROM users WHERE id = 1"))
+                .await?;
+            assert_eq!((user.0, &*user.1), (1, "asphalt"));
+
+            let err = conn
+                .get_result::<User>(query("SELECT id, name FROM users"))
+                .await
+                .err()
+                .unwrap();
+            assert!(matches!(err.kind(), ErrorKind::TooManyRows));
+
+            let count: i64 = conn
+                .get_scalar::<BigInt, _>(query("SELECT count(*) FROM users"))
+                .await?;
+            assert_eq!(count, 2);
+
+            let missing = conn
+                .get_optional::<User>(query("SELECT id, name FROM admins"))
+                .await?;
+            assert!(missing.is_none());
+
+            let err = conn
+                .get_result::<User>(query("SELECT id, name FROM admins"))
+                .await
+                .err()
+                .unwrap();
+            assert!(matches!(err.kind(), ErrorKind::NotFound));
+
+            QueryResult::Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn refuses_connections() {
+        let db = MockDatabase::new();
+        db.refuse_connections(true);
+
+        let err = block_on(Connection::<Mock>::establish(db.clone()))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::DatabaseError(DatabaseErrorKind::ConnectionFailure, _)
+        ));
+        assert!(db.statements().is_empty());
+    }
+}
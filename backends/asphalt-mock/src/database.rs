@@ -0,0 +1,315 @@
+use crate::Value;
+use asphalt_core::error::{DatabaseErrorKind, Error, QueryResult};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// The script and the recorded statements shared by mock connections.
+///
+/// This is the configuration used to establish [`Mock`](crate::Mock) connections.
+/// Every connection established from the same database, or from any of its clones,
+/// shares the same script and records its statements in the same log, which makes it
+/// possible to inspect what a connection did after it is moved into the code under test.
+#[derive(Debug, Clone, Default)]
+pub struct MockDatabase {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    responses: Vec<ScriptedResponse>,
+    statements: Vec<RecordedStatement>,
+    refuse_connections: bool,
+    slow_connections: bool,
+}
+
+#[derive(Debug)]
+struct ScriptedResponse {
+    pattern: Pattern,
+    response: Response,
+    /// Number of times the response can still be used, `None` if unlimited.
+    remaining: Option<usize>,
+}
+
+/// A statement executed by a mock connection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedStatement {
+    /// The SQL of the statement, with placeholders in the `$N` format.
+    pub sql: String,
+    /// The values bound to the statement, in placeholder order.
+    pub binds: Vec<Value>,
+}
+
+/// Which statements a scripted response applies to.
+///
+/// A `&str` converts to an exact pattern.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Pattern {
+    /// Statements with exactly this SQL.
+    Exact(String),
+    /// Statements whose SQL starts with this string.
+    Prefix(String),
+    /// Statements whose SQL contains this string.
+    Contains(String),
+    /// Every statement.
+    Any,
+}
+
+impl Pattern {
+    pub fn exact(sql: impl Into<String>) -> Self {
+        Self::Exact(sql.into())
+    }
+
+    pub fn prefix(sql: impl Into<String>) -> Self {
+        Self::Prefix(sql.into())
+    }
+
+    pub fn contains(sql: impl Into<String>) -> Self {
+        Self::Contains(sql.into())
+    }
+
+    /// Does `sql` match this pattern?
+    pub fn matches(&self, sql: &str) -> bool {
+        match self {
+            Self::Exact(pattern) => sql == pattern,
+            Self::Prefix(pattern) => sql.starts_with(&**pattern),
+            Self::Contains(pattern) => sql.contains(&**pattern),
+            Self::Any => true,
+        }
+    }
+}
+
+impl From<&'_ str> for Pattern {
+    fn from(sql: &str) -> Self {
+        Self::exact(sql)
+    }
+}
+
+impl From<String> for Pattern {
+    fn from(sql: String) -> Self {
+        Self::Exact(sql)
+    }
+}
+
+/// The result of a statement matching a scripted pattern.
+#[derive(Debug, Clone)]
+pub enum Response {
+    /// Return these rows, for queries, or their count, for executed statements.
+    Rows {
+        columns: Vec<String>,
+        rows: Vec<Vec<Value>>,
+    },
+    /// Report this number of affected rows, returning no rows for queries.
+    Affected(u64),
+    /// Fail with a database error.
+    Error {
+        kind: DatabaseErrorKind,
+        message: String,
+    },
+}
+
+impl Response {
+    pub fn rows(columns: &[&str], rows: Vec<Vec<Value>>) -> Self {
+        Self::Rows {
+            columns: columns.iter().map(|col| String::from(*col)).collect(),
+            rows,
+        }
+    }
+
+    pub fn affected(count: u64) -> Self {
+        Self::Affected(count)
+    }
+
+    pub fn error(kind: DatabaseErrorKind, message: impl Into<String>) -> Self {
+        Self::Error {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Unmatched statements succeed without affecting any row.
+    fn empty() -> Self {
+        Self::Affected(0)
+    }
+
+    pub(crate) fn into_result(self, sql: &str) -> QueryResult<Self> {
+        match self {
+            Self::Error { kind, message } => {
+                Err(Error::database_error(kind, message).with_query(sql))
+            }
+            response => Ok(response),
+        }
+    }
+}
+
+/// Builder of a scripted response, see [`MockDatabase::when`].
+#[must_use = "The response is only scripted by calling `respond`"]
+pub struct When<'db> {
+    db: &'db MockDatabase,
+    pattern: Pattern,
+    times: Option<usize>,
+}
+
+impl When<'_> {
+    /// Use the response only for the first matching statement.
+    pub fn once(self) -> Self {
+        self.times(1)
+    }
+
+    /// Use the response only for the first `times` matching statements.
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    /// Script the response of the matching statements.
+    pub fn respond(self, response: Response) {
+        self.db.state.lock().responses.push(ScriptedResponse {
+            pattern: self.pattern,
+            response,
+            remaining: self.times,
+        });
+    }
+}
+
+impl MockDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Script the response of the statements matching `pattern`.
+    ///
+    /// When many scripted responses match a statement, the first one scripted is used.
+    /// Statements that match no response succeed without affecting nor returning rows.
+    pub fn when(&self, pattern: impl Into<Pattern>) -> When<'_> {
+        When {
+            db: self,
+            pattern: pattern.into(),
+            times: None,
+        }
+    }
+
+    /// Makes new connections fail to be established.
+    pub fn refuse_connections(&self, refuse: bool) {
+        self.state.lock().refuse_connections = refuse;
+    }
+
+    /// Makes new connections yield to the executor once while being established, which
+    /// lets concurrent tasks run in between.
+    pub fn slow_connections(&self, slow: bool) {
+        self.state.lock().slow_connections = slow;
+    }
+
+    /// Returns the statements executed until now, in execution order.
+    pub fn recorded(&self) -> Vec<RecordedStatement> {
+        self.state.lock().statements.clone()
+    }
+
+    /// Returns the SQL of the statements executed until now, in execution order.
+    pub fn statements(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .statements
+            .iter()
+            .map(|stmt| stmt.sql.clone())
+            .collect()
+    }
+
+    /// Forgets the recorded statements, keeping the script.
+    pub fn clear(&self) {
+        self.state.lock().statements.clear();
+    }
+
+    /// Asserts that exactly these statements were executed, in this order.
+    ///
+    /// Transaction control statements, like `BEGIN`, `SAVEPOINT` and `COMMIT`, are
+    /// recorded as any other statement.
+    ///
+    /// # Panics
+    ///
+    /// If the executed statements differ from `expected`.
+    #[track_caller]
+    pub fn assert_statements(&self, expected: &[&str]) {
+        let statements = self.statements();
+
+        if statements.len() != expected.len()
+            || statements.iter().zip(expected).any(|(s, e)| s != e)
+        {
+            panic!(
+                "Unexpected statements executed.\nexpected: {:#?}\n   found: {:#?}",
+                expected, statements
+            );
+        }
+    }
+
+    /// Asserts that statements matching the patterns were executed in this order,
+    /// possibly with other statements between them.
+    ///
+    /// # Panics
+    ///
+    /// If no subsequence of the executed statements matches `patterns`.
+    #[track_caller]
+    pub fn assert_sequence(&self, patterns: &[Pattern]) {
+        let statements = self.statements();
+        let mut remaining = statements.iter();
+
+        for pattern in patterns {
+            if !remaining.any(|sql| pattern.matches(sql)) {
+                panic!(
+                    "No statement matching {:?} executed in the expected order.\nexpected: {:#?}\n   found: {:#?}",
+                    pattern, patterns, statements
+                );
+            }
+        }
+    }
+
+    /// Asserts that a statement matching `pattern` was executed.
+    ///
+    /// # Panics
+    ///
+    /// If no executed statement matches `pattern`.
+    #[track_caller]
+    pub fn assert_executed(&self, pattern: impl Into<Pattern>) {
+        let pattern = pattern.into();
+        let statements = self.statements();
+
+        if !statements.iter().any(|sql| pattern.matches(sql)) {
+            panic!(
+                "No statement matching {:?} executed.\nfound: {:#?}",
+                pattern, statements
+            );
+        }
+    }
+
+    pub(crate) fn is_refusing_connections(&self) -> bool {
+        self.state.lock().refuse_connections
+    }
+
+    pub(crate) fn is_slowing_connections(&self) -> bool {
+        self.state.lock().slow_connections
+    }
+
+    /// Records the statement, returning its scripted response.
+    pub(crate) fn execute(&self, sql: &str, binds: Vec<Value>) -> Response {
+        let mut state = self.state.lock();
+        state.statements.push(RecordedStatement {
+            sql: sql.to_string(),
+            binds,
+        });
+
+        let scripted = state
+            .responses
+            .iter_mut()
+            .find(|scripted| scripted.remaining != Some(0) && scripted.pattern.matches(sql));
+
+        match scripted {
+            Some(scripted) => {
+                if let Some(remaining) = &mut scripted.remaining {
+                    *remaining -= 1;
+                }
+
+                scripted.response.clone()
+            }
+            None => Response::empty(),
+        }
+    }
+}
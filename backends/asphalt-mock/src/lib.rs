@@ -0,0 +1,78 @@
+#![feature(generic_associated_types)]
+//! A mock backend, for testing code that uses asphalt connections without a database.
+//!
+//! Connections are established from a [`MockDatabase`], which records every statement
+//! executed by them and answers queries with the responses scripted by the test:
+//!
+//! ```ignore
+//! let db = MockDatabase::new();
+//! db.when(Pattern::prefix("SELECT")).respond(Response::rows(&["id"], vec![vec![1.into()]]));
+//!
+//! let conn = Connection::<Mock>::establish(db.clone()).await?;
+//! // ...
+//!
+//! db.assert_statements(&["BEGIN", "SELECT id FROM users", "COMMIT"]);
+//! ```
+use asphalt_core::backend::{Backend, TypeMetadata};
+use asphalt_core::extensions::{self, Supports};
+use asphalt_core::values::RawValue;
+use std::borrow::Cow;
+
+mod connection;
+mod database;
+mod query;
+mod types;
+
+#[doc(inline)]
+pub use self::connection::{MockRawConnection, MockRow};
+#[doc(inline)]
+pub use self::database::{MockDatabase, Pattern, RecordedStatement, Response, When};
+#[doc(inline)]
+pub use self::query::{MockBindCollector, MockQuery, MockQueryWriter};
+#[doc(inline)]
+pub use self::types::Value;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Mock;
+
+impl Backend for Mock {
+    type Query = MockQuery;
+    type QueryWriter = MockQueryWriter;
+    type BindName = usize;
+    type BindCollector = MockBindCollector;
+    type RawConnection = MockRawConnection;
+    type RawValue<'a> = MockValue<'a>;
+}
+
+impl Supports<extensions::Transaction> for Mock {}
+impl Supports<extensions::IsolationLevel> for Mock {}
+impl Supports<extensions::ReadOnly> for Mock {}
+
+impl TypeMetadata for Mock {
+    /// The name of the SQL type.
+    type TypeMetadata = &'static str;
+    type MetadataLookup = ();
+}
+
+/// A raw mock value.
+///
+/// Values read from rows are borrowed, while values being bound are owned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockValue<'a>(pub(crate) Cow<'a, Value>);
+
+impl<'a> MockValue<'a> {
+    /// Returns the underlying value.
+    pub fn as_value(&self) -> &Value {
+        &self.0
+    }
+}
+
+impl RawValue<Mock> for MockValue<'_> {
+    fn is_null(&self) -> bool {
+        *self.0 == Value::Null
+    }
+
+    fn null_value() -> Self {
+        Self(Cow::Owned(Value::Null))
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{Mock, MockValue, Value};
+use asphalt_core::backend::{Backend, HasSqlType, TypeMetadata};
+use asphalt_core::error::{Error, QueryResult};
+use asphalt_core::query::{BindCollector, DebugBind, PreparableQuery, QueryWriter};
+use asphalt_core::types::ToSql;
+use asphalt_core::LocalBoxFuture;
+
+pub struct MockQuery {
+    pub(crate) sql: String,
+    prepared: bool,
+}
+
+impl PreparableQuery<Mock> for MockQuery {
+    // There is no server to prepare the statement, so preparing only marks the
+    // query as prepared, letting the connection statement cache work as usual.
+    type Prepared = String;
+    type CacheKey = String;
+
+    fn prepare(
+        self,
+        _conn: &<Mock as Backend>::RawConnection,
+    ) -> LocalBoxFuture<'_, QueryResult<Self::Prepared>> {
+        Box::pin(async move { Ok(self.sql) })
+    }
+
+    fn from_prepared(prepared: Self::Prepared) -> Self {
+        Self {
+            sql: prepared,
+            prepared: true,
+        }
+    }
+
+    fn cache_key(&self) -> Option<Self::CacheKey> {
+        if self.prepared {
+            None
+        } else {
+            Some(self.sql.clone())
+        }
+    }
+}
+
+/// The `QueryWriter` for the `Mock` backend.
+///
+/// Identifiers are quoted with double quotes, and bind parameters are written as `$N`.
+#[derive(Default)]
+pub struct MockQueryWriter {
+    query: String,
+}
+
+impl QueryWriter<Mock> for MockQueryWriter {
+    fn push_sql(&mut self, sql: &str) {
+        self.query.push_str(sql);
+    }
+
+    fn push_identifier(&mut self, identifier: &str) {
+        self.query.reserve(2 + identifier.len());
+        self.query.push('"');
+        self.query.push_str(&identifier.replace('"', "\"\""));
+        self.query.push('"');
+    }
+
+    fn push_bind_param(&mut self, bind: &<Mock as Backend>::BindName) {
+        use std::fmt::Write;
+        // Writing to memory never fails.
+        write!(&mut self.query, "${}", bind).unwrap();
+    }
+
+    fn sql(&self) -> &str {
+        &self.query
+    }
+
+    fn finish(self) -> <Mock as Backend>::Query {
+        MockQuery {
+            sql: self.query,
+            prepared: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MockBindCollector {
+    binds: Vec<Value>,
+    /// The type of each parameter, used only for debugging.
+    types: Vec<&'static str>,
+}
+
+impl MockBindCollector {
+    /// Returns the values bound until now.
+    pub fn binds(&self) -> &[Value] {
+        &self.binds
+    }
+
+    pub(crate) fn into_binds(self) -> Vec<Value> {
+        self.binds
+    }
+}
+
+impl BindCollector<Mock> for MockBindCollector {
+    fn push_bound_value<'a, SqlTy, RustTy>(
+        &'a mut self,
+        bind: &'a RustTy,
+        metadata_lookup: &'a <Mock as TypeMetadata>::MetadataLookup,
+    ) -> LocalBoxFuture<'a, QueryResult<<Mock as Backend>::BindName>>
+    where
+        Mock: HasSqlType<SqlTy>,
+        RustTy: ToSql<SqlTy, Mock> + ?Sized,
+    {
+        Box::pin(async move {
+            let metadata = <Mock as HasSqlType<SqlTy>>::metadata(metadata_lookup).await?;
+            let MockValue(value) = bind
+                .to_sql(&metadata, self)
+                .map_err(Error::serialization_failure)?;
+            let value = value.into_owned();
+
+            self.binds.push(value);
+            self.types.push(metadata);
+
+            Ok(self.binds.len())
+        })
+    }
+
+    fn bind_count(&self) -> usize {
+        self.binds.len()
+    }
+
+    fn debug_binds(&self) -> Vec<DebugBind> {
+        self.binds
+            .iter()
+            .zip(&self.types)
+            .map(|(value, ty)| DebugBind {
+                sql_type: ty.to_string(),
+                literal: value.to_literal(),
+            })
+            .collect()
+    }
+}
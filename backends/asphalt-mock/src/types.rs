@@ -0,0 +1,193 @@
+use crate::{Mock, MockValue};
+use asphalt_core::backend::{Backend, HasSqlType};
+use asphalt_core::error::{AnyResult, QueryResult};
+use asphalt_core::types::*;
+use asphalt_core::LocalBoxFuture;
+use std::borrow::Cow;
+use std::convert::TryInto;
+
+/// A value bound to a mock statement, or returned in a mock row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Value {
+    /// Returns the name of the SQL type of the value.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Null => "NULL",
+            Self::Bool(_) => "BOOL",
+            Self::Int(_) => "INTEGER",
+            Self::Float(_) => "DOUBLE",
+            Self::Text(_) => "TEXT",
+            Self::Bytes(_) => "BINARY",
+        }
+    }
+
+    /// Renders the value as a SQL literal.
+    pub fn to_literal(&self) -> String {
+        match self {
+            Self::Null => String::from("NULL"),
+            Self::Bool(value) => String::from(if *value { "TRUE" } else { "FALSE" }),
+            Self::Int(value) => value.to_string(),
+            Self::Float(value) => value.to_string(),
+            Self::Text(value) => format!("'{}'", value.replace('\'', "''")),
+            Self::Bytes(value) => {
+                let hex: String = value.iter().map(|byte| format!("{:02X}", byte)).collect();
+                format!("X'{}'", hex)
+            }
+        }
+    }
+}
+
+macro_rules! value_from {
+    ($($rust_ty: ty => |$value: ident| $to_value: expr);+) => {$(
+        impl From<$rust_ty> for Value {
+            fn from($value: $rust_ty) -> Self {
+                $to_value
+            }
+        }
+    )+};
+}
+
+value_from! {
+    bool => |value| Value::Bool(value);
+    i8 => |value| Value::Int(i64::from(value));
+    i16 => |value| Value::Int(i64::from(value));
+    i32 => |value| Value::Int(i64::from(value));
+    i64 => |value| Value::Int(value);
+    f32 => |value| Value::Float(f64::from(value));
+    f64 => |value| Value::Float(value);
+    String => |value| Value::Text(value);
+    &'_ str => |value| Value::Text(value.to_string());
+    Vec<u8> => |value| Value::Bytes(value);
+    &'_ [u8] => |value| Value::Bytes(value.to_vec())
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+macro_rules! mock_types {
+    ($($asp_ty: ty => $name: literal),+) => {$(
+        impl HasSqlType<$asp_ty> for Mock {
+            fn metadata(
+                _: &Self::MetadataLookup,
+            ) -> LocalBoxFuture<'_, QueryResult<Self::TypeMetadata>> {
+                Box::pin(async move { Ok($name) })
+            }
+        }
+    )+};
+}
+
+mock_types! {
+    Bool => "BOOL",
+    TinyInt => "INTEGER",
+    SmallInt => "INTEGER",
+    Integer => "INTEGER",
+    BigInt => "INTEGER",
+    Float => "DOUBLE",
+    Double => "DOUBLE",
+    Text => "TEXT",
+    Binary => "BINARY"
+}
+
+macro_rules! mock_to_sql {
+    ($($rust_ty: ty => $asp_ty: ty),+) => {$(
+        impl ToSql<$asp_ty, Mock> for $rust_ty {
+            fn to_sql<'a>(
+                &'a self,
+                _metadata: &&'static str,
+                _collector: &'a mut <Mock as Backend>::BindCollector,
+            ) -> AnyResult<<Mock as Backend>::RawValue<'a>> {
+                Ok(MockValue(Cow::Owned(Value::from(self.clone()))))
+            }
+        }
+    )+};
+}
+
+mock_to_sql! {
+    bool => Bool,
+    i8 => TinyInt,
+    i16 => SmallInt,
+    i32 => Integer,
+    i64 => BigInt,
+    f32 => Float,
+    f64 => Double,
+    String => Text,
+    &'_ str => Text,
+    Vec<u8> => Binary,
+    &'_ [u8] => Binary
+}
+
+macro_rules! mock_from_sql {
+    ($($rust_ty: ty => $asp_ty: ty => $variant: ident),+) => {$(
+        impl<'a> FromSql<'a, $asp_ty, Mock> for $rust_ty {
+            fn from_sql(_metadata: &&'static str, raw: MockValue<'a>) -> AnyResult<Self> {
+                match &*raw.0 {
+                    Value::$variant(value) => Ok(value.clone().try_into()?),
+                    value => Err(unexpected_value(stringify!($variant), value)),
+                }
+            }
+        }
+    )+};
+}
+
+mock_from_sql! {
+    bool => Bool => Bool,
+    i8 => TinyInt => Int,
+    i16 => SmallInt => Int,
+    i32 => Integer => Int,
+    i64 => BigInt => Int,
+    f64 => Double => Float,
+    String => Text => Text,
+    Vec<u8> => Binary => Bytes
+}
+
+impl<'a> FromSql<'a, Float, Mock> for f32 {
+    fn from_sql(_metadata: &&'static str, raw: MockValue<'a>) -> AnyResult<Self> {
+        match &*raw.0 {
+            Value::Float(value) => Ok(*value as f32),
+            value => Err(unexpected_value("Float", value)),
+        }
+    }
+}
+
+impl<'a> FromSql<'a, Text, Mock> for &'a str {
+    fn from_sql(_metadata: &&'static str, raw: MockValue<'a>) -> AnyResult<Self> {
+        match raw.0 {
+            Cow::Borrowed(Value::Text(value)) => Ok(value),
+            Cow::Borrowed(value) => Err(unexpected_value("Text", value)),
+            Cow::Owned(_) => Err("Can't borrow a text from an owned value".into()),
+        }
+    }
+}
+
+impl<'a> FromSql<'a, Binary, Mock> for &'a [u8] {
+    fn from_sql(_metadata: &&'static str, raw: MockValue<'a>) -> AnyResult<Self> {
+        match raw.0 {
+            Cow::Borrowed(Value::Bytes(value)) => Ok(value),
+            Cow::Borrowed(value) => Err(unexpected_value("Bytes", value)),
+            Cow::Owned(_) => Err("Can't borrow bytes from an owned value".into()),
+        }
+    }
+}
+
+fn unexpected_value(expected: &str, found: &Value) -> asphalt_core::error::AnyError {
+    format!("Expected a {} value, found {:?}", expected, found).into()
+}
+
+/// Can values of the `actual` type be read as values of the `expected` type?
+///
+/// `NULL`s are handled by the nullable types.
+pub(crate) fn is_compatible(expected: &str, actual: &str) -> bool {
+    expected == actual || actual == "NULL"
+}
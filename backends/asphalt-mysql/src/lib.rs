@@ -0,0 +1,154 @@
+#![feature(generic_associated_types)]
+use asphalt_core::backend::{Backend, TypeMetadata};
+use asphalt_core::error::{DatabaseErrorInformation, DatabaseErrorKind, Error, ErrorPosition};
+use asphalt_core::extensions::{self, Supports};
+use asphalt_core::values::RawValue;
+use mysql_async::consts::ColumnType;
+use mysql_async::{ServerError, Value};
+use std::borrow::Cow;
+
+mod connection;
+mod query;
+mod transaction;
+mod types;
+
+#[doc(inline)]
+pub use self::connection::{Config, MySqlRawConnection, MySqlRow};
+#[doc(inline)]
+pub use self::query::{MySqlBindCollector, MySqlQuery, MySqlQueryWriter, MySqlStatement};
+#[doc(inline)]
+pub use self::transaction::MySqlTransactionManager;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct MySql;
+
+impl Backend for MySql {
+    type Query = MySqlQuery;
+    type QueryWriter = MySqlQueryWriter;
+    type BindName = usize;
+    type BindCollector = MySqlBindCollector;
+    type RawConnection = MySqlRawConnection;
+    type RawValue<'a> = MySqlValue<'a>;
+}
+
+impl Supports<extensions::Transaction> for MySql {}
+impl Supports<extensions::IsolationLevel> for MySql {}
+impl Supports<extensions::ReadOnly> for MySql {}
+
+impl TypeMetadata for MySql {
+    type TypeMetadata = ColumnType;
+    type MetadataLookup = ();
+}
+
+/// A raw MySQL value.
+///
+/// Values read from rows are borrowed, while values being bound are owned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MySqlValue<'a>(pub(crate) Cow<'a, Value>);
+
+impl<'a> MySqlValue<'a> {
+    /// Returns the underlying `mysql_async` value.
+    pub fn as_value(&self) -> &Value {
+        &self.0
+    }
+}
+
+impl RawValue<MySql> for MySqlValue<'_> {
+    fn is_null(&self) -> bool {
+        *self.0 == Value::NULL
+    }
+
+    fn null_value() -> Self {
+        Self(Cow::Owned(Value::NULL))
+    }
+}
+
+pub(crate) fn server_error_to_query_error(err: ServerError) -> Error {
+    // See https://dev.mysql.com/doc/mysql-errors/8.0/en/server-error-reference.html
+    let kind = match err.code {
+        // ER_DUP_ENTRY, ER_DUP_ENTRY_WITH_KEY_NAME
+        1062 | 1586 => DatabaseErrorKind::UniqueViolation,
+        // ER_NO_REFERENCED_ROW, ER_ROW_IS_REFERENCED, and their `_2` versions.
+        1216 | 1217 | 1451 | 1452 => DatabaseErrorKind::ForeignKeyViolation,
+        // ER_BAD_NULL_ERROR, ER_NO_DEFAULT_FOR_FIELD
+        1048 | 1364 => DatabaseErrorKind::NotNullViolation,
+        // ER_CHECK_CONSTRAINT_VIOLATED, and ER_CONSTRAINT_FAILED in MariaDB.
+        3819 | 4025 => DatabaseErrorKind::CheckViolation,
+        // ER_LOCK_DEADLOCK
+        1213 => DatabaseErrorKind::DeadlockDetected,
+        // ER_CANT_EXECUTE_IN_READ_ONLY_TRANSACTION
+        1792 => DatabaseErrorKind::ReadOnlyTransaction,
+        // ER_QUERY_INTERRUPTED, ER_QUERY_TIMEOUT
+        1317 | 3024 => DatabaseErrorKind::QueryCanceled,
+        // ER_NEED_REPREPARE
+        1615 => DatabaseErrorKind::StalePreparedStatement,
+        // ER_WARN_DATA_OUT_OF_RANGE, WARN_DATA_TRUNCATED, ER_TRUNCATED_WRONG_VALUE,
+        // ER_TRUNCATED_WRONG_VALUE_FOR_FIELD, ER_DATA_TOO_LONG
+        1264 | 1265 | 1292 | 1366 | 1406 => DatabaseErrorKind::InvalidInput,
+        _ => DatabaseErrorKind::Unknown,
+    };
+
+    Error::database_error(kind, MySqlErrorInfo(err))
+}
+
+pub(crate) fn error_to_query_error(err: mysql_async::Error) -> Error {
+    match err {
+        mysql_async::Error::Server(err) => server_error_to_query_error(err),
+        mysql_async::Error::Io(err) => {
+            Error::database_error(DatabaseErrorKind::ConnectionFailure, err.to_string())
+        }
+        err => Error::database_error(DatabaseErrorKind::Unknown, err.to_string()),
+    }
+}
+
+pub struct MySqlErrorInfo(ServerError);
+
+impl DatabaseErrorInformation for MySqlErrorInfo {
+    fn message(&self) -> &str {
+        &self.0.message
+    }
+
+    fn sql_state(&self) -> Option<&str> {
+        Some(&self.0.state)
+    }
+
+    fn severity(&self) -> Option<&str> {
+        None
+    }
+
+    fn details(&self) -> Option<&str> {
+        None
+    }
+
+    fn hint(&self) -> Option<&str> {
+        None
+    }
+
+    fn position(&self) -> Option<ErrorPosition<'_>> {
+        None
+    }
+
+    fn where_(&self) -> Option<&str> {
+        None
+    }
+
+    fn schema(&self) -> Option<&str> {
+        None
+    }
+
+    fn table(&self) -> Option<&str> {
+        None
+    }
+
+    fn column(&self) -> Option<&str> {
+        None
+    }
+
+    fn datatype(&self) -> Option<&str> {
+        None
+    }
+
+    fn constraint(&self) -> Option<&str> {
+        None
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{MySql, MySqlValue};
+use asphalt_core::backend::{Backend, HasSqlType, TypeMetadata};
+use asphalt_core::connection::ColumnDescriptor;
+use asphalt_core::error::{Error, QueryResult};
+use asphalt_core::query::{
+    BindCollector, DebugBind, DescribePrepared, PreparableQuery, QueryWriter,
+};
+use asphalt_core::types::ToSql;
+use asphalt_core::LocalBoxFuture;
+use mysql_async::consts::ColumnType;
+use mysql_async::prelude::Queryable;
+use mysql_async::{Params, Statement, Value};
+use std::sync::Arc;
+
+pub struct MySqlQuery {
+    inner: InnerQuery,
+    /// The bind parameter referenced by each `?` placeholder, in order.
+    params: Arc<[usize]>,
+}
+
+enum InnerQuery {
+    Raw(String),
+    Stmt(MySqlStatement),
+}
+
+/// A prepared MySQL statement.
+///
+/// The SQL of the statement is kept to report it along with errors.
+#[derive(Clone)]
+pub struct MySqlStatement {
+    pub(crate) stmt: Statement,
+    pub(crate) params: Arc<[usize]>,
+    pub(crate) sql: Arc<str>,
+}
+
+impl PreparableQuery<MySql> for MySqlQuery {
+    type Prepared = MySqlStatement;
+    type CacheKey = (String, Arc<[usize]>);
+
+    fn prepare(
+        self,
+        conn: &<MySql as Backend>::RawConnection,
+    ) -> LocalBoxFuture<'_, QueryResult<Self::Prepared>> {
+        Box::pin(async move {
+            match self.inner {
+                InnerQuery::Stmt(stmt) => Ok(stmt),
+                InnerQuery::Raw(raw) => match conn.inner.lock().await.prep(&raw).await {
+                    Ok(stmt) => Ok(MySqlStatement {
+                        stmt,
+                        params: self.params,
+                        sql: raw.into(),
+                    }),
+                    Err(err) => Err(crate::error_to_query_error(err).with_query(raw)),
+                },
+            }
+        })
+    }
+
+    fn from_prepared(prepared: Self::Prepared) -> Self {
+        Self {
+            params: prepared.params.clone(),
+            inner: InnerQuery::Stmt(prepared),
+        }
+    }
+
+    fn deallocate(
+        prepared: Self::Prepared,
+        conn: &<MySql as Backend>::RawConnection,
+    ) -> LocalBoxFuture<'_, QueryResult<()>> {
+        Box::pin(async move {
+            let MySqlStatement { stmt, sql, .. } = prepared;
+            conn.inner
+                .lock()
+                .await
+                .close(stmt)
+                .await
+                .map_err(|err| crate::error_to_query_error(err).with_query(&*sql))
+        })
+    }
+
+    fn cache_key(&self) -> Option<Self::CacheKey> {
+        match &self.inner {
+            // The same SQL can reference the parameters in different orders.
+            InnerQuery::Raw(raw) => Some((raw.clone(), self.params.clone())),
+            InnerQuery::Stmt(_) => None,
+        }
+    }
+}
+
+impl DescribePrepared<MySql> for MySqlStatement {
+    fn param_types(&self) -> Vec<ColumnType> {
+        // The server describes each `?` placeholder, which may reference any parameter.
+        let n_binds = self.params.iter().map(|idx| idx + 1).max().unwrap_or(0);
+
+        (0..n_binds)
+            .map(|bind| {
+                self.params
+                    .iter()
+                    .position(|idx| *idx == bind)
+                    .and_then(|pos| self.stmt.params().get(pos))
+                    .map_or(ColumnType::MYSQL_TYPE_NULL, |param| param.column_type())
+            })
+            .collect()
+    }
+
+    fn columns(&self) -> Vec<ColumnDescriptor<'_, MySql>> {
+        self.stmt
+            .columns()
+            .iter()
+            .filter_map(|col| {
+                let name = std::str::from_utf8(col.name_ref()).ok()?;
+                Some(ColumnDescriptor::new(name, col.column_type()))
+            })
+            .collect()
+    }
+}
+
+/// The `QueryWriter` for the `MySql` backend.
+#[derive(Default)]
+pub struct MySqlQueryWriter {
+    query: String,
+    params: Vec<usize>,
+}
+
+impl QueryWriter<MySql> for MySqlQueryWriter {
+    fn push_sql(&mut self, sql: &str) {
+        self.query.push_str(sql);
+    }
+
+    fn push_identifier(&mut self, identifier: &str) {
+        self.query.reserve(2 + identifier.len());
+        self.query.push('`');
+        self.query.push_str(&identifier.replace('`', "``"));
+        self.query.push('`');
+    }
+
+    fn push_bind_param(&mut self, bind: &<MySql as Backend>::BindName) {
+        // MySQL placeholders are positional, so parameters referenced more than once
+        // are sent once for each reference.
+        self.query.push('?');
+        self.params.push(*bind);
+    }
+
+    fn sql(&self) -> &str {
+        &self.query
+    }
+
+    fn finish(self) -> <MySql as Backend>::Query {
+        MySqlQuery {
+            inner: InnerQuery::Raw(self.query),
+            params: self.params.into(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MySqlBindCollector {
+    binds: Vec<Value>,
+    /// The type of each parameter, used only for debugging.
+    types: Vec<ColumnType>,
+}
+
+impl MySqlBindCollector {
+    /// Returns the parameters referenced by the placeholders of a statement.
+    pub(crate) fn params(&self, order: &[usize]) -> Params {
+        if order.is_empty() {
+            Params::Empty
+        } else {
+            Params::Positional(order.iter().map(|&idx| self.binds[idx].clone()).collect())
+        }
+    }
+}
+
+impl BindCollector<MySql> for MySqlBindCollector {
+    fn push_bound_value<'a, SqlTy, RustTy>(
+        &'a mut self,
+        bind: &'a RustTy,
+        metadata_lookup: &'a <MySql as TypeMetadata>::MetadataLookup,
+    ) -> LocalBoxFuture<'a, QueryResult<<MySql as Backend>::BindName>>
+    where
+        MySql: HasSqlType<SqlTy>,
+        RustTy: ToSql<SqlTy, MySql> + ?Sized,
+    {
+        Box::pin(async move {
+            let metadata = <MySql as HasSqlType<SqlTy>>::metadata(metadata_lookup).await?;
+            let MySqlValue(value) = bind
+                .to_sql(&metadata, self)
+                .map_err(Error::serialization_failure)?;
+            let value = value.into_owned();
+
+            self.binds.push(value);
+            self.types.push(metadata);
+
+            Ok(self.binds.len() - 1)
+        })
+    }
+
+    fn bind_count(&self) -> usize {
+        self.binds.len()
+    }
+
+    fn debug_binds(&self) -> Vec<DebugBind> {
+        self.binds
+            .iter()
+            .zip(&self.types)
+            .map(|(value, ty)| DebugBind {
+                sql_type: format!("{:?}", ty),
+                literal: value.as_sql(false),
+            })
+            .collect()
+    }
+}
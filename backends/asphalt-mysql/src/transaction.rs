@@ -0,0 +1,82 @@
+use asphalt_core::connection::{
+    IsolationLevel, RawConnection, TransactionConfig, TransactionManager,
+};
+use asphalt_core::error::QueryResult;
+use asphalt_core::sql::TransactionState;
+use asphalt_core::LocalBoxFuture;
+
+/// The [`TransactionManager`] of MySQL connections.
+///
+/// MySQL doesn't accept the isolation level in the `START TRANSACTION` statement, it
+/// must be set with `SET TRANSACTION` before the transaction is started. Nested
+/// transactions use savepoints, as in the ANSI transaction manager.
+#[derive(Debug, Default)]
+pub struct MySqlTransactionManager {
+    state: TransactionState,
+}
+
+impl MySqlTransactionManager {
+    /// Returns the number of open transactions, including savepoints.
+    ///
+    /// Zero means that the connection isn't inside a transaction.
+    pub fn transaction_depth(&self) -> u8 {
+        self.state.transaction_depth()
+    }
+
+    async fn first_transaction<Conn>(
+        &self,
+        config: TransactionConfig,
+        conn: &Conn,
+    ) -> QueryResult<()>
+    where
+        Conn: RawConnection,
+    {
+        if let Some(lvl) = config.isolation {
+            let stmt = match lvl {
+                IsolationLevel::ReadCommitted => "SET TRANSACTION ISOLATION LEVEL READ COMMITTED",
+                IsolationLevel::RepeatableRead => "SET TRANSACTION ISOLATION LEVEL REPEATABLE READ",
+                IsolationLevel::Serializable => "SET TRANSACTION ISOLATION LEVEL SERIALIZABLE",
+            };
+
+            conn.simple_execute(stmt).await?;
+        }
+
+        if config.read_only == Some(true) {
+            conn.simple_execute("START TRANSACTION READ ONLY").await
+        } else {
+            conn.simple_execute("START TRANSACTION").await
+        }
+    }
+}
+
+impl<Conn> TransactionManager<Conn> for MySqlTransactionManager
+where
+    Conn: RawConnection,
+{
+    fn begin_transaction<'c>(
+        &'c self,
+        config: TransactionConfig,
+        conn: &'c Conn,
+    ) -> LocalBoxFuture<'c, QueryResult<()>> {
+        Box::pin(
+            self.state
+                .begin(conn, move || self.first_transaction(config, conn)),
+        )
+    }
+
+    fn commit_transaction<'c>(&'c self, conn: &'c Conn) -> LocalBoxFuture<'c, QueryResult<()>> {
+        Box::pin(self.state.commit(conn))
+    }
+
+    fn rollback_transaction<'c>(&'c self, conn: &'c Conn) -> LocalBoxFuture<'c, QueryResult<()>> {
+        Box::pin(self.state.rollback(conn))
+    }
+
+    fn is_broken(&self) -> bool {
+        self.state.is_broken()
+    }
+
+    fn mark_broken(&self) {
+        self.state.mark_broken();
+    }
+}
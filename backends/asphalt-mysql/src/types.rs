@@ -0,0 +1,127 @@
+use crate::{MySql, MySqlValue};
+use asphalt_core::backend::{Backend, HasSqlType};
+use asphalt_core::error::{AnyResult, QueryResult};
+use asphalt_core::types::*;
+use asphalt_core::LocalBoxFuture;
+use mysql_async::consts::ColumnType;
+use mysql_async::Value;
+use std::borrow::Cow;
+
+macro_rules! delegate_to_mysqlvalue {
+    ($($($rust_ty: ty),+ => $asp_ty: ty => $mysql_ty: ident);+) => {$(
+        impl HasSqlType<$asp_ty> for MySql {
+            fn metadata(
+                _: &Self::MetadataLookup,
+            ) -> LocalBoxFuture<'_, QueryResult<Self::TypeMetadata>> {
+                Box::pin(async move { Ok(ColumnType::$mysql_ty) })
+            }
+        }
+
+        $(impl ToSql<$asp_ty, MySql> for $rust_ty {
+            fn to_sql<'a>(
+                &'a self,
+                _metadata: &ColumnType,
+                _collector: &'a mut <MySql as Backend>::BindCollector,
+            ) -> AnyResult<<MySql as Backend>::RawValue<'a>> {
+                Ok(MySqlValue(Cow::Owned(Value::from(self.clone()))))
+            }
+        })+
+    )+};
+}
+
+delegate_to_mysqlvalue! {
+    bool => Bool => MYSQL_TYPE_TINY;
+    i8 => TinyInt => MYSQL_TYPE_TINY;
+    i16 => SmallInt => MYSQL_TYPE_SHORT;
+    i32 => Integer => MYSQL_TYPE_LONG;
+    i64 => BigInt => MYSQL_TYPE_LONGLONG;
+    f32 => Float => MYSQL_TYPE_FLOAT;
+    f64 => Double => MYSQL_TYPE_DOUBLE;
+    String, &'_ str => Text => MYSQL_TYPE_VAR_STRING;
+    Vec<u8>, &'_ [u8] => Binary => MYSQL_TYPE_BLOB
+}
+
+macro_rules! delegate_to_mysqlfromvalue {
+    ($($($rust_ty: ty),+ => $asp_ty: ty);+) => {$(
+        $(impl<'a> FromSql<'a, $asp_ty, MySql> for $rust_ty {
+            fn from_sql(_metadata: &ColumnType, raw: MySqlValue<'a>) -> AnyResult<Self> {
+                Ok(mysql_async::from_value_opt(raw.0.into_owned())?)
+            }
+        })+
+    )+};
+}
+
+delegate_to_mysqlfromvalue! {
+    bool => Bool;
+    i8 => TinyInt;
+    i16 => SmallInt;
+    i32 => Integer;
+    i64 => BigInt;
+    f32 => Float;
+    f64 => Double;
+    String => Text;
+    Vec<u8> => Binary
+}
+
+/// Returns the bytes of a value borrowed from a row.
+fn borrowed_bytes<'a>(raw: &MySqlValue<'a>) -> AnyResult<&'a [u8]> {
+    match raw.0 {
+        Cow::Borrowed(Value::Bytes(bytes)) => Ok(bytes),
+        Cow::Borrowed(_) => Err("Value is not a string".into()),
+        Cow::Owned(_) => Err("Only values borrowed from a row can be borrowed".into()),
+    }
+}
+
+impl<'a> FromSql<'a, Text, MySql> for &'a str {
+    fn from_sql(_metadata: &ColumnType, raw: MySqlValue<'a>) -> AnyResult<Self> {
+        Ok(std::str::from_utf8(borrowed_bytes(&raw)?)?)
+    }
+}
+
+impl<'a> FromSql<'a, Binary, MySql> for &'a [u8] {
+    fn from_sql(_metadata: &ColumnType, raw: MySqlValue<'a>) -> AnyResult<Self> {
+        borrowed_bytes(&raw)
+    }
+}
+
+/// Groups of column types whose values share the same representation.
+#[derive(Eq, PartialEq)]
+enum TypeFamily {
+    Integer,
+    Float,
+    Bytes,
+}
+
+fn family(ty: ColumnType) -> Option<TypeFamily> {
+    use ColumnType::*;
+
+    match ty {
+        MYSQL_TYPE_TINY | MYSQL_TYPE_SHORT | MYSQL_TYPE_INT24 | MYSQL_TYPE_LONG
+        | MYSQL_TYPE_LONGLONG | MYSQL_TYPE_YEAR => Some(TypeFamily::Integer),
+        MYSQL_TYPE_FLOAT | MYSQL_TYPE_DOUBLE => Some(TypeFamily::Float),
+        // `TEXT` columns are reported as `BLOB`s with a character set.
+        MYSQL_TYPE_VARCHAR
+        | MYSQL_TYPE_VAR_STRING
+        | MYSQL_TYPE_STRING
+        | MYSQL_TYPE_TINY_BLOB
+        | MYSQL_TYPE_MEDIUM_BLOB
+        | MYSQL_TYPE_LONG_BLOB
+        | MYSQL_TYPE_BLOB => Some(TypeFamily::Bytes),
+        _ => None,
+    }
+}
+
+/// Can values of the `actual` column type be read as values of the `expected` type?
+///
+/// Integers of different sizes are compatible, as the conversion checks the range of
+/// the value, and so are the string and blob types.
+pub(crate) fn is_compatible(expected: ColumnType, actual: ColumnType) -> bool {
+    if expected == actual || actual == ColumnType::MYSQL_TYPE_NULL {
+        return true;
+    }
+
+    match (family(expected), family(actual)) {
+        (Some(expected), Some(actual)) => expected == actual,
+        _ => false,
+    }
+}
@@ -1,12 +1,18 @@
 use crate::metadata::MetadataLookup;
-use crate::Pg;
+use crate::notify::Listeners;
+use crate::{Pg, PgValue};
 use asphalt_core::backend::{HasSqlType, TypeMetadata};
-use asphalt_core::connection::{EstablishResult, RawConnection, Row, RowStream};
+use asphalt_core::connection::{
+    ColumnDescriptor, ColumnTypeMismatch, EstablishResult, RawConnection, Row, RowStream,
+};
 use asphalt_core::error::{AnyResult, QueryResult};
 use asphalt_core::query::{PreparableQuery, Query};
 use asphalt_core::sql::AnsiTransactionManager;
 use asphalt_core::types::FromSql;
 use asphalt_core::LocalBoxFuture;
+use futures_util::FutureExt;
+use parking_lot::Mutex;
+use std::sync::Arc;
 use tokio::stream::StreamExt;
 use tokio_postgres::{types::Type, Client, Config as PgConfig, NoTls};
 #[cfg(feature = "tls")]
@@ -15,6 +21,7 @@ use tokio_postgres_rustls::MakeRustlsConnect;
 #[doc(inline)]
 pub type ConnectionConfig = PgConfig;
 
+#[derive(Clone)]
 pub struct Config {
     connection: ConnectionConfig,
     #[cfg(feature = "tls")]
@@ -40,7 +47,11 @@ impl Config {
 pub struct PgRawConnection {
     pub(crate) inner: Client,
     manager: AnsiTransactionManager,
-    metadata: MetadataLookup,
+    pub(crate) metadata: Arc<MetadataLookup>,
+    /// Cursors dropped before being exhausted, which must be closed.
+    dropped_cursors: Mutex<Vec<String>>,
+    /// Subscribers of the notifications received by the connection.
+    pub(crate) listeners: Arc<Listeners>,
 }
 
 impl PgRawConnection {
@@ -49,34 +60,54 @@ impl PgRawConnection {
         let tls = MakeRustlsConnect::new(config.tls);
 
         let (client, connection) = config.connection.connect(tls).await?;
-        tokio::spawn(async move {
-            if let Err(err) = connection.await {
-                eprintln!("connection error: {}", err)
-            }
-        });
+        let listeners = Arc::new(Listeners::default());
+        crate::notify::spawn_connection(connection, listeners.clone());
 
         Ok(Self {
             inner: client,
             manager: AnsiTransactionManager::default(),
-            metadata: MetadataLookup::default(),
+            metadata: Arc::default(),
+            dropped_cursors: Mutex::default(),
+            listeners,
         })
     }
 
     #[cfg(not(feature = "tls"))]
     async fn connect(config: Config) -> EstablishResult<Self> {
         let (client, connection) = config.connection.connect(NoTls).await?;
-        tokio::spawn(async move {
-            if let Err(err) = connection.await {
-                eprintln!("connection error: {}", err)
-            }
-        });
+        let listeners = Arc::new(Listeners::default());
+        crate::notify::spawn_connection(connection, listeners.clone());
 
         Ok(Self {
             inner: client,
             manager: AnsiTransactionManager::default(),
-            metadata: MetadataLookup::default(),
+            metadata: Arc::default(),
+            dropped_cursors: Mutex::default(),
+            listeners,
         })
     }
+
+    /// Close the cursor named `name` before the next statement is executed.
+    pub(crate) fn close_cursor_later(&self, name: String) {
+        self.dropped_cursors.lock().push(name);
+    }
+
+    /// Close the cursors dropped before being exhausted.
+    ///
+    /// Errors are ignored, as they only happen when the transaction is already
+    /// aborted, in which case the cursors are closed with it.
+    pub(crate) async fn close_dropped_cursors(&self) {
+        let cursors = std::mem::take(&mut *self.dropped_cursors.lock());
+        if cursors.is_empty() {
+            return;
+        }
+
+        let sql: String = cursors
+            .iter()
+            .map(|name| format!("CLOSE {};", name))
+            .collect();
+        let _ = self.inner.batch_execute(&sql).await;
+    }
 }
 
 impl RawConnection for PgRawConnection {
@@ -96,22 +127,25 @@ impl RawConnection for PgRawConnection {
 
     fn simple_execute<'s>(&'s self, sql: &'s str) -> LocalBoxFuture<'s, QueryResult<()>> {
         Box::pin(async move {
+            self.close_dropped_cursors().await;
+
             Ok(self
                 .inner
                 .batch_execute(sql)
                 .await
-                .map_err(crate::error_to_query_error)?)
+                .map_err(|err| crate::error_to_query_error(err).with_query(sql))?)
         })
     }
 
     fn execute(&self, query: Query<Self::Backend>) -> LocalBoxFuture<'_, QueryResult<u64>> {
         Box::pin(async move {
+            self.close_dropped_cursors().await;
             let stmt = query.inner.prepare(self).await?;
 
             self.inner
-                .execute_raw(&stmt, query.binds.binds())
+                .execute_raw(&stmt.stmt, query.binds.binds())
                 .await
-                .map_err(crate::error_to_query_error)
+                .map_err(|err| crate::error_to_query_error(err).with_query(&*stmt.sql))
         })
     }
 
@@ -120,15 +154,18 @@ impl RawConnection for PgRawConnection {
         query: Query<Self::Backend>,
     ) -> LocalBoxFuture<'_, QueryResult<RowStream<'_, Self>>> {
         Box::pin(async move {
+            self.close_dropped_cursors().await;
             let stmt = query.inner.prepare(self).await?;
 
             let stream = self
                 .inner
-                .query_raw(&stmt, query.binds.binds())
+                .query_raw(&stmt.stmt, query.binds.binds())
                 .await
-                .map_err(crate::error_to_query_error)?;
+                .map_err(|err| crate::error_to_query_error(err).with_query(&*stmt.sql))?;
 
-            let mut stream = Box::pin(stream).map(|r| r.map_err(crate::error_to_query_error));
+            let sql = stmt.sql;
+            let mut stream = Box::pin(stream)
+                .map(move |r| r.map_err(|err| crate::error_to_query_error(err).with_query(&*sql)));
             if let Some(row) = stream.try_next().await? {
                 // Register the columns types metadata so we can use them in parameters.
                 for col in row.columns() {
@@ -136,8 +173,14 @@ impl RawConnection for PgRawConnection {
                 }
 
                 let stream = tokio::stream::once(Ok(row)).chain(stream);
+                let lookup = self.metadata.clone();
 
-                Ok(Box::pin(stream.map(|r| r.map(|inner| PgRow { inner }))) as RowStream<'_, Self>)
+                Ok(Box::pin(stream.map(move |r| {
+                    r.map(|inner| PgRow {
+                        inner,
+                        lookup: lookup.clone(),
+                    })
+                })) as RowStream<'_, Self>)
             } else {
                 // The stream is empty.
                 Ok(Box::pin(tokio::stream::empty()) as RowStream<'_, Self>)
@@ -152,6 +195,13 @@ impl RawConnection for PgRawConnection {
 
 pub struct PgRow {
     inner: tokio_postgres::Row,
+    lookup: Arc<MetadataLookup>,
+}
+
+impl PgRow {
+    pub(crate) fn new(inner: tokio_postgres::Row, lookup: Arc<MetadataLookup>) -> Self {
+        Self { inner, lookup }
+    }
 }
 
 impl Row for PgRow {
@@ -161,7 +211,53 @@ impl Row for PgRow {
         self.inner.len()
     }
 
+    fn column(&self, idx: usize) -> Option<ColumnDescriptor<'_, Pg>> {
+        // tokio-postgres doesn't expose the table OID and attribute number of the
+        // columns, so only the name and type are available.
+        self.inner
+            .columns()
+            .get(idx)
+            .map(|col| ColumnDescriptor::new(col.name(), Some(col.type_().clone())))
+    }
+
+    fn column_index(&self, name: &str) -> Option<usize> {
+        self.inner
+            .columns()
+            .iter()
+            .position(|col| col.name() == name)
+    }
+
+    /// Fails unless the column has the type of `SqlTy`, or a type with the same binary
+    /// format (`VARCHAR`, `BPCHAR` and `NAME` for `Text`). Other pairings, like `INT2`
+    /// read as `Integer`, are errors.
     fn get_column<'a, SqlTy, RustTy>(&'a self, idx: usize) -> AnyResult<RustTy>
+    where
+        Self::Backend: HasSqlType<SqlTy>,
+        RustTy: FromSql<'a, SqlTy, Self::Backend>,
+    {
+        let column = self
+            .inner
+            .columns()
+            .get(idx)
+            .ok_or_else(|| format!("Column index {} out of bounds", idx))?;
+
+        // Built-in types are resolved immediately, types that need to be queried from
+        // the database aren't checked.
+        let expected = <Pg as HasSqlType<SqlTy>>::metadata(&self.lookup).now_or_never();
+        if let Some(expected) = expected.transpose()?.flatten() {
+            if !crate::types::is_compatible(&expected, column.type_()) {
+                return Err(Box::new(ColumnTypeMismatch {
+                    column: column.name().to_string(),
+                    expected: expected.name().to_string(),
+                    actual: column.type_().name().to_string(),
+                }));
+            }
+        }
+
+        self.get_column_unchecked::<SqlTy, RustTy>(idx)
+    }
+
+    fn get_column_unchecked<'a, SqlTy, RustTy>(&'a self, idx: usize) -> AnyResult<RustTy>
     where
         Self::Backend: HasSqlType<SqlTy>,
         RustTy: FromSql<'a, SqlTy, Self::Backend>,
@@ -172,11 +268,15 @@ impl Row for PgRow {
     }
 }
 
-struct PgRowCol<'b>(&'b [u8]);
+struct PgRowCol<'b>(PgValue<'b>);
 
 impl<'a> tokio_postgres::types::FromSql<'a> for PgRowCol<'a> {
     fn from_sql(_ty: &Type, raw: &'a [u8]) -> AnyResult<Self> {
-        Ok(Self(raw))
+        Ok(Self(PgValue::new(raw)))
+    }
+
+    fn from_sql_null(_ty: &Type) -> AnyResult<Self> {
+        Ok(Self(PgValue::null()))
     }
 
     fn accepts(_ty: &Type) -> bool {
@@ -0,0 +1,370 @@
+use crate::connection::PgRawConnection;
+use crate::{MetadataLookup, Pg, PgBindCollector};
+use asphalt_core::backend::HasSqlType;
+use asphalt_core::error::{Error, QueryResult};
+use asphalt_core::query::BindCollector;
+use asphalt_core::types::ToSql;
+use bytes::{BufMut, Bytes, BytesMut};
+use futures_util::sink::{Sink, SinkExt};
+use futures_util::stream::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio_postgres::{CopyInSink, CopyOutStream};
+
+/// The header of the binary `COPY` format: the signature, the flags and the length of
+/// the header extension area.
+const BINARY_HEADER: &[u8] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+
+/// Encoded rows are sent to the server once they reach this size.
+const FLUSH_THRESHOLD: usize = 64 * 1024;
+
+impl PgRawConnection {
+    /// Starts a `COPY ... FROM STDIN` statement, returning a sink for its data.
+    pub async fn copy_in(&self, statement: &str) -> QueryResult<CopyInWriter> {
+        self.close_dropped_cursors().await;
+
+        let sink = self
+            .inner
+            .copy_in(statement)
+            .await
+            .map_err(|err| crate::error_to_query_error(err).with_query(statement))?;
+
+        Ok(CopyInWriter {
+            sink: Box::pin(sink),
+        })
+    }
+
+    /// Starts a `COPY ... FROM STDIN BINARY` statement, returning a writer which encodes
+    /// the rows in the binary format.
+    pub async fn copy_in_binary(&self, statement: &str) -> QueryResult<BinaryCopyInWriter<'_>> {
+        let writer = self.copy_in(statement).await?;
+
+        Ok(BinaryCopyInWriter {
+            writer,
+            encoder: BinaryCopyEncoder::new(&self.metadata),
+        })
+    }
+
+    /// Starts a `COPY ... TO STDOUT` statement, returning a stream of its data.
+    pub async fn copy_out(&self, statement: &str) -> QueryResult<CopyOutReader> {
+        self.close_dropped_cursors().await;
+
+        let stream = self
+            .inner
+            .copy_out(statement)
+            .await
+            .map_err(|err| crate::error_to_query_error(err).with_query(statement))?;
+
+        Ok(CopyOutReader {
+            stream: Box::pin(stream),
+        })
+    }
+}
+
+/// A sink for the data of a `COPY ... FROM STDIN` statement.
+///
+/// The data must be in the format given in the statement, i.e. text, CSV or binary (see
+/// [`BinaryCopyEncoder`]), and can be split in chunks of any size. The copied rows are
+/// only committed by [`CopyInWriter::finish`], or when the sink is closed. Dropping the
+/// writer before that aborts the copy.
+pub struct CopyInWriter {
+    sink: Pin<Box<CopyInSink<Bytes>>>,
+}
+
+impl CopyInWriter {
+    /// Sends a chunk of data to the server.
+    pub async fn send(&mut self, data: Bytes) -> QueryResult<()> {
+        self.sink
+            .send(data)
+            .await
+            .map_err(crate::error_to_query_error)
+    }
+
+    /// Finishes the copy, returning the number of copied rows.
+    pub async fn finish(mut self) -> QueryResult<u64> {
+        self.sink
+            .as_mut()
+            .finish()
+            .await
+            .map_err(crate::error_to_query_error)
+    }
+}
+
+impl Sink<Bytes> for CopyInWriter {
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<QueryResult<()>> {
+        self.sink
+            .as_mut()
+            .poll_ready(cx)
+            .map_err(crate::error_to_query_error)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, data: Bytes) -> QueryResult<()> {
+        self.sink
+            .as_mut()
+            .start_send(data)
+            .map_err(crate::error_to_query_error)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<QueryResult<()>> {
+        self.sink
+            .as_mut()
+            .poll_flush(cx)
+            .map_err(crate::error_to_query_error)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<QueryResult<()>> {
+        self.sink
+            .as_mut()
+            .poll_close(cx)
+            .map_err(crate::error_to_query_error)
+    }
+}
+
+/// The data of a `COPY ... TO STDOUT` statement.
+///
+/// In the text and CSV formats, each chunk contains exactly one row.
+pub struct CopyOutReader {
+    stream: Pin<Box<CopyOutStream>>,
+}
+
+impl Stream for CopyOutReader {
+    type Item = QueryResult<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.stream
+            .as_mut()
+            .poll_next(cx)
+            .map(|item| item.map(|res| res.map_err(crate::error_to_query_error)))
+    }
+}
+
+/// Encodes rows in the binary format of `COPY`.
+///
+/// The fields are serialized with the same [`ToSql`] implementations used for bind
+/// parameters, so they must be pushed with the SQL types of the columns being copied,
+/// in order:
+///
+/// ```text
+/// COPY users (id, name) FROM STDIN BINARY
+/// ```
+///
+/// requires each row to be encoded by pushing an `Integer` and a `Text`, followed by
+/// a call to [`finish_row`](BinaryCopyEncoder::finish_row).
+pub struct BinaryCopyEncoder<'c> {
+    lookup: &'c MetadataLookup,
+    collector: PgBindCollector,
+    buffer: BytesMut,
+    /// Position of the field count of the row being encoded, if any.
+    row_start: Option<usize>,
+    fields: i16,
+}
+
+impl<'c> BinaryCopyEncoder<'c> {
+    pub fn new(lookup: &'c MetadataLookup) -> Self {
+        let mut buffer = BytesMut::new();
+        buffer.put_slice(BINARY_HEADER);
+
+        Self {
+            lookup,
+            collector: PgBindCollector::default(),
+            buffer,
+            row_start: None,
+            fields: 0,
+        }
+    }
+
+    /// Encodes the next field of the current row, starting a new row if needed.
+    pub async fn push<ST, RT>(&mut self, value: &RT) -> QueryResult<()>
+    where
+        Pg: HasSqlType<ST>,
+        RT: ToSql<ST, Pg> + ?Sized,
+    {
+        if self.row_start.is_none() {
+            self.row_start = Some(self.buffer.len());
+            // Placeholder for the field count, written when the row is finished.
+            self.buffer.put_i16(0);
+        }
+
+        self.collector
+            .push_bound_value::<ST, RT>(value, self.lookup)
+            .await?;
+
+        match self.collector.pop_value() {
+            Some(value) => {
+                self.buffer.put_i32(value.len() as i32);
+                self.buffer.put_slice(&value);
+            }
+            None => self.buffer.put_i32(-1),
+        }
+
+        self.fields += 1;
+        Ok(())
+    }
+
+    /// Finishes the current row.
+    pub fn finish_row(&mut self) {
+        match self.row_start.take() {
+            Some(start) => {
+                self.buffer[start..start + 2].copy_from_slice(&self.fields.to_be_bytes());
+            }
+            // A row without columns.
+            None => self.buffer.put_i16(0),
+        }
+
+        self.fields = 0;
+    }
+
+    /// Returns the number of encoded bytes not taken yet.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Takes the data of the rows finished until now.
+    pub fn take(&mut self) -> Bytes {
+        let end = self.row_start.unwrap_or_else(|| self.buffer.len());
+        if let Some(start) = &mut self.row_start {
+            *start -= end;
+        }
+
+        self.buffer.split_to(end).freeze()
+    }
+
+    /// Finishes the encoding, returning the remaining data.
+    ///
+    /// The current row is finished, if any.
+    pub fn finish(mut self) -> Bytes {
+        if self.row_start.is_some() {
+            self.finish_row();
+        }
+
+        // The file trailer.
+        self.buffer.put_i16(-1);
+        self.buffer.freeze()
+    }
+}
+
+/// A writer for `COPY ... FROM STDIN BINARY` statements, encoding the rows with a
+/// [`BinaryCopyEncoder`].
+///
+/// Dropping the writer before calling [`finish`](BinaryCopyInWriter::finish) aborts
+/// the copy.
+pub struct BinaryCopyInWriter<'c> {
+    writer: CopyInWriter,
+    encoder: BinaryCopyEncoder<'c>,
+}
+
+impl BinaryCopyInWriter<'_> {
+    /// Encodes the next field of the current row, starting a new row if needed.
+    pub async fn push<ST, RT>(&mut self, value: &RT) -> QueryResult<()>
+    where
+        Pg: HasSqlType<ST>,
+        RT: ToSql<ST, Pg> + ?Sized,
+    {
+        self.encoder.push::<ST, RT>(value).await
+    }
+
+    /// Finishes the current row, sending the encoded rows to the server once enough
+    /// of them are buffered.
+    pub async fn finish_row(&mut self) -> QueryResult<()> {
+        self.encoder.finish_row();
+
+        if self.encoder.buffered() >= FLUSH_THRESHOLD {
+            let data = self.encoder.take();
+            self.writer.send(data).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Finishes the copy, returning the number of copied rows.
+    pub async fn finish(mut self) -> QueryResult<u64> {
+        let data = self.encoder.finish();
+        self.writer.send(data).await?;
+        self.writer.finish().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::connection;
+    use crate::PgConnectionExt;
+    use asphalt_core::types::{Integer, Nullable, Text};
+    use futures_util::{FutureExt, TryStreamExt};
+
+    #[test]
+    fn encodes_binary_rows() {
+        let lookup = MetadataLookup::default();
+        let mut encoder = BinaryCopyEncoder::new(&lookup);
+
+        encoder
+            .push::<Integer, _>(&7)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        encoder
+            .push::<Nullable<Text>, _>(&None::<String>)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        encoder.finish_row();
+        encoder
+            .push::<Integer, _>(&8)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+
+        let mut expected = BINARY_HEADER.to_vec();
+        expected.extend_from_slice(&[0, 2, 0, 0, 0, 4, 0, 0, 0, 7, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(encoder.take(), expected);
+
+        let expected = [0, 1, 0, 0, 0, 4, 0, 0, 0, 8, 0xff, 0xff];
+        assert_eq!(encoder.finish(), &expected[..]);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn copies_rows_in_and_out() {
+        let conn = connection().await;
+        conn.batch_execute("CREATE TEMPORARY TABLE copied (id INTEGER, name TEXT)")
+            .await
+            .unwrap();
+
+        let mut writer = conn
+            .copy_in_binary("COPY copied (id, name) FROM STDIN BINARY")
+            .await
+            .unwrap();
+        writer.push::<Integer, _>(&1).await.unwrap();
+        writer
+            .push::<Nullable<Text>, _>(&Some(String::from("asphalt")))
+            .await
+            .unwrap();
+        writer.finish_row().await.unwrap();
+        writer.push::<Integer, _>(&2).await.unwrap();
+        writer
+            .push::<Nullable<Text>, _>(&None::<String>)
+            .await
+            .unwrap();
+        writer.finish_row().await.unwrap();
+        assert_eq!(writer.finish().await.unwrap(), 2);
+
+        let mut writer = conn
+            .copy_in("COPY copied (id, name) FROM STDIN")
+            .await
+            .unwrap();
+        writer.send(Bytes::from_static(b"3\tcopy\n")).await.unwrap();
+        assert_eq!(writer.finish().await.unwrap(), 1);
+
+        let chunks: Vec<Bytes> = conn
+            .copy_out("COPY (SELECT id, name FROM copied ORDER BY id) TO STDOUT")
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(chunks.concat(), b"1\tasphalt\n2\t\\N\n3\tcopy\n");
+    }
+}
@@ -0,0 +1,272 @@
+use crate::connection::{PgRawConnection, PgRow};
+use crate::Pg;
+use asphalt_core::connection::{Connection, RawConnection, RowStream};
+use asphalt_core::error::{Error, QueryResult};
+use asphalt_core::query::{Query, QueryBuilder};
+use futures_util::stream::{self, StreamExt};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio_postgres::Statement;
+
+/// Number of rows fetched at once by default.
+const DEFAULT_BATCH_SIZE: u32 = 1000;
+
+static NEXT_CURSOR_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A server-side cursor, see [`PgConnectionExt::cursor`](crate::PgConnectionExt::cursor).
+///
+/// The rows of the query are fetched from the server in batches, with `FETCH`, so only
+/// a single batch is kept in memory at a time.
+#[must_use = "The cursor is only declared by calling `open`"]
+pub struct Cursor<'c> {
+    conn: &'c Connection<Pg>,
+    query: QueryBuilder<'c, 'static, Pg>,
+    batch_size: u32,
+}
+
+impl<'c> Cursor<'c> {
+    pub(crate) fn new(conn: &'c Connection<Pg>, query: QueryBuilder<'c, 'static, Pg>) -> Self {
+        Self {
+            conn,
+            query,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+
+    /// Sets the number of rows fetched from the server at once.
+    ///
+    /// # Panics
+    ///
+    /// If `batch_size` is zero.
+    pub fn batch_size(mut self, batch_size: u32) -> Self {
+        assert!(
+            batch_size > 0,
+            "The batch size of a cursor must be positive"
+        );
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Declares the cursor, returning its rows as a stream.
+    ///
+    /// Cursors only exist inside a transaction, so this fails if the connection isn't
+    /// inside one. The cursor is closed when all its rows are consumed. If the stream is
+    /// dropped before that, the cursor is closed before the next statement executed by
+    /// the connection.
+    pub async fn open(self) -> QueryResult<RowStream<'c, PgRawConnection>> {
+        let conn = self.conn.raw_connection();
+        let manager = conn.transaction_manager();
+        if manager.transaction_depth() == 0 {
+            return Err(Error::query_builder_error(
+                "Cursors can only be opened inside a transaction".into(),
+            ));
+        }
+
+        let name = format!(
+            "asphalt_cursor_{}",
+            NEXT_CURSOR_ID.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let Query { inner, binds } = self.query.finish();
+        let inner = inner.declare_cursor(&name);
+        conn.execute(Query { inner, binds }).await?;
+
+        let fetch = format!("FETCH FORWARD {} FROM {}", self.batch_size, name);
+        let fetch = conn
+            .inner
+            .prepare(&fetch)
+            .await
+            .map_err(|err| crate::error_to_query_error(err).with_query(fetch))?;
+        // Register the columns types metadata so we can use them in parameters.
+        for col in fetch.columns() {
+            conn.metadata.register_type_metadata(col.type_().clone());
+        }
+
+        let state = CursorState {
+            conn,
+            fetch,
+            name,
+            generation: manager.transaction_generation(),
+            batch_size: self.batch_size as usize,
+            done: false,
+            close_error: None,
+        };
+
+        let batches = stream::unfold(state, |mut state| async move {
+            if let Some(err) = state.close_error.take() {
+                return Some((Err(err), state));
+            }
+            if state.done {
+                return None;
+            }
+
+            let batch = state.fetch_batch().await;
+            Some((batch, state))
+        });
+
+        Ok(Box::pin(batches.flat_map(|batch| {
+            let rows: Vec<_> = match batch {
+                Ok(rows) => rows.into_iter().map(Ok).collect(),
+                Err(err) => vec![Err(err)],
+            };
+
+            stream::iter(rows)
+        })) as RowStream<'c, PgRawConnection>)
+    }
+}
+
+/// An open cursor.
+struct CursorState<'c> {
+    conn: &'c PgRawConnection,
+    fetch: Statement,
+    name: String,
+    /// The transaction generation when the cursor was declared.
+    generation: u64,
+    batch_size: usize,
+    /// Was the cursor exhausted, or failed?
+    done: bool,
+    /// The error closing the exhausted cursor, reported after its last rows.
+    close_error: Option<Error>,
+}
+
+impl CursorState<'_> {
+    async fn fetch_batch(&mut self) -> QueryResult<Vec<PgRow>> {
+        let rows = self.conn.inner.query(&self.fetch, &[]).await;
+
+        let rows = match rows {
+            Ok(rows) => rows,
+            Err(err) => {
+                // The transaction is aborted, taking the cursor with it.
+                self.done = true;
+                return Err(crate::error_to_query_error(err));
+            }
+        };
+
+        if rows.len() < self.batch_size {
+            self.done = true;
+
+            let close = format!("CLOSE {}", self.name);
+            if let Err(err) = self.conn.inner.batch_execute(&close).await {
+                self.close_error = Some(crate::error_to_query_error(err).with_query(close));
+            }
+        }
+
+        Ok(rows
+            .into_iter()
+            .map(|row| PgRow::new(row, self.conn.metadata.clone()))
+            .collect())
+    }
+}
+
+impl Drop for CursorState<'_> {
+    fn drop(&mut self) {
+        // If the transaction in which the cursor was declared was rolled back, or the
+        // outermost one finished, the cursor was closed with it, and closing it again
+        // would abort the current transaction.
+        let generation = self.conn.transaction_manager().transaction_generation();
+        if !self.done && generation == self.generation {
+            self.conn.close_cursor_later(std::mem::take(&mut self.name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! These tests need a running PostgreSQL server, see [`test_db`](crate::test_db).
+    use super::*;
+    use crate::test_db::connection;
+    use crate::PgConnectionExt;
+    use asphalt_core::types::BigInt;
+    use futures_util::TryStreamExt;
+
+    async fn open(conn: &Connection<Pg>) -> QueryResult<RowStream<'_, PgRawConnection>> {
+        let mut query = conn.query_builder();
+        query.push_sql("SELECT * FROM generate_series(1, 10)");
+
+        let mut rows = conn.cursor(query).batch_size(2).open().await?;
+        rows.try_next().await?;
+        Ok(rows)
+    }
+
+    async fn open_cursors(conn: &Connection<Pg>) -> QueryResult<i64> {
+        let mut query = conn.query_builder();
+        query.push_sql("SELECT count(*) FROM pg_cursors WHERE name LIKE 'asphalt_cursor_%'");
+
+        conn.get_scalar::<BigInt, _>(query).await
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn closes_dropped_cursors() {
+        let conn = connection().await;
+
+        let res = conn
+            .transaction(async {
+                let mut cursor = None;
+                conn.transaction(async {
+                    cursor = Some(open(&conn).await?);
+                    QueryResult::Ok(())
+                })
+                .await?;
+
+                // Released savepoints keep their cursors.
+                drop(cursor);
+                assert_eq!(open_cursors(&conn).await?, 0);
+
+                QueryResult::Ok(())
+            })
+            .await;
+
+        res.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn doesnt_close_cursors_of_finished_transactions() {
+        let conn = connection().await;
+
+        let mut cursor = None;
+        conn.transaction(async {
+            cursor = Some(open(&conn).await?);
+            QueryResult::Ok(())
+        })
+        .await
+        .unwrap();
+
+        // Closing the cursor would abort the new transaction.
+        let res = conn
+            .transaction(async {
+                drop(cursor.take());
+                conn.batch_execute("SELECT 1").await
+            })
+            .await;
+
+        res.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn doesnt_close_cursors_of_rolled_back_savepoints() {
+        let conn = connection().await;
+
+        let res = conn
+            .transaction(async {
+                let mut cursor = None;
+                conn.transaction(async {
+                    cursor = Some(open(&conn).await?);
+                    Err::<(), _>(Error::rollback_transaction())
+                })
+                .await
+                .unwrap_err();
+
+                // A new savepoint at the same depth as the one of the cursor.
+                conn.transaction(async {
+                    drop(cursor.take());
+                    conn.batch_execute("SELECT 1").await
+                })
+                .await
+            })
+            .await;
+
+        res.unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+use crate::copy::{BinaryCopyInWriter, CopyInWriter, CopyOutReader};
+use crate::cursor::Cursor;
+use crate::notify::Notifications;
+use crate::Pg;
+use asphalt_core::connection::Connection;
+use asphalt_core::error::QueryResult;
+use asphalt_core::query::QueryBuilder;
+use asphalt_core::LocalBoxFuture;
+
+/// Postgres specific functionality of [`Connection`]s.
+pub trait PgConnectionExt {
+    /// Creates a server-side cursor for the query stored inside a [`QueryBuilder`].
+    ///
+    /// Cursors stream the rows of queries with huge result sets using bounded memory.
+    /// See [`Cursor`] for more info.
+    fn cursor<'c>(&'c self, query: QueryBuilder<'c, 'static, Pg>) -> Cursor<'c>;
+
+    /// Starts a `COPY ... FROM STDIN` statement, returning a sink for its data.
+    ///
+    /// See [`CopyInWriter`] for more info.
+    fn copy_in<'c>(&'c self, statement: &'c str) -> LocalBoxFuture<'c, QueryResult<CopyInWriter>>;
+
+    /// Starts a `COPY ... FROM STDIN BINARY` statement, returning a writer which encodes
+    /// the rows in the binary format.
+    ///
+    /// See [`BinaryCopyEncoder`](crate::BinaryCopyEncoder) for more info.
+    fn copy_in_binary<'c>(
+        &'c self,
+        statement: &'c str,
+    ) -> LocalBoxFuture<'c, QueryResult<BinaryCopyInWriter<'c>>>;
+
+    /// Starts a `COPY ... TO STDOUT` statement, returning a stream of its data.
+    fn copy_out<'c>(&'c self, statement: &'c str)
+        -> LocalBoxFuture<'c, QueryResult<CopyOutReader>>;
+
+    /// Subscribes to the notifications sent to `channel`, with `LISTEN`.
+    ///
+    /// The notifications are received through [`notifications`](Self::notifications).
+    /// Connections checked out from a pool may be replaced at any time, use a
+    /// [`PgListener`](crate::PgListener) to keep listening across reconnects.
+    fn listen<'c>(&'c self, channel: &'c str) -> LocalBoxFuture<'c, QueryResult<()>>;
+
+    /// Unsubscribes from the notifications sent to `channel`, with `UNLISTEN`.
+    fn unlisten<'c>(&'c self, channel: &'c str) -> LocalBoxFuture<'c, QueryResult<()>>;
+
+    /// Sends a notification with the given `payload` to `channel`.
+    fn notify<'c>(
+        &'c self,
+        channel: &'c str,
+        payload: &'c str,
+    ) -> LocalBoxFuture<'c, QueryResult<()>>;
+
+    /// Returns a stream of the notifications received by the connection from now on.
+    fn notifications(&self) -> Notifications;
+}
+
+impl PgConnectionExt for Connection<Pg> {
+    fn cursor<'c>(&'c self, query: QueryBuilder<'c, 'static, Pg>) -> Cursor<'c> {
+        Cursor::new(self, query)
+    }
+
+    fn copy_in<'c>(&'c self, statement: &'c str) -> LocalBoxFuture<'c, QueryResult<CopyInWriter>> {
+        Box::pin(self.raw_connection().copy_in(statement))
+    }
+
+    fn copy_in_binary<'c>(
+        &'c self,
+        statement: &'c str,
+    ) -> LocalBoxFuture<'c, QueryResult<BinaryCopyInWriter<'c>>> {
+        Box::pin(self.raw_connection().copy_in_binary(statement))
+    }
+
+    fn copy_out<'c>(
+        &'c self,
+        statement: &'c str,
+    ) -> LocalBoxFuture<'c, QueryResult<CopyOutReader>> {
+        Box::pin(self.raw_connection().copy_out(statement))
+    }
+
+    fn listen<'c>(&'c self, channel: &'c str) -> LocalBoxFuture<'c, QueryResult<()>> {
+        Box::pin(self.raw_connection().listen(channel))
+    }
+
+    fn unlisten<'c>(&'c self, channel: &'c str) -> LocalBoxFuture<'c, QueryResult<()>> {
+        Box::pin(self.raw_connection().unlisten(channel))
+    }
+
+    fn notify<'c>(
+        &'c self,
+        channel: &'c str,
+        payload: &'c str,
+    ) -> LocalBoxFuture<'c, QueryResult<()>> {
+        Box::pin(self.raw_connection().notify(channel, payload))
+    }
+
+    fn notifications(&self) -> Notifications {
+        self.raw_connection().notifications()
+    }
+}
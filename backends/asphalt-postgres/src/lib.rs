@@ -1,22 +1,41 @@
 #![feature(generic_associated_types)]
 use asphalt_core::backend::{Backend, TypeMetadata};
-use asphalt_core::error::{DatabaseErrorInformation, DatabaseErrorKind, Error};
+use asphalt_core::error::{DatabaseErrorInformation, DatabaseErrorKind, Error, ErrorPosition};
+use asphalt_core::extensions::{self, Supports};
 use asphalt_core::values::RawValue;
 use std::error::Error as StdError;
-use tokio_postgres::error::SqlState;
+use tokio_postgres::error::{ErrorPosition as PgErrorPosition, SqlState};
 use tokio_postgres::types::Type;
 
 mod connection;
+mod copy;
+mod cursor;
+mod ext;
+mod listener;
 mod metadata;
+mod notify;
 mod query;
+#[cfg(test)]
+mod test_db;
 mod types;
 
 #[doc(inline)]
-pub use self::connection::PgRawConnection;
+pub use self::connection::{Config, ConnectionConfig, PgRawConnection, PgRow};
+#[doc(inline)]
+pub use self::copy::{BinaryCopyEncoder, BinaryCopyInWriter, CopyInWriter, CopyOutReader};
+#[doc(inline)]
+pub use self::cursor::Cursor;
+#[doc(inline)]
+pub use self::ext::PgConnectionExt;
+#[doc(inline)]
+pub use self::listener::PgListener;
 #[doc(inline)]
 pub use self::metadata::MetadataLookup;
 #[doc(inline)]
-pub use self::query::{PgBindCollector, PgQuery, PgQueryWriter};
+pub use self::notify::Notifications;
+#[doc(inline)]
+pub use self::query::{PgBindCollector, PgQuery, PgQueryWriter, PgStatement};
+pub use tokio_postgres::Notification;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Pg;
@@ -27,48 +46,94 @@ impl Backend for Pg {
     type BindName = (u16, Option<Type>);
     type BindCollector = PgBindCollector;
     type RawConnection = PgRawConnection;
-    type RawValue<'a> = &'a [u8];
+    type RawValue<'a> = PgValue<'a>;
 }
 
+impl Supports<extensions::Transaction> for Pg {}
+impl Supports<extensions::IsolationLevel> for Pg {}
+impl Supports<extensions::ReadOnly> for Pg {}
+
 impl TypeMetadata for Pg {
     // The metadata is handled automatically by the database driver.
     type TypeMetadata = Option<Type>;
     type MetadataLookup = MetadataLookup;
 }
 
-impl RawValue<Pg> for &'_ [u8] {
+/// A raw value in the Postgres binary format.
+///
+/// `NULL` is represented separately from the value bytes, so that zero-length values,
+/// like empty strings, aren't mistaken for `NULL`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct PgValue<'a>(Option<&'a [u8]>);
+
+impl<'a> PgValue<'a> {
+    /// A non-null value with the given bytes.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self(Some(bytes))
+    }
+
+    /// The `NULL` value.
+    pub fn null() -> Self {
+        Self(None)
+    }
+
+    /// The bytes of the value, or `None` if it is `NULL`.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        self.0
+    }
+}
+
+impl RawValue<Pg> for PgValue<'_> {
     fn is_null(&self) -> bool {
-        self.is_empty()
+        self.0.is_none()
     }
 
     fn null_value() -> Self {
-        &[]
+        Self::null()
     }
 }
 
 pub(crate) fn dberror_to_query_error(err: tokio_postgres::error::DbError) -> Error {
-    let kind = if *err.code() == SqlState::UNIQUE_VIOLATION {
-        DatabaseErrorKind::UniqueViolation
-    } else if *err.code() == SqlState::FOREIGN_KEY_VIOLATION {
-        DatabaseErrorKind::ForeignKeyViolation
-    } else if *err.code() == SqlState::READ_ONLY_SQL_TRANSACTION {
-        DatabaseErrorKind::ReadOnlyTransaction
-    } else if [
-        SqlState::INVALID_JSON_TEXT,
-        SqlState::INVALID_XML_DOCUMENT,
-        SqlState::INVALID_XML_COMMENT,
-        SqlState::INVALID_XML_CONTENT,
-    ]
-    .contains(err.code())
-    {
-        DatabaseErrorKind::SerializationFailure
-    } else {
-        DatabaseErrorKind::Unknown
-    };
-
+    let kind = kind_of(err.code(), err.message());
     Error::database_error(kind, PgErrorInfo(err))
 }
 
+/// Classifies an error by its SQLSTATE code, and its message when the code is too
+/// generic.
+fn kind_of(code: &SqlState, message: &str) -> DatabaseErrorKind {
+    // See https://www.postgresql.org/docs/current/errcodes-appendix.html
+    match &code.code()[..2] {
+        // Connection Exception.
+        "08" => DatabaseErrorKind::ConnectionFailure,
+        // Feature Not Supported.
+        "0A" if message == "cached plan must not change result type" => {
+            DatabaseErrorKind::StalePreparedStatement
+        }
+        // Data Exception.
+        "22" => DatabaseErrorKind::InvalidInput,
+        // Integrity Constraint Violation.
+        "23" if *code == SqlState::UNIQUE_VIOLATION => DatabaseErrorKind::UniqueViolation,
+        "23" if *code == SqlState::FOREIGN_KEY_VIOLATION => DatabaseErrorKind::ForeignKeyViolation,
+        "23" if *code == SqlState::NOT_NULL_VIOLATION => DatabaseErrorKind::NotNullViolation,
+        "23" if *code == SqlState::CHECK_VIOLATION => DatabaseErrorKind::CheckViolation,
+        "23" if *code == SqlState::EXCLUSION_VIOLATION => DatabaseErrorKind::ExclusionViolation,
+        // Invalid Transaction State.
+        "25" if *code == SqlState::READ_ONLY_SQL_TRANSACTION => {
+            DatabaseErrorKind::ReadOnlyTransaction
+        }
+        // Transaction Rollback.
+        "40" if *code == SqlState::T_R_SERIALIZATION_FAILURE => {
+            DatabaseErrorKind::SerializationFailure
+        }
+        "40" if *code == SqlState::T_R_DEADLOCK_DETECTED => DatabaseErrorKind::DeadlockDetected,
+        // Operator Intervention, everything other than cancellations means that
+        // the server is shutting down or the database is gone.
+        "57" if *code == SqlState::QUERY_CANCELED => DatabaseErrorKind::QueryCanceled,
+        "57" => DatabaseErrorKind::ConnectionFailure,
+        _ => DatabaseErrorKind::Unknown,
+    }
+}
+
 pub(crate) fn error_to_query_error(err: tokio_postgres::Error) -> Error {
     if let Some(db_error) = err
         .source()
@@ -76,11 +141,21 @@ pub(crate) fn error_to_query_error(err: tokio_postgres::Error) -> Error {
         .cloned()
     {
         dberror_to_query_error(db_error)
+    } else if is_closed(&err) || err.source().map_or(false, |err| err.is::<std::io::Error>()) {
+        Error::database_error(DatabaseErrorKind::ConnectionFailure, err.to_string())
     } else {
         Error::database_error(DatabaseErrorKind::Unknown, err.to_string())
     }
 }
 
+/// Was the error caused by the connection being closed?
+///
+/// tokio-postgres doesn't expose the kind of its errors, but these are the only ones
+/// without a cause reported like this.
+fn is_closed(err: &tokio_postgres::Error) -> bool {
+    err.source().is_none() && err.to_string() == "connection closed"
+}
+
 pub struct PgErrorInfo(tokio_postgres::error::DbError);
 
 impl DatabaseErrorInformation for PgErrorInfo {
@@ -88,6 +163,14 @@ impl DatabaseErrorInformation for PgErrorInfo {
         self.0.message()
     }
 
+    fn sql_state(&self) -> Option<&str> {
+        Some(self.0.code().code())
+    }
+
+    fn severity(&self) -> Option<&str> {
+        Some(self.0.severity())
+    }
+
     fn details(&self) -> Option<&str> {
         self.0.detail()
     }
@@ -96,6 +179,24 @@ impl DatabaseErrorInformation for PgErrorInfo {
         self.0.hint()
     }
 
+    fn position(&self) -> Option<ErrorPosition<'_>> {
+        match self.0.position()? {
+            PgErrorPosition::Original(position) => Some(ErrorPosition::Original(*position)),
+            PgErrorPosition::Internal { position, query } => Some(ErrorPosition::Internal {
+                position: *position,
+                query,
+            }),
+        }
+    }
+
+    fn where_(&self) -> Option<&str> {
+        self.0.where_()
+    }
+
+    fn schema(&self) -> Option<&str> {
+        self.0.schema()
+    }
+
     fn table(&self) -> Option<&str> {
         self.0.table()
     }
@@ -104,7 +205,77 @@ impl DatabaseErrorInformation for PgErrorInfo {
         self.0.column()
     }
 
+    fn datatype(&self) -> Option<&str> {
+        self.0.datatype()
+    }
+
     fn constraint(&self) -> Option<&str> {
         self.0.constraint()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(code: &str) -> DatabaseErrorKind {
+        kind_of(&SqlState::from_code(code), "")
+    }
+
+    #[test]
+    fn classifies_transaction_rollbacks() {
+        assert_eq!(kind("40001"), DatabaseErrorKind::SerializationFailure);
+        assert_eq!(kind("40P01"), DatabaseErrorKind::DeadlockDetected);
+        assert_eq!(kind("40002"), DatabaseErrorKind::Unknown);
+    }
+
+    #[test]
+    fn classifies_integrity_constraint_violations() {
+        assert_eq!(kind("23505"), DatabaseErrorKind::UniqueViolation);
+        assert_eq!(kind("23503"), DatabaseErrorKind::ForeignKeyViolation);
+        assert_eq!(kind("23502"), DatabaseErrorKind::NotNullViolation);
+        assert_eq!(kind("23514"), DatabaseErrorKind::CheckViolation);
+        assert_eq!(kind("23P01"), DatabaseErrorKind::ExclusionViolation);
+    }
+
+    #[test]
+    fn classifies_cancellations_apart_from_shutdowns() {
+        assert_eq!(kind("57014"), DatabaseErrorKind::QueryCanceled);
+        // admin_shutdown, crash_shutdown and database_dropped.
+        assert_eq!(kind("57P01"), DatabaseErrorKind::ConnectionFailure);
+        assert_eq!(kind("57P02"), DatabaseErrorKind::ConnectionFailure);
+        assert_eq!(kind("57P04"), DatabaseErrorKind::ConnectionFailure);
+    }
+
+    #[test]
+    fn classifies_connection_exceptions() {
+        assert_eq!(kind("08000"), DatabaseErrorKind::ConnectionFailure);
+        assert_eq!(kind("08006"), DatabaseErrorKind::ConnectionFailure);
+        assert_eq!(kind("08P01"), DatabaseErrorKind::ConnectionFailure);
+    }
+
+    #[test]
+    fn classifies_stale_plans_by_their_message() {
+        let feature_not_supported = SqlState::from_code("0A000");
+
+        assert_eq!(
+            kind_of(
+                &feature_not_supported,
+                "cached plan must not change result type"
+            ),
+            DatabaseErrorKind::StalePreparedStatement
+        );
+        assert_eq!(
+            kind_of(&feature_not_supported, "cannot use subquery in default"),
+            DatabaseErrorKind::Unknown
+        );
+    }
+
+    #[test]
+    fn classifies_json_errors_as_invalid_input() {
+        // Used to be classified as serialization failures, which are retried.
+        for code in &["22030", "22031", "22032", "22033", "22034", "22035"] {
+            assert_eq!(kind(code), DatabaseErrorKind::InvalidInput);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::notify::Notifications;
+use crate::{Pg, PgRawConnection};
+use asphalt_core::connection::{Connection, RawConnection, RetryPolicy, TransactionManager};
+use asphalt_core::error::{DatabaseErrorKind, Error, QueryResult};
+use futures_util::stream::{self, LocalBoxStream, StreamExt};
+use std::fmt::Display;
+use std::future::Future;
+use std::ops::Deref;
+use std::time::Duration;
+use tokio_postgres::Notification;
+
+/// A dedicated connection listening to notifications, which reconnects when the
+/// connection is lost.
+///
+/// The listener establishes its connections with `connect`, which can return any type
+/// dereferencing to a [`Connection`], e.g. a connection checked out from a pool:
+///
+/// ```ignore
+/// let mut listener = PgListener::new(move || {
+///     let pool = pool.clone();
+///     async move { pool.get().await }
+/// });
+/// listener.listen("jobs").await?;
+///
+/// while let Ok(notification) = listener.recv().await {
+///     println!("{}", notification.payload());
+/// }
+/// ```
+///
+/// After reconnecting, the listener subscribes again to all its channels. Notifications
+/// sent while the listener is disconnected are lost.
+///
+/// Connections are unsubscribed from all channels, with `UNLISTEN *`, before being
+/// released, so they can be reused by a pool. Use [`close`](PgListener::close) to
+/// release the connection when the listener isn't needed anymore, as dropping the
+/// listener can't unsubscribe it.
+pub struct PgListener<C, F> {
+    connect: F,
+    conn: Option<(C, Notifications)>,
+    channels: Vec<String>,
+    policy: RetryPolicy,
+}
+
+impl<C, F, Fut, E> PgListener<C, F>
+where
+    C: Deref<Target = Connection<Pg>>,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<C, E>>,
+    E: Display,
+{
+    pub fn new(connect: F) -> Self {
+        Self {
+            connect,
+            conn: None,
+            channels: Vec::new(),
+            policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets how many times, and how often, the listener tries to reconnect before
+    /// returning an error.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Subscribes to the notifications sent to `channel`.
+    pub async fn listen(&mut self, channel: &str) -> QueryResult<()> {
+        if !self.channels.iter().any(|listened| listened == channel) {
+            self.channels.push(channel.to_string());
+        }
+
+        if let Some((conn, _)) = &self.conn {
+            return conn.raw_connection().listen(channel).await;
+        }
+
+        // All the channels are listened to when connecting.
+        self.reconnect().await
+    }
+
+    /// Unsubscribes from the notifications sent to `channel`.
+    pub async fn unlisten(&mut self, channel: &str) -> QueryResult<()> {
+        self.channels.retain(|listened| listened != channel);
+
+        match &self.conn {
+            Some((conn, _)) => conn.raw_connection().unlisten(channel).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Waits for the next notification, reconnecting if the connection is lost.
+    pub async fn recv(&mut self) -> QueryResult<Notification> {
+        loop {
+            if self.conn.is_none() {
+                self.reconnect().await?;
+            }
+
+            let (_, notifications) = self.conn.as_mut().unwrap();
+            match notifications.next().await {
+                Some(notification) => return Ok(notification),
+                // The connection was closed.
+                None => self.release().await,
+            }
+        }
+    }
+
+    /// Unsubscribes from all the channels and releases the connection.
+    pub async fn close(mut self) {
+        self.release().await;
+    }
+
+    /// Turns the listener into a stream of notifications.
+    pub fn into_stream(self) -> LocalBoxStream<'static, QueryResult<Notification>>
+    where
+        C: 'static,
+        F: 'static,
+        Fut: 'static,
+    {
+        Box::pin(stream::unfold(self, |mut listener| async move {
+            let notification = listener.recv().await;
+            Some((notification, listener))
+        }))
+    }
+
+    /// Connects again, and subscribes to all the channels.
+    async fn reconnect(&mut self) -> QueryResult<()> {
+        self.release().await;
+        let mut attempt = 1;
+
+        loop {
+            match self.try_connect().await {
+                Ok(conn) => {
+                    self.conn = Some(conn);
+                    return Ok(());
+                }
+                Err(err) if attempt >= self.policy.max_attempts => return Err(err),
+                Err(_) => {
+                    let delay = self.policy.backoff.delay(attempt);
+                    if delay > Duration::from_secs(0) {
+                        tokio::time::delay_for(delay).await;
+                    }
+
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Releases the connection, if any, unsubscribing it from all the channels.
+    async fn release(&mut self) {
+        let (conn, _) = match self.conn.take() {
+            Some(conn) => conn,
+            None => return,
+        };
+
+        // Don't let a closed connection, or one which may still be listening, be reused.
+        let raw = conn.raw_connection();
+        if raw.is_closed() || raw.simple_execute("UNLISTEN *").await.is_err() {
+            TransactionManager::<PgRawConnection>::mark_broken(raw.transaction_manager());
+        }
+    }
+
+    async fn try_connect(&mut self) -> QueryResult<(C, Notifications)> {
+        let conn = (self.connect)().await.map_err(|err| {
+            Error::database_error(DatabaseErrorKind::ConnectionFailure, err.to_string())
+        })?;
+
+        // Subscribe before listening, so no notification is lost.
+        let notifications = conn.raw_connection().notifications();
+        for channel in &self.channels {
+            conn.raw_connection().listen(channel).await?;
+        }
+
+        Ok((conn, notifications))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! These tests need a running PostgreSQL server, see [`test_db`](crate::test_db).
+    use super::*;
+    use crate::test_db::{connection, url};
+    use crate::Config;
+    use asphalt_core::types::{BigInt, Integer};
+    use asphalt_pool::{Pool, PoolConfig};
+    use futures_util::future::{self, Either};
+
+    const CHANNEL: &str = "asphalt_listener_reconnects";
+
+    /// Returns the process ID of the backend listening to `CHANNEL`.
+    async fn listening_backend(conn: &Connection<Pg>) -> QueryResult<i32> {
+        let mut query = conn.query_builder();
+        query.push_sql("SELECT pid FROM pg_stat_activity WHERE query = 'LISTEN \"");
+        query.push_sql(CHANNEL);
+        query.push_sql("\"'");
+
+        conn.get_scalar::<Integer, _>(query).await
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn resubscribes_after_reconnecting() {
+        let config = PoolConfig {
+            max_size: 1,
+            ..PoolConfig::default()
+        };
+        let pool = Pool::<Pg>::new(config, Config::new(url().parse().unwrap()))
+            .await
+            .unwrap();
+        let conn = connection().await;
+
+        let mut listener = PgListener::new(|| {
+            let pool = pool.clone();
+            async move { pool.get().await }
+        });
+        listener.listen(CHANNEL).await.unwrap();
+
+        let killed = listening_backend(&conn).await.unwrap();
+        let terminate = format!("SELECT pg_terminate_backend({})", killed);
+        conn.batch_execute(&terminate).await.unwrap();
+
+        // Notifications sent before the listener subscribes again are lost.
+        let notify = async {
+            loop {
+                conn.raw_connection().notify(CHANNEL, "ping").await.unwrap();
+                tokio::time::delay_for(Duration::from_millis(50)).await;
+            }
+        };
+        let notification = match future::select(Box::pin(listener.recv()), Box::pin(notify)).await {
+            Either::Left((notification, _)) => notification.unwrap(),
+            Either::Right(_) => unreachable!(),
+        };
+        assert_eq!(notification.payload(), "ping");
+
+        let listening = listening_backend(&conn).await.unwrap();
+        assert_ne!(listening, killed);
+
+        // The pool reuses the connection of the listener, which isn't listening anymore.
+        listener.close().await;
+        assert_eq!(pool.size(), 1);
+
+        let pooled = pool.get().await.unwrap();
+        let mut query = pooled.query_builder();
+        query.push_sql("SELECT count(*) FROM pg_listening_channels()");
+        assert_eq!(pooled.get_scalar::<BigInt, i64>(query).await.unwrap(), 0);
+
+        let mut query = pooled.query_builder();
+        query.push_sql("SELECT pg_backend_pid()");
+        assert_eq!(
+            pooled.get_scalar::<Integer, i32>(query).await.unwrap(),
+            listening
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use crate::connection::PgRawConnection;
+use asphalt_core::connection::RawConnection;
+use asphalt_core::error::QueryResult;
+use futures_util::stream::{self, Stream, StreamExt};
+use parking_lot::Mutex;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_postgres::{AsyncMessage, Connection, Notification};
+
+/// The subscribers of the notifications received by a connection.
+pub(crate) struct Listeners<T = Notification> {
+    /// The senders of the subscribers, `None` after the connection is closed.
+    senders: Mutex<Option<Vec<UnboundedSender<T>>>>,
+}
+
+impl<T> Default for Listeners<T> {
+    fn default() -> Self {
+        Self {
+            senders: Mutex::new(Some(Vec::new())),
+        }
+    }
+}
+
+impl<T: Clone> Listeners<T> {
+    fn subscribe(&self) -> UnboundedReceiver<T> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let mut senders = self.senders.lock();
+        if let Some(senders) = &mut *senders {
+            senders.push(sender);
+        }
+        // Otherwise the sender is dropped, closing the receiver right away.
+
+        receiver
+    }
+
+    fn dispatch(&self, notification: T) {
+        if let Some(senders) = &mut *self.senders.lock() {
+            // Forget the subscribers that were dropped.
+            senders.retain(|sender| sender.send(notification.clone()).is_ok());
+        }
+    }
+
+    fn close(&self) {
+        self.senders.lock().take();
+    }
+}
+
+/// Drive the connection in the background, forwarding the notifications received by it
+/// to the `listeners`.
+pub(crate) fn spawn_connection<S, T>(mut connection: Connection<S, T>, listeners: Arc<Listeners>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(AsyncMessage::Notification(notification)) => listeners.dispatch(notification),
+                Ok(_) => {}
+                Err(err) => {
+                    eprintln!("connection error: {}", err);
+                    break;
+                }
+            }
+        }
+
+        // Let the subscribers know that no more notifications will be received.
+        listeners.close();
+    });
+}
+
+impl PgRawConnection {
+    /// Subscribes to the notifications sent to `channel`, with `LISTEN`.
+    ///
+    /// The notifications are received through [`PgRawConnection::notifications`].
+    pub async fn listen(&self, channel: &str) -> QueryResult<()> {
+        let sql = format!("LISTEN {}", quote_identifier(channel));
+        self.simple_execute(&sql).await
+    }
+
+    /// Unsubscribes from the notifications sent to `channel`, with `UNLISTEN`.
+    pub async fn unlisten(&self, channel: &str) -> QueryResult<()> {
+        let sql = format!("UNLISTEN {}", quote_identifier(channel));
+        self.simple_execute(&sql).await
+    }
+
+    /// Sends a notification to `channel`, with `pg_notify`.
+    ///
+    /// As with `NOTIFY`, if the connection is inside a transaction, the notification is
+    /// only delivered when the transaction is committed.
+    pub async fn notify(&self, channel: &str, payload: &str) -> QueryResult<()> {
+        self.close_dropped_cursors().await;
+
+        let sql = "SELECT pg_notify($1, $2)";
+        self.inner
+            .execute(sql, &[&channel, &payload])
+            .await
+            .map_err(|err| crate::error_to_query_error(err).with_query(sql))?;
+
+        Ok(())
+    }
+
+    /// Returns a stream of the notifications received by the connection from now on,
+    /// from all the channels it listens to.
+    ///
+    /// The stream ends when the connection is closed.
+    pub fn notifications(&self) -> Notifications {
+        Notifications {
+            receiver: self.listeners.subscribe(),
+        }
+    }
+
+    /// Has the connection to the server been closed?
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+}
+
+/// A stream of the notifications received by a connection, see
+/// [`PgRawConnection::notifications`].
+pub struct Notifications {
+    receiver: UnboundedReceiver<Notification>,
+}
+
+impl Stream for Notifications {
+    type Item = Notification;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    fn recv(receiver: &mut UnboundedReceiver<&'static str>) -> Option<Option<&'static str>> {
+        receiver.recv().now_or_never()
+    }
+
+    fn subscribers(listeners: &Listeners<&'static str>) -> Option<usize> {
+        listeners.senders.lock().as_ref().map(Vec::len)
+    }
+
+    #[test]
+    fn dispatches_to_all_subscribers() {
+        let listeners = Listeners::default();
+        let mut first = listeners.subscribe();
+        let mut second = listeners.subscribe();
+
+        listeners.dispatch("jobs");
+
+        assert_eq!(recv(&mut first), Some(Some("jobs")));
+        assert_eq!(recv(&mut second), Some(Some("jobs")));
+        // Nothing else was received.
+        assert_eq!(recv(&mut first), None);
+    }
+
+    #[test]
+    fn forgets_dropped_subscribers() {
+        let listeners = Listeners::default();
+        let mut kept = listeners.subscribe();
+        drop(listeners.subscribe());
+
+        listeners.dispatch("jobs");
+
+        assert_eq!(recv(&mut kept), Some(Some("jobs")));
+        assert_eq!(subscribers(&listeners), Some(1));
+    }
+
+    #[test]
+    fn closing_ends_the_subscriptions() {
+        let listeners = Listeners::default();
+        let mut before = listeners.subscribe();
+
+        listeners.close();
+        listeners.dispatch("jobs");
+        let mut after = listeners.subscribe();
+
+        assert_eq!(recv(&mut before), Some(None));
+        assert_eq!(recv(&mut after), Some(None));
+        assert_eq!(subscribers(&listeners), None);
+    }
+}
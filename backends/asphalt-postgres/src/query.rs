@@ -1,10 +1,15 @@
 use crate::Pg;
 use asphalt_core::backend::{Backend, HasSqlType, TypeMetadata};
+use asphalt_core::connection::ColumnDescriptor;
 use asphalt_core::error::{AnyResult, Error, QueryResult};
-use asphalt_core::query::{BindCollector, PreparableQuery, QueryWriter};
+use asphalt_core::query::{
+    BindCollector, DebugBind, DescribePrepared, PreparableQuery, QueryWriter,
+};
 use asphalt_core::types::ToSql;
+use asphalt_core::values::RawValue;
 use asphalt_core::LocalBoxFuture;
 use bytes::{Bytes, BytesMut};
+use std::sync::Arc;
 use tokio_postgres::types::{IsNull, Type};
 use tokio_postgres::Statement;
 
@@ -14,11 +19,36 @@ pub struct PgQuery {
 
 enum InnerQuery {
     Raw(String, Vec<Type>),
-    Stmt(Statement),
+    Stmt(PgStatement),
+}
+
+/// A prepared statement of the `Pg` backend.
+///
+/// The SQL of the statement is kept to point to the position of errors in it.
+#[derive(Clone)]
+pub struct PgStatement {
+    pub(crate) stmt: Statement,
+    pub(crate) sql: Arc<str>,
+}
+
+impl PgQuery {
+    /// Turns the query into the declaration of a cursor named `name` for it.
+    pub(crate) fn declare_cursor(self, name: &str) -> Self {
+        match self.inner {
+            InnerQuery::Raw(raw, types) => Self {
+                inner: InnerQuery::Raw(
+                    format!("DECLARE {} NO SCROLL CURSOR FOR {}", name, raw),
+                    types,
+                ),
+            },
+            InnerQuery::Stmt(_) => unreachable!("Tried to declare a cursor for a prepared query"),
+        }
+    }
 }
 
 impl PreparableQuery<Pg> for PgQuery {
-    type Prepared = Statement;
+    type Prepared = PgStatement;
+    type CacheKey = (String, Vec<Type>);
 
     fn prepare(
         self,
@@ -27,10 +57,13 @@ impl PreparableQuery<Pg> for PgQuery {
         match self.inner {
             InnerQuery::Stmt(stmt) => Box::pin(async move { Ok(stmt) }),
             InnerQuery::Raw(raw, types) => Box::pin(async move {
-                conn.inner
-                    .prepare_typed(&raw, &types)
-                    .await
-                    .map_err(crate::error_to_query_error)
+                match conn.inner.prepare_typed(&raw, &types).await {
+                    Ok(stmt) => Ok(PgStatement {
+                        stmt,
+                        sql: raw.into(),
+                    }),
+                    Err(err) => Err(crate::error_to_query_error(err).with_query(raw)),
+                }
             }),
         }
     }
@@ -40,6 +73,28 @@ impl PreparableQuery<Pg> for PgQuery {
             inner: InnerQuery::Stmt(prepared),
         }
     }
+
+    fn cache_key(&self) -> Option<Self::CacheKey> {
+        match &self.inner {
+            InnerQuery::Raw(raw, types) => Some((raw.clone(), types.clone())),
+            InnerQuery::Stmt(_) => None,
+        }
+    }
+}
+
+/// The description of the statement is sent by the server when it is prepared.
+impl DescribePrepared<Pg> for PgStatement {
+    fn param_types(&self) -> Vec<Option<Type>> {
+        self.stmt.params().iter().cloned().map(Some).collect()
+    }
+
+    fn columns(&self) -> Vec<ColumnDescriptor<'_, Pg>> {
+        self.stmt
+            .columns()
+            .iter()
+            .map(|col| ColumnDescriptor::new(col.name(), Some(col.type_().clone())))
+            .collect()
+    }
 }
 
 /// The `QueryWriter` for the `Pg` backend.
@@ -78,6 +133,10 @@ impl QueryWriter<Pg> for PgQueryWriter {
         }
     }
 
+    fn sql(&self) -> &str {
+        &self.query
+    }
+
     fn finish(self) -> <Pg as Backend>::Query {
         PgQuery {
             inner: InnerQuery::Raw(self.query, self.types),
@@ -88,6 +147,8 @@ impl QueryWriter<Pg> for PgQueryWriter {
 #[derive(Default)]
 pub struct PgBindCollector {
     binds: Vec<PgParam>,
+    /// The type of each parameter, used only for debugging.
+    types: Vec<Option<Type>>,
     buffer: BytesMut,
 }
 
@@ -96,6 +157,12 @@ impl PgBindCollector {
         &mut self.buffer
     }
 
+    /// Removes the last bound value, returning its bytes, or `None` if it is `NULL`.
+    pub(crate) fn pop_value(&mut self) -> Option<Bytes> {
+        self.types.pop();
+        self.binds.pop().and_then(|param| param.0)
+    }
+
     pub(crate) fn binds(
         &self,
     ) -> impl Iterator<Item = &dyn tokio_postgres::types::ToSql> + std::iter::ExactSizeIterator
@@ -114,34 +181,279 @@ impl BindCollector<Pg> for PgBindCollector {
     ) -> LocalBoxFuture<'a, QueryResult<<Pg as Backend>::BindName>>
     where
         Pg: HasSqlType<SqlTy>,
-        RustTy: ToSql<SqlTy, Pg>,
+        RustTy: ToSql<SqlTy, Pg> + ?Sized,
     {
         Box::pin(async move {
             let metadata = <Pg as HasSqlType<SqlTy>>::metadata(metadata_lookup).await?;
-            bind.to_sql(&metadata, self)
-                .map_err(Error::serialization_failure)?;
+            let is_null = bind
+                .to_sql(&metadata, self)
+                .map_err(Error::serialization_failure)?
+                .is_null();
 
-            self.binds.push(PgParam(self.buffer.split().freeze()));
+            // Always split the buffer, so that it is empty for the next value.
+            let value = self.buffer.split().freeze();
+            self.binds
+                .push(PgParam(if is_null { None } else { Some(value) }));
+            self.types.push(metadata.clone());
 
             // TODO: error if too many parameters
             Ok((self.binds.len() as u16, metadata))
         })
     }
+
+    fn bind_count(&self) -> usize {
+        self.binds.len()
+    }
+
+    fn debug_binds(&self) -> Vec<DebugBind> {
+        self.binds
+            .iter()
+            .zip(&self.types)
+            .map(|(param, ty)| DebugBind {
+                sql_type: ty.as_ref().map_or("unknown", Type::name).to_string(),
+                literal: debug_literal(ty.as_ref(), param.0.as_deref()),
+            })
+            .collect()
+    }
+}
+
+/// Renders a parameter, in the binary format, as a SQL literal.
+///
+/// Values of types other than booleans, numbers and strings are rendered as strings
+/// cast to their type, e.g. `'2020-01-01'::date`. Parameters of types without a known
+/// representation are rendered as `bytea` literals.
+fn debug_literal(ty: Option<&Type>, value: Option<&[u8]>) -> String {
+    use tokio_postgres::types::FromSql;
+
+    let value = match value {
+        Some(value) => value,
+        None => return String::from("NULL"),
+    };
+
+    let literal = match ty {
+        Some(ty) if *ty == Type::BOOL => {
+            bool::from_sql(ty, value).map(|v| String::from(if v { "TRUE" } else { "FALSE" }))
+        }
+        Some(ty) if *ty == Type::INT2 => i16::from_sql(ty, value).map(|v| v.to_string()),
+        Some(ty) if *ty == Type::INT4 => i32::from_sql(ty, value).map(|v| v.to_string()),
+        Some(ty) if *ty == Type::INT8 => i64::from_sql(ty, value).map(|v| v.to_string()),
+        Some(ty) if *ty == Type::FLOAT4 => {
+            f32::from_sql(ty, value).map(|v| float_literal(f64::from(v)))
+        }
+        Some(ty) if *ty == Type::FLOAT8 => f64::from_sql(ty, value).map(float_literal),
+        Some(ty) if <&str>::accepts(ty) => <&str>::from_sql(ty, value).map(string_literal),
+        Some(ty) => match cast_text(ty, value) {
+            Some(text) => text.map(|text| format!("{}::{}", string_literal(&text), ty.name())),
+            None => return bytea_literal(value),
+        },
+        None => return bytea_literal(value),
+    };
+
+    literal.unwrap_or_else(|_| bytea_literal(value))
+}
+
+/// Renders the value of a type written as a string cast to the type, or `None` if
+/// the type isn't one of them.
+fn cast_text(ty: &Type, value: &[u8]) -> Option<AnyResult<String>> {
+    use tokio_postgres::types::FromSql;
+
+    let text = if *ty == Type::UUID {
+        uuid::Uuid::from_sql(ty, value).map(|v| v.to_string())
+    } else if *ty == Type::DATE {
+        i32::from_sql(ty, value).map(date_text)
+    } else if *ty == Type::TIME {
+        i64::from_sql(ty, value).map(time_text)
+    } else if *ty == Type::TIMESTAMP {
+        i64::from_sql(ty, value).map(|v| timestamp_text(v, ""))
+    } else if *ty == Type::TIMESTAMPTZ {
+        // Timestamps with time zone are sent in UTC.
+        i64::from_sql(ty, value).map(|v| timestamp_text(v, "+00"))
+    } else if *ty == Type::NUMERIC {
+        numeric_text(value).ok_or_else(|| "Invalid numeric value".into())
+    } else if *ty == Type::JSON {
+        String::from_utf8(value.to_vec()).map_err(Into::into)
+    } else if *ty == Type::JSONB {
+        // The JSON is prefixed by the version of the format.
+        match value.split_first() {
+            Some((1, json)) => String::from_utf8(json.to_vec()).map_err(Into::into),
+            _ => Err("Unknown jsonb format".into()),
+        }
+    } else {
+        return None;
+    };
+
+    Some(text)
+}
+
+fn string_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn float_literal(value: f64) -> String {
+    // Postgres only accepts the special values as strings.
+    if value.is_nan() {
+        String::from("'NaN'")
+    } else if value.is_infinite() && value > 0.0 {
+        String::from("'Infinity'")
+    } else if value.is_infinite() {
+        String::from("'-Infinity'")
+    } else {
+        value.to_string()
+    }
 }
 
+/// Renders a date, given as the number of days since 2000-01-01.
+fn date_text(days: i32) -> String {
+    match days {
+        i32::MAX => String::from("infinity"),
+        i32::MIN => String::from("-infinity"),
+        _ => {
+            let (date, era) = date_and_era(i64::from(days));
+            format!("{}{}", date, era)
+        }
+    }
+}
+
+/// Renders the date `days` after 2000-01-01, and its era, ` BC` or nothing.
+fn date_and_era(days: i64) -> (String, &'static str) {
+    // Converts the days since 1970-01-01 to the proleptic Gregorian calendar, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let days = days + 10_957 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    // There is no year 0, 1 BC comes right before 1 AD.
+    if year <= 0 {
+        (format!("{:04}-{:02}-{:02}", 1 - year, month, day), " BC")
+    } else {
+        (format!("{:04}-{:02}-{:02}", year, month, day), "")
+    }
+}
+
+/// Renders a time of the day, given as the number of microseconds since midnight.
+fn time_text(micros: i64) -> String {
+    let seconds = micros / 1_000_000;
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+
+    match micros % 1_000_000 {
+        0 => time,
+        fraction => format!("{}.{:06}", time, fraction),
+    }
+}
+
+/// Renders a timestamp, given as the number of microseconds since 2000-01-01, in
+/// the given time zone.
+fn timestamp_text(micros: i64, time_zone: &str) -> String {
+    const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+    match micros {
+        i64::MAX => String::from("infinity"),
+        i64::MIN => String::from("-infinity"),
+        _ => {
+            let (date, era) = date_and_era(micros.div_euclid(MICROS_PER_DAY));
+            let time = time_text(micros.rem_euclid(MICROS_PER_DAY));
+            format!("{} {}{}{}", date, time, time_zone, era)
+        }
+    }
+}
+
+/// Renders a `numeric` in the binary format, or `None` if it is malformed.
+///
+/// The value is sent as its number of digits in base 10000, the weight of the first
+/// digit, its sign and its scale, followed by the digits.
+fn numeric_text(value: &[u8]) -> Option<String> {
+    let word = |idx: usize| Some(u16::from_be_bytes([*value.get(idx)?, *value.get(idx + 1)?]));
+    let (n_digits, weight, sign, scale) = (word(0)?, word(2)? as i16, word(4)?, word(6)?);
+    let digits = (0..usize::from(n_digits))
+        .map(|idx| word(8 + 2 * idx))
+        .collect::<Option<Vec<_>>>()?;
+    // The digits which aren't sent are zeros.
+    let digit = |idx: i32| {
+        if idx < 0 {
+            0
+        } else {
+            digits.get(idx as usize).copied().unwrap_or(0)
+        }
+    };
+
+    let mut text = String::new();
+    match sign {
+        0x0000 => {}
+        0x4000 => text.push('-'),
+        0xc000 => return Some(String::from("NaN")),
+        0xd000 => return Some(String::from("Infinity")),
+        0xf000 => return Some(String::from("-Infinity")),
+        _ => return None,
+    }
+
+    let weight = i32::from(weight);
+    if weight < 0 {
+        text.push('0');
+    } else {
+        text.push_str(&digit(0).to_string());
+        for idx in 1..=weight {
+            text.push_str(&format!("{:04}", digit(idx)));
+        }
+    }
+
+    if scale > 0 {
+        let scale = usize::from(scale);
+        let mut fraction = String::with_capacity(scale + 4);
+        let mut idx = weight + 1;
+        while fraction.len() < scale {
+            fraction.push_str(&format!("{:04}", digit(idx)));
+            idx += 1;
+        }
+        fraction.truncate(scale);
+
+        text.push('.');
+        text.push_str(&fraction);
+    }
+
+    Some(text)
+}
+
+fn bytea_literal(value: &[u8]) -> String {
+    use std::fmt::Write;
+
+    let mut literal = String::with_capacity(4 + 2 * value.len());
+    literal.push_str("'\\x");
+    for byte in value {
+        // Writing to memory never fails.
+        write!(&mut literal, "{:02x}", byte).unwrap();
+    }
+    literal.push('\'');
+
+    literal
+}
+
+/// A bind parameter, `None` being `NULL`.
 #[derive(Debug)]
-pub(crate) struct PgParam(pub(crate) Bytes);
+pub(crate) struct PgParam(pub(crate) Option<Bytes>);
 
 impl tokio_postgres::types::ToSql for PgParam {
     fn to_sql(&self, _ty: &Type, out: &mut BytesMut) -> AnyResult<IsNull>
     where
         Self: Sized,
     {
-        if self.0.is_empty() {
-            Ok(IsNull::Yes)
-        } else {
-            out.extend_from_slice(&self.0);
-            Ok(IsNull::No)
+        match &self.0 {
+            Some(value) => {
+                out.extend_from_slice(value);
+                Ok(IsNull::No)
+            }
+            None => Ok(IsNull::Yes),
         }
     }
 
@@ -154,3 +466,122 @@ impl tokio_postgres::types::ToSql for PgParam {
 
     tokio_postgres::types::to_sql_checked!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(ty: Type, value: &[u8]) -> String {
+        debug_literal(Some(&ty), Some(value))
+    }
+
+    /// A `numeric` in the binary format, with its digits in base 10000.
+    fn numeric(weight: i16, sign: u16, scale: u16, digits: &[u16]) -> Vec<u8> {
+        let mut value = Vec::new();
+        value.extend_from_slice(&(digits.len() as u16).to_be_bytes());
+        value.extend_from_slice(&weight.to_be_bytes());
+        value.extend_from_slice(&sign.to_be_bytes());
+        value.extend_from_slice(&scale.to_be_bytes());
+        for digit in digits {
+            value.extend_from_slice(&digit.to_be_bytes());
+        }
+
+        value
+    }
+
+    #[test]
+    fn renders_null() {
+        assert_eq!(debug_literal(Some(&Type::INT4), None), "NULL");
+        assert_eq!(debug_literal(None, None), "NULL");
+    }
+
+    #[test]
+    fn escapes_quotes_of_strings() {
+        assert_eq!(literal(Type::TEXT, b"o'brien"), "'o''brien'");
+        assert_eq!(literal(Type::VARCHAR, b"''"), "''''''");
+        assert_eq!(
+            literal(Type::JSON, br#"{"name": "o'brien"}"#),
+            r#"'{"name": "o''brien"}'::json"#
+        );
+    }
+
+    #[test]
+    fn renders_special_floats_as_strings() {
+        assert_eq!(literal(Type::FLOAT8, &f64::NAN.to_be_bytes()), "'NaN'");
+        assert_eq!(
+            literal(Type::FLOAT8, &f64::INFINITY.to_be_bytes()),
+            "'Infinity'"
+        );
+        assert_eq!(
+            literal(Type::FLOAT4, &f32::NEG_INFINITY.to_be_bytes()),
+            "'-Infinity'"
+        );
+        assert_eq!(literal(Type::FLOAT8, &1.5f64.to_be_bytes()), "1.5");
+    }
+
+    #[test]
+    fn renders_bytea() {
+        assert_eq!(literal(Type::BYTEA, &[0xde, 0xad, 0x00]), "'\\xdead00'");
+        assert_eq!(literal(Type::BYTEA, &[]), "'\\x'");
+        assert_eq!(debug_literal(None, Some(&[0x01])), "'\\x01'");
+        // Malformed values of known types too.
+        assert_eq!(literal(Type::INT4, &[0x01]), "'\\x01'");
+    }
+
+    #[test]
+    fn casts_other_types() {
+        let uuid = uuid::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8);
+        assert_eq!(
+            literal(Type::UUID, uuid.as_bytes()),
+            "'67e55044-10b1-426f-9247-bb680e5fe0c8'::uuid"
+        );
+        assert_eq!(literal(Type::JSONB, b"\x01[1, 2]"), "'[1, 2]'::jsonb");
+    }
+
+    #[test]
+    fn renders_dates_and_times() {
+        let date = |days: i32| literal(Type::DATE, &days.to_be_bytes());
+        assert_eq!(date(0), "'2000-01-01'::date");
+        assert_eq!(date(7305), "'2020-01-01'::date");
+        assert_eq!(date(-1), "'1999-12-31'::date");
+        assert_eq!(date(-730_120), "'0001-12-31 BC'::date");
+        assert_eq!(date(-746_117), "'0044-03-15 BC'::date");
+        assert_eq!(date(i32::MAX), "'infinity'::date");
+
+        let time = |micros: i64| literal(Type::TIME, &micros.to_be_bytes());
+        assert_eq!(time(0), "'00:00:00'::time");
+        assert_eq!(time(45_296_000_001), "'12:34:56.000001'::time");
+
+        let timestamp = |micros: i64| literal(Type::TIMESTAMP, &micros.to_be_bytes());
+        assert_eq!(
+            timestamp(86_401_500_000),
+            "'2000-01-02 00:00:01.500000'::timestamp"
+        );
+        assert_eq!(timestamp(i64::MIN), "'-infinity'::timestamp");
+
+        let timestamptz = |micros: i64| literal(Type::TIMESTAMPTZ, &micros.to_be_bytes());
+        assert_eq!(
+            timestamptz(-1),
+            "'1999-12-31 23:59:59.999999+00'::timestamptz"
+        );
+        assert_eq!(
+            timestamptz(-730_120 * 86_400_000_000 + 43_200_000_000),
+            "'0001-12-31 12:00:00+00 BC'::timestamptz"
+        );
+    }
+
+    #[test]
+    fn renders_numerics() {
+        let numeric = |weight, sign, scale, digits: &[u16]| {
+            literal(Type::NUMERIC, &numeric(weight, sign, scale, digits))
+        };
+        assert_eq!(
+            numeric(1, 0x0000, 3, &[1, 2345, 6780]),
+            "'12345.678'::numeric"
+        );
+        assert_eq!(numeric(-1, 0x4000, 4, &[12]), "'-0.0012'::numeric");
+        assert_eq!(numeric(1, 0x0000, 0, &[100]), "'1000000'::numeric");
+        assert_eq!(numeric(0, 0x0000, 2, &[]), "'0.00'::numeric");
+        assert_eq!(numeric(0, 0xc000, 0, &[]), "'NaN'::numeric");
+    }
+}
@@ -1,9 +1,9 @@
-use crate::Pg;
+use crate::{Pg, PgValue};
 use asphalt_core::backend::{Backend, HasSqlType};
 use asphalt_core::error::{AnyResult, QueryResult};
 use asphalt_core::types::*;
 use asphalt_core::LocalBoxFuture;
-use tokio_postgres::types::{FromSql as PgFromSql, ToSql as PgToSql, Type};
+use tokio_postgres::types::{FromSql as PgFromSql, IsNull, ToSql as PgToSql, Type};
 
 macro_rules! delegate_to_pgtosql {
     ($($($rust_ty: ty),+ => $asp_ty: ty => $pg_ty: ident);+) => {$(
@@ -21,9 +21,12 @@ macro_rules! delegate_to_pgtosql {
                 _metadata: &Option<Type>,
                 collector: &'a mut <Pg as Backend>::BindCollector,
             ) -> AnyResult<<Pg as Backend>::RawValue<'a>> {
-                PgToSql::to_sql(self, &Type::$pg_ty, collector.buffer())?;
+                let buffer = collector.buffer();
 
-                Ok(&[])
+                match PgToSql::to_sql(self, &Type::$pg_ty, buffer)? {
+                    IsNull::Yes => Ok(PgValue::null()),
+                    IsNull::No => Ok(PgValue::new(buffer)),
+                }
             }
         })+
     )+};
@@ -37,7 +40,7 @@ delegate_to_pgtosql! {
     i64 => BigInt => INT8;
     f32 => Float => FLOAT4;
     f64 => Double => FLOAT8;
-    String => Text => TEXT;
+    String, &'_ str => Text => TEXT;
     Vec<u8>, &'_ [u8] => Binary => BYTEA;
     uuid::Uuid => Uuid => UUID
 }
@@ -45,8 +48,8 @@ delegate_to_pgtosql! {
 macro_rules! delegate_to_pgfromsql {
     ($($($rust_ty: ty),+ => $asp_ty: ty => $pg_ty: ident);+) => {$(
         $(impl<'a> FromSql<'a, $asp_ty, Pg> for $rust_ty {
-            fn from_sql(_metadata: &Option<Type>, raw: &'a [u8]) -> AnyResult<Self> {
-                Ok(PgFromSql::from_sql(&Type::$pg_ty, raw)?)
+            fn from_sql(_metadata: &Option<Type>, raw: PgValue<'a>) -> AnyResult<Self> {
+                Ok(PgFromSql::from_sql_nullable(&Type::$pg_ty, raw.as_bytes())?)
             }
         })+
     )+};
@@ -66,13 +69,126 @@ delegate_to_pgfromsql! {
 }
 
 impl<'a> FromSql<'a, Binary, Pg> for &'a [u8] {
-    fn from_sql(_metadata: &Option<Type>, raw: &'a [u8]) -> AnyResult<Self> {
-        Ok(PgFromSql::from_sql(&Type::BYTEA, raw)?)
+    fn from_sql(_metadata: &Option<Type>, raw: PgValue<'a>) -> AnyResult<Self> {
+        Ok(PgFromSql::from_sql_nullable(&Type::BYTEA, raw.as_bytes())?)
     }
 }
 
 impl<'a> FromSql<'a, Text, Pg> for &'a str {
-    fn from_sql(_metadata: &Option<Type>, raw: &'a [u8]) -> AnyResult<Self> {
-        Ok(PgFromSql::from_sql(&Type::TEXT, raw)?)
+    fn from_sql(_metadata: &Option<Type>, raw: PgValue<'a>) -> AnyResult<Self> {
+        Ok(PgFromSql::from_sql_nullable(&Type::TEXT, raw.as_bytes())?)
+    }
+}
+
+/// Can values of the `actual` type be read as values of the `expected` type?
+///
+/// Besides equal types, this accepts the types that share the binary representation of
+/// the expected type, e.g. `VARCHAR` values can be read as `TEXT`.
+pub(crate) fn is_compatible(expected: &Type, actual: &Type) -> bool {
+    if expected == actual {
+        return true;
+    }
+
+    if *expected == Type::TEXT {
+        [Type::VARCHAR, Type::BPCHAR, Type::NAME, Type::UNKNOWN].contains(actual)
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MetadataLookup, PgBindCollector};
+    use asphalt_core::query::BindCollector;
+    use bytes::BytesMut;
+    use futures_util::FutureExt;
+
+    /// Binds `value` as `SqlTy`, returning the bytes sent to the database.
+    fn bind<SqlTy, RustTy>(value: &RustTy) -> Option<Vec<u8>>
+    where
+        Pg: HasSqlType<SqlTy>,
+        RustTy: ToSql<SqlTy, Pg>,
+    {
+        let lookup = MetadataLookup::default();
+        let mut collector = PgBindCollector::default();
+        collector
+            .push_bound_value::<SqlTy, RustTy>(value, &lookup)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+
+        let param = collector.binds().next().unwrap();
+        let mut out = BytesMut::new();
+        match param.to_sql_checked(&Type::TEXT, &mut out).unwrap() {
+            IsNull::Yes => None,
+            IsNull::No => Some(out.to_vec()),
+        }
+    }
+
+    fn value(bytes: &Option<Vec<u8>>) -> PgValue<'_> {
+        match bytes {
+            Some(bytes) => PgValue::new(bytes),
+            None => PgValue::null(),
+        }
+    }
+
+    #[test]
+    fn empty_text_is_not_null() {
+        let bytes = bind::<Text, _>(&String::new());
+        assert_eq!(bytes, Some(vec![]));
+
+        let text: String = FromSql::<Text, Pg>::from_sql(&None, value(&bytes)).unwrap();
+        assert_eq!(text, "");
+    }
+
+    #[test]
+    fn empty_binary_is_not_null() {
+        let bytes = bind::<Binary, _>(&Vec::<u8>::new());
+        assert_eq!(bytes, Some(vec![]));
+
+        let binary: Vec<u8> = FromSql::<Binary, Pg>::from_sql(&None, value(&bytes)).unwrap();
+        assert!(binary.is_empty());
+    }
+
+    #[test]
+    fn nullable_text_round_trips() {
+        for text in &[None, Some(String::new()), Some("asphalt".to_string())] {
+            let bytes = bind::<Nullable<Text>, _>(text);
+            assert_eq!(bytes.is_none(), text.is_none());
+
+            let decoded: Option<String> =
+                FromSql::<Nullable<Text>, Pg>::from_sql(&None, value(&bytes)).unwrap();
+            assert_eq!(&decoded, text);
+        }
+    }
+
+    #[test]
+    fn null_is_rejected_by_non_nullable_types() {
+        let res: AnyResult<String> = FromSql::<Text, Pg>::from_sql(&None, PgValue::null());
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn text_accepts_other_character_types() {
+        for actual in &[
+            Type::TEXT,
+            Type::VARCHAR,
+            Type::BPCHAR,
+            Type::NAME,
+            Type::UNKNOWN,
+        ] {
+            assert!(is_compatible(&Type::TEXT, actual), "{} as TEXT", actual);
+        }
+        assert!(!is_compatible(&Type::TEXT, &Type::BYTEA));
+        assert!(!is_compatible(&Type::VARCHAR, &Type::TEXT));
+    }
+
+    #[test]
+    fn integers_of_other_sizes_are_rejected() {
+        assert!(is_compatible(&Type::INT4, &Type::INT4));
+        assert!(!is_compatible(&Type::INT4, &Type::INT8));
+        assert!(!is_compatible(&Type::INT8, &Type::INT4));
+        assert!(!is_compatible(&Type::INT4, &Type::INT2));
     }
 }
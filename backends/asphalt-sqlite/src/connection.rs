@@ -0,0 +1,335 @@
+use crate::{Sqlite, SqliteValue};
+use asphalt_core::backend::{HasSqlType, TypeMetadata};
+use asphalt_core::connection::{
+    ColumnDescriptor, ColumnTypeMismatch, EstablishResult, RawConnection, Row, RowStream,
+};
+use asphalt_core::error::{AnyResult, QueryResult};
+use asphalt_core::query::Query;
+use asphalt_core::sql::AnsiTransactionManager;
+use asphalt_core::types::FromSql;
+use asphalt_core::LocalBoxFuture;
+use futures_util::FutureExt;
+use parking_lot::Mutex;
+use rusqlite::types::{Value, ValueRef};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Where the database is stored.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Config {
+    /// A temporary in-memory database, private to the connection.
+    Memory,
+    /// A database file, which is created if it doesn't exist.
+    File(PathBuf),
+}
+
+pub struct SqliteRawConnection {
+    pub(crate) inner: Mutex<rusqlite::Connection>,
+    manager: AnsiTransactionManager,
+}
+
+impl SqliteRawConnection {
+    fn connect(config: Config) -> EstablishResult<Self> {
+        let conn = match config {
+            Config::Memory => rusqlite::Connection::open_in_memory()?,
+            Config::File(path) => rusqlite::Connection::open(path)?,
+        };
+
+        // SQLite doesn't enforce foreign keys by default.
+        conn.execute_batch("PRAGMA foreign_keys = ON")?;
+
+        Ok(Self {
+            inner: Mutex::new(conn),
+            manager: AnsiTransactionManager::default(),
+        })
+    }
+}
+
+impl RawConnection for SqliteRawConnection {
+    type Backend = Sqlite;
+    type TransactionManager = AnsiTransactionManager;
+    type Row = SqliteRow;
+    type Config = Config;
+    type EstablishError = rusqlite::Error;
+
+    fn establish(config: Self::Config) -> LocalBoxFuture<'static, EstablishResult<Self>> {
+        Box::pin(async move { Self::connect(config) })
+    }
+
+    fn transaction_manager(&self) -> &Self::TransactionManager {
+        &self.manager
+    }
+
+    fn simple_execute<'s>(&'s self, sql: &'s str) -> LocalBoxFuture<'s, QueryResult<()>> {
+        Box::pin(async move {
+            self.inner
+                .lock()
+                .execute_batch(sql)
+                .map_err(|err| crate::error_to_query_error(err).with_query(sql))
+        })
+    }
+
+    fn execute(&self, query: Query<Self::Backend>) -> LocalBoxFuture<'_, QueryResult<u64>> {
+        Box::pin(async move {
+            let sql = &query.inner.sql;
+            let to_query_error = |err| crate::error_to_query_error(err).with_query(&**sql);
+
+            let conn = self.inner.lock();
+            let mut stmt = conn.prepare_cached(sql).map_err(to_query_error)?;
+            let affected = stmt.execute(query.binds.binds()).map_err(to_query_error)?;
+
+            Ok(affected as u64)
+        })
+    }
+
+    fn query(
+        &self,
+        query: Query<Self::Backend>,
+    ) -> LocalBoxFuture<'_, QueryResult<RowStream<'_, Self>>> {
+        Box::pin(async move {
+            let sql = &query.inner.sql;
+            let to_query_error = |err| crate::error_to_query_error(err).with_query(&**sql);
+
+            let conn = self.inner.lock();
+            let mut stmt = conn.prepare_cached(sql).map_err(to_query_error)?;
+            let columns: Arc<[String]> =
+                stmt.column_names().into_iter().map(String::from).collect();
+
+            // The rows borrow the connection, so they are all read before returning.
+            let mut rows = stmt.query(query.binds.binds()).map_err(to_query_error)?;
+            let mut result = Vec::new();
+            while let Some(row) = rows.next().map_err(to_query_error)? {
+                let values = (0..columns.len())
+                    .map(|idx| Value::from(row.get_raw(idx)))
+                    .collect();
+
+                result.push(Ok(SqliteRow {
+                    columns: columns.clone(),
+                    values,
+                }));
+            }
+
+            Ok(Box::pin(futures_util::stream::iter(result)) as RowStream<'_, Self>)
+        })
+    }
+
+    fn metadata_lookup(&self) -> &<Self::Backend as TypeMetadata>::MetadataLookup {
+        &()
+    }
+}
+
+pub struct SqliteRow {
+    columns: Arc<[String]>,
+    values: Vec<Value>,
+}
+
+impl Row for SqliteRow {
+    type Backend = Sqlite;
+
+    fn n_columns(&self) -> usize {
+        self.values.len()
+    }
+
+    fn column(&self, idx: usize) -> Option<ColumnDescriptor<'_, Sqlite>> {
+        // SQLite is dynamically typed, so the type of the column is the type of
+        // its value in this row.
+        let value = self.values.get(idx)?;
+        Some(ColumnDescriptor::new(&self.columns[idx], value.data_type()))
+    }
+
+    fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|col| col == name)
+    }
+
+    fn get_column<'a, SqlTy, RustTy>(&'a self, idx: usize) -> AnyResult<RustTy>
+    where
+        Self::Backend: HasSqlType<SqlTy>,
+        RustTy: FromSql<'a, SqlTy, Self::Backend>,
+    {
+        let value = self
+            .values
+            .get(idx)
+            .ok_or_else(|| format!("Column index {} out of bounds", idx))?;
+
+        // The metadata of the SQLite types is always available immediately.
+        let expected = <Sqlite as HasSqlType<SqlTy>>::metadata(&()).now_or_never();
+        if let Some(expected) = expected.transpose()? {
+            if !crate::types::is_compatible(&expected, &value.data_type()) {
+                return Err(Box::new(ColumnTypeMismatch {
+                    column: self.columns[idx].clone(),
+                    expected: expected.to_string(),
+                    actual: value.data_type().to_string(),
+                }));
+            }
+        }
+
+        self.get_column_unchecked::<SqlTy, RustTy>(idx)
+    }
+
+    fn get_column_unchecked<'a, SqlTy, RustTy>(&'a self, idx: usize) -> AnyResult<RustTy>
+    where
+        Self::Backend: HasSqlType<SqlTy>,
+        RustTy: FromSql<'a, SqlTy, Self::Backend>,
+    {
+        let value = self
+            .values
+            .get(idx)
+            .ok_or_else(|| format!("Column index {} out of bounds", idx))?;
+
+        RustTy::from_sql(&value.data_type(), SqliteValue(ValueRef::from(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asphalt_core::connection::Connection;
+    use asphalt_core::error::{DatabaseErrorKind, ErrorKind};
+    use asphalt_core::types::{BigInt, Binary, Double, Integer, Nullable, Text};
+    use futures_executor::block_on;
+    use futures_util::TryStreamExt;
+
+    fn connection() -> Connection<Sqlite> {
+        let conn = block_on(Connection::establish(Config::Memory)).unwrap();
+        execute(
+            &conn,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE, bio TEXT)",
+        )
+        .unwrap();
+
+        conn
+    }
+
+    fn execute(conn: &Connection<Sqlite>, sql: &str) -> QueryResult<u64> {
+        let mut query = conn.query_builder();
+        query.push_sql(sql);
+
+        block_on(conn.executes(query))
+    }
+
+    async fn insert_user(
+        conn: &Connection<Sqlite>,
+        name: &str,
+        bio: Option<&str>,
+    ) -> QueryResult<u64> {
+        let mut query = conn.query_builder();
+        query.push_sql("INSERT INTO users (name, bio) VALUES (");
+        query.push_bind_param::<Text, _>(&name).await?;
+        query.push_sql(", ");
+        query.push_bind_param::<Nullable<Text>, _>(&bio).await?;
+        query.push_sql(")");
+
+        conn.executes(query).await
+    }
+
+    fn select(conn: &Connection<Sqlite>, sql: &str) -> Vec<SqliteRow> {
+        let mut query = conn.query_builder();
+        query.push_sql(sql);
+
+        block_on(async { conn.query(query).await?.try_collect().await }).unwrap()
+    }
+
+    #[test]
+    fn round_trips_values() {
+        let conn = connection();
+
+        let row = block_on(async {
+            let mut query = conn.query_builder();
+            query.push_sql("SELECT ");
+            query.push_bind_param::<Integer, _>(&42).await?;
+            query.push_sql(", ");
+            query.push_bind_param::<BigInt, _>(&i64::MAX).await?;
+            query.push_sql(", ");
+            query.push_bind_param::<Double, _>(&1.5).await?;
+            query.push_sql(", ");
+            query.push_bind_param::<Text, _>(&"asphalt").await?;
+            query.push_sql(", ");
+            query.push_bind_param::<Binary, _>(&vec![1u8, 2, 3]).await?;
+
+            let mut rows: Vec<SqliteRow> = conn.query(query).await?.try_collect().await?;
+            QueryResult::Ok(rows.remove(0))
+        })
+        .unwrap();
+
+        assert_eq!(row.get_column::<Integer, i32>(0).unwrap(), 42);
+        assert_eq!(row.get_column::<BigInt, i64>(1).unwrap(), i64::MAX);
+        assert_eq!(row.get_column::<Double, f64>(2).unwrap(), 1.5);
+        assert_eq!(row.get_column::<Text, &str>(3).unwrap(), "asphalt");
+        assert_eq!(row.get_column::<Binary, &[u8]>(4).unwrap(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn distinguishes_null_from_empty_text() {
+        let conn = connection();
+        block_on(insert_user(&conn, "empty", Some(""))).unwrap();
+        block_on(insert_user(&conn, "null", None)).unwrap();
+
+        let rows = select(&conn, "SELECT bio FROM users ORDER BY id");
+        let bios: Vec<Option<String>> = rows
+            .iter()
+            .map(|row| row.get_column_by_name::<Nullable<Text>, _>("bio").unwrap())
+            .collect();
+
+        assert_eq!(bios, vec![Some(String::new()), None]);
+    }
+
+    #[test]
+    fn reports_column_type_mismatches() {
+        let conn = connection();
+        block_on(insert_user(&conn, "asphalt", None)).unwrap();
+
+        let rows = select(&conn, "SELECT name FROM users");
+        let err = rows[0].get_column::<Integer, i32>(0).unwrap_err();
+
+        assert!(err.is::<ColumnTypeMismatch>());
+        assert!(err.to_string().contains("`name`"));
+    }
+
+    #[test]
+    fn maps_constraint_violations() {
+        let conn = connection();
+        block_on(insert_user(&conn, "asphalt", None)).unwrap();
+
+        let err = block_on(insert_user(&conn, "asphalt", None)).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::DatabaseError(DatabaseErrorKind::UniqueViolation, _)
+        ));
+    }
+
+    #[test]
+    fn rolls_back_failed_transactions() {
+        let conn = connection();
+
+        let res: QueryResult<()> = block_on(conn.transaction(async {
+            insert_user(&conn, "asphalt", None).await?;
+            Err(asphalt_core::error::Error::rollback_transaction())
+        }));
+        assert!(res.is_err());
+
+        block_on(conn.transaction(insert_user(&conn, "sqlite", None))).unwrap();
+
+        let rows = select(&conn, "SELECT name FROM users");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column::<Text, &str>(0).unwrap(), "sqlite");
+    }
+
+    #[test]
+    fn persists_file_databases() {
+        let path = std::env::temp_dir().join(format!("asphalt-sqlite-{}.db", std::process::id()));
+        let config = Config::File(path.clone());
+
+        {
+            let conn = block_on(Connection::<Sqlite>::establish(config.clone())).unwrap();
+            execute(&conn, "CREATE TABLE items (id INTEGER PRIMARY KEY)").unwrap();
+            execute(&conn, "INSERT INTO items (id) VALUES (1)").unwrap();
+        }
+
+        let conn = block_on(Connection::<Sqlite>::establish(config)).unwrap();
+        let rows = select(&conn, "SELECT id FROM items");
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column::<Integer, i32>(0).unwrap(), 1);
+    }
+}
@@ -0,0 +1,102 @@
+#![feature(generic_associated_types)]
+//! The SQLite backend of asphalt.
+//!
+//! SQLite is an embedded database, so every operation runs synchronously inside the
+//! returned futures, blocking the executor while the database is accessed. This makes
+//! the backend well suited for tests and small applications.
+use asphalt_core::backend::{Backend, TypeMetadata};
+use asphalt_core::error::{DatabaseErrorKind, Error};
+use asphalt_core::extensions::{self, Supports};
+use asphalt_core::values::RawValue;
+use rusqlite::ffi;
+use rusqlite::types::{Type, ValueRef};
+
+mod connection;
+mod query;
+mod types;
+
+#[doc(inline)]
+pub use self::connection::{Config, SqliteRawConnection, SqliteRow};
+#[doc(inline)]
+pub use self::query::{SqliteBindCollector, SqliteQuery, SqliteQueryWriter};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Sqlite;
+
+impl Backend for Sqlite {
+    type Query = SqliteQuery;
+    type QueryWriter = SqliteQueryWriter;
+    type BindName = usize;
+    type BindCollector = SqliteBindCollector;
+    type RawConnection = SqliteRawConnection;
+    type RawValue<'a> = SqliteValue<'a>;
+}
+
+impl Supports<extensions::Transaction> for Sqlite {}
+
+impl TypeMetadata for Sqlite {
+    // SQLite is dynamically typed, values only have a storage class.
+    type TypeMetadata = Type;
+    type MetadataLookup = ();
+}
+
+/// A raw SQLite value, borrowed from a row or from the value being bound.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SqliteValue<'a>(pub(crate) ValueRef<'a>);
+
+impl<'a> SqliteValue<'a> {
+    /// Returns the underlying `rusqlite` value.
+    pub fn as_value_ref(&self) -> ValueRef<'a> {
+        self.0
+    }
+}
+
+impl<'a> From<ValueRef<'a>> for SqliteValue<'a> {
+    fn from(value: ValueRef<'a>) -> Self {
+        Self(value)
+    }
+}
+
+impl RawValue<Sqlite> for SqliteValue<'_> {
+    fn is_null(&self) -> bool {
+        self.0 == ValueRef::Null
+    }
+
+    fn null_value() -> Self {
+        Self(ValueRef::Null)
+    }
+}
+
+pub(crate) fn error_to_query_error(err: rusqlite::Error) -> Error {
+    let code = match &err {
+        rusqlite::Error::SqliteFailure(code, _) => *code,
+        _ => return Error::database_error(DatabaseErrorKind::Unknown, err.to_string()),
+    };
+
+    // See https://www.sqlite.org/rescode.html
+    let kind = match code.extended_code {
+        ffi::SQLITE_CONSTRAINT_UNIQUE | ffi::SQLITE_CONSTRAINT_PRIMARYKEY => {
+            DatabaseErrorKind::UniqueViolation
+        }
+        ffi::SQLITE_CONSTRAINT_FOREIGNKEY => DatabaseErrorKind::ForeignKeyViolation,
+        ffi::SQLITE_CONSTRAINT_NOTNULL => DatabaseErrorKind::NotNullViolation,
+        ffi::SQLITE_CONSTRAINT_CHECK => DatabaseErrorKind::CheckViolation,
+        // A read transaction couldn't be upgraded because another connection wrote
+        // to the database since it started.
+        ffi::SQLITE_BUSY_SNAPSHOT => DatabaseErrorKind::SerializationFailure,
+        _ => match code.code {
+            ffi::ErrorCode::ReadOnly => DatabaseErrorKind::ReadOnlyTransaction,
+            ffi::ErrorCode::OperationInterrupted => DatabaseErrorKind::QueryCanceled,
+            ffi::ErrorCode::SchemaChanged => DatabaseErrorKind::StalePreparedStatement,
+            ffi::ErrorCode::TypeMismatch
+            | ffi::ErrorCode::TooBig
+            | ffi::ErrorCode::ParameterOutOfRange => DatabaseErrorKind::InvalidInput,
+            ffi::ErrorCode::CannotOpen
+            | ffi::ErrorCode::SystemIOFailure
+            | ffi::ErrorCode::NotADatabase => DatabaseErrorKind::ConnectionFailure,
+            _ => DatabaseErrorKind::Unknown,
+        },
+    };
+
+    Error::database_error(kind, err.to_string())
+}
@@ -0,0 +1,164 @@
+use crate::{Sqlite, SqliteValue};
+use asphalt_core::backend::{Backend, HasSqlType, TypeMetadata};
+use asphalt_core::error::{Error, QueryResult};
+use asphalt_core::query::{BindCollector, DebugBind, PreparableQuery, QueryWriter};
+use asphalt_core::types::ToSql;
+use asphalt_core::LocalBoxFuture;
+use rusqlite::types::{Type, Value};
+
+pub struct SqliteQuery {
+    pub(crate) sql: String,
+    prepared: bool,
+}
+
+impl PreparableQuery<Sqlite> for SqliteQuery {
+    // `rusqlite` statements borrow the connection, so they can't be stored outside
+    // of it. Instead, statements are kept in the `rusqlite` statement cache, and the
+    // SQL is used to find them.
+    type Prepared = String;
+    type CacheKey = String;
+
+    fn prepare(
+        self,
+        conn: &<Sqlite as Backend>::RawConnection,
+    ) -> LocalBoxFuture<'_, QueryResult<Self::Prepared>> {
+        Box::pin(async move {
+            if !self.prepared {
+                conn.inner
+                    .lock()
+                    .prepare_cached(&self.sql)
+                    .map_err(|err| crate::error_to_query_error(err).with_query(&*self.sql))?;
+            }
+
+            Ok(self.sql)
+        })
+    }
+
+    fn from_prepared(prepared: Self::Prepared) -> Self {
+        Self {
+            sql: prepared,
+            prepared: true,
+        }
+    }
+
+    fn cache_key(&self) -> Option<Self::CacheKey> {
+        if self.prepared {
+            None
+        } else {
+            Some(self.sql.clone())
+        }
+    }
+}
+
+/// The `QueryWriter` for the `Sqlite` backend.
+#[derive(Default)]
+pub struct SqliteQueryWriter {
+    query: String,
+}
+
+impl QueryWriter<Sqlite> for SqliteQueryWriter {
+    fn push_sql(&mut self, sql: &str) {
+        self.query.push_str(sql);
+    }
+
+    fn push_identifier(&mut self, identifier: &str) {
+        self.query.reserve(2 + identifier.len());
+        self.query.push('"');
+        self.query.push_str(&identifier.replace('"', "\"\""));
+        self.query.push('"');
+    }
+
+    fn push_bind_param(&mut self, bind: &<Sqlite as Backend>::BindName) {
+        use std::fmt::Write;
+        // Numbered placeholders allow the same parameter to be referenced many times.
+        // Writing to memory never fails.
+        write!(&mut self.query, "?{}", bind).unwrap();
+    }
+
+    fn sql(&self) -> &str {
+        &self.query
+    }
+
+    fn finish(self) -> <Sqlite as Backend>::Query {
+        SqliteQuery {
+            sql: self.query,
+            prepared: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SqliteBindCollector {
+    binds: Vec<Value>,
+    /// The type of each parameter, used only for debugging.
+    types: Vec<Type>,
+}
+
+impl SqliteBindCollector {
+    pub(crate) fn binds(&self) -> &[Value] {
+        &self.binds
+    }
+}
+
+impl BindCollector<Sqlite> for SqliteBindCollector {
+    fn push_bound_value<'a, SqlTy, RustTy>(
+        &'a mut self,
+        bind: &'a RustTy,
+        metadata_lookup: &'a <Sqlite as TypeMetadata>::MetadataLookup,
+    ) -> LocalBoxFuture<'a, QueryResult<<Sqlite as Backend>::BindName>>
+    where
+        Sqlite: HasSqlType<SqlTy>,
+        RustTy: ToSql<SqlTy, Sqlite> + ?Sized,
+    {
+        Box::pin(async move {
+            let metadata = <Sqlite as HasSqlType<SqlTy>>::metadata(metadata_lookup).await?;
+            let SqliteValue(value) = bind
+                .to_sql(&metadata, self)
+                .map_err(Error::serialization_failure)?;
+            let value = Value::from(value);
+
+            self.binds.push(value);
+            self.types.push(metadata);
+
+            Ok(self.binds.len())
+        })
+    }
+
+    fn bind_count(&self) -> usize {
+        self.binds.len()
+    }
+
+    fn debug_binds(&self) -> Vec<DebugBind> {
+        self.binds
+            .iter()
+            .zip(&self.types)
+            .map(|(value, ty)| DebugBind {
+                sql_type: ty.to_string(),
+                literal: debug_literal(value),
+            })
+            .collect()
+    }
+}
+
+/// Renders a value as a SQL literal.
+fn debug_literal(value: &Value) -> String {
+    use std::fmt::Write;
+
+    match value {
+        Value::Null => String::from("NULL"),
+        Value::Integer(value) => value.to_string(),
+        Value::Real(value) => value.to_string(),
+        Value::Text(value) => format!("'{}'", value.replace('\'', "''")),
+        Value::Blob(value) => {
+            let mut literal = String::with_capacity(3 + 2 * value.len());
+            literal.push_str("X'");
+            for byte in value {
+                // Writing to memory never fails.
+                write!(&mut literal, "{:02X}", byte).unwrap();
+            }
+            literal.push('\'');
+
+            literal
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{Sqlite, SqliteValue};
+use asphalt_core::backend::{Backend, HasSqlType};
+use asphalt_core::error::{AnyResult, QueryResult};
+use asphalt_core::types::*;
+use asphalt_core::LocalBoxFuture;
+use rusqlite::types::{FromSql as SqliteFromSql, Type, ValueRef};
+
+macro_rules! sqlite_types {
+    ($($asp_ty: ty => $sqlite_ty: ident),+) => {$(
+        impl HasSqlType<$asp_ty> for Sqlite {
+            fn metadata(
+                _: &Self::MetadataLookup,
+            ) -> LocalBoxFuture<'_, QueryResult<Self::TypeMetadata>> {
+                Box::pin(async move { Ok(Type::$sqlite_ty) })
+            }
+        }
+    )+};
+}
+
+sqlite_types! {
+    Bool => Integer,
+    TinyInt => Integer,
+    SmallInt => Integer,
+    Integer => Integer,
+    BigInt => Integer,
+    Float => Real,
+    Double => Real,
+    Text => Text,
+    Binary => Blob
+}
+
+macro_rules! sqlite_to_sql {
+    ($($rust_ty: ty => $asp_ty: ty => |$value: ident| $to_value: expr);+) => {$(
+        impl ToSql<$asp_ty, Sqlite> for $rust_ty {
+            fn to_sql<'a>(
+                &'a self,
+                _metadata: &Type,
+                _collector: &'a mut <Sqlite as Backend>::BindCollector,
+            ) -> AnyResult<<Sqlite as Backend>::RawValue<'a>> {
+                let $value = self;
+                Ok(SqliteValue($to_value))
+            }
+        }
+    )+};
+}
+
+sqlite_to_sql! {
+    bool => Bool => |value| ValueRef::Integer(i64::from(*value));
+    i8 => TinyInt => |value| ValueRef::Integer(i64::from(*value));
+    i16 => SmallInt => |value| ValueRef::Integer(i64::from(*value));
+    i32 => Integer => |value| ValueRef::Integer(i64::from(*value));
+    i64 => BigInt => |value| ValueRef::Integer(*value);
+    f32 => Float => |value| ValueRef::Real(f64::from(*value));
+    f64 => Double => |value| ValueRef::Real(*value);
+    String => Text => |value| ValueRef::Text(value.as_bytes());
+    &'_ str => Text => |value| ValueRef::Text(value.as_bytes());
+    Vec<u8> => Binary => |value| ValueRef::Blob(value);
+    &'_ [u8] => Binary => |value| ValueRef::Blob(value)
+}
+
+macro_rules! delegate_to_sqlitefromsql {
+    ($($rust_ty: ty => $asp_ty: ty),+) => {$(
+        impl<'a> FromSql<'a, $asp_ty, Sqlite> for $rust_ty {
+            fn from_sql(_metadata: &Type, raw: SqliteValue<'a>) -> AnyResult<Self> {
+                Ok(SqliteFromSql::column_result(raw.0)?)
+            }
+        }
+    )+};
+}
+
+delegate_to_sqlitefromsql! {
+    bool => Bool,
+    i8 => TinyInt,
+    i16 => SmallInt,
+    i32 => Integer,
+    i64 => BigInt,
+    f64 => Double,
+    String => Text,
+    Vec<u8> => Binary
+}
+
+impl<'a> FromSql<'a, Float, Sqlite> for f32 {
+    fn from_sql(_metadata: &Type, raw: SqliteValue<'a>) -> AnyResult<Self> {
+        // SQLite stores all floating point numbers with double precision.
+        Ok(f64::column_result(raw.0)? as f32)
+    }
+}
+
+impl<'a> FromSql<'a, Text, Sqlite> for &'a str {
+    fn from_sql(_metadata: &Type, raw: SqliteValue<'a>) -> AnyResult<Self> {
+        Ok(raw.0.as_str()?)
+    }
+}
+
+impl<'a> FromSql<'a, Binary, Sqlite> for &'a [u8] {
+    fn from_sql(_metadata: &Type, raw: SqliteValue<'a>) -> AnyResult<Self> {
+        Ok(raw.0.as_blob()?)
+    }
+}
+
+/// Can values stored as `actual` be read as values of the `expected` type?
+///
+/// `NULL`s are handled by the nullable types, and integers are accepted as
+/// floating point numbers, as SQLite stores integral `REAL` values as integers.
+pub(crate) fn is_compatible(expected: &Type, actual: &Type) -> bool {
+    expected == actual
+        || *actual == Type::Null
+        || (*expected == Type::Real && *actual == Type::Integer)
+}